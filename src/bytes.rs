@@ -1,6 +1,6 @@
 use core::{fmt, fmt::Debug};
 use std::{
-    borrow::Cow,
+    borrow::{Borrow, Cow},
     hash::{Hash, Hasher},
     marker::PhantomData,
     mem::ManuallyDrop,
@@ -17,20 +17,34 @@ pub struct Bytes<'a> {
     _lt: PhantomData<&'a [u8]>,
 }
 
+/// The type that is used for the length of [`Bytes`]
+#[cfg(not(feature = "span64"))]
+type Length = u32;
+/// The type that is used for the length of [`Bytes`]
+#[cfg(feature = "span64")]
+type Length = u64;
+
+/// Checks whether the given length fits in the length of [`Bytes`]
+#[inline]
+pub(crate) fn is_valid_length(len: usize) -> bool {
+    Length::try_from(len).is_ok()
+}
+
 /// The inner data of [`Bytes`]
 ///
 /// Instead of using `&[u8]` and `Vec<u8>` for the variants,
 /// we use raw pointers and a `u32` for the length.
 /// This is to keep the size of the enum to 16 (on 64-bit machines),
-/// which is the same as if this was just `struct Bytes<'a>(&'a [u8])`
+/// which is the same as if this was just `struct Bytes<'a>(&'a [u8])`.
+/// With the `span64` feature, the length is a `u64` instead, which makes the enum 24 bytes large.
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum BytesInner {
     /// Borrowed bytes
-    Borrowed(*const u8, u32),
+    Borrowed(*const u8, Length),
     /// Owned bytes
     ///
     /// This pointer is managed and will be freed when dropped
-    Owned(*mut u8, u32),
+    Owned(*mut u8, Length),
 }
 
 impl<'a> PartialEq<str> for Bytes<'a> {
@@ -65,6 +79,15 @@ impl<'a> Hash for Bytes<'a> {
     }
 }
 
+// `Hash` and `Eq` are implemented in terms of the referenced bytes,
+// which allows looking up `Bytes` keys in maps by a plain byte slice
+impl<'a> Borrow<[u8]> for Bytes<'a> {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
 impl<'a> Clone for Bytes<'a> {
     fn clone(&self) -> Self {
         // It is important to manually implement Clone for Bytes,
@@ -98,7 +121,7 @@ impl<'a> From<&'a [u8]> for Bytes<'a> {
     #[inline]
     fn from(s: &'a [u8]) -> Self {
         Bytes {
-            data: BytesInner::Borrowed(s.as_ptr(), s.len() as u32),
+            data: BytesInner::Borrowed(s.as_ptr(), s.len() as Length),
             _lt: PhantomData,
         }
     }
@@ -117,25 +140,25 @@ impl TryFrom<String> for Bytes<'static> {
 
 /// Converts `Bytes` raw parts to a slice
 #[inline]
-unsafe fn compact_bytes_to_slice<'a>(ptr: *const u8, l: u32) -> &'a [u8] {
+unsafe fn compact_bytes_to_slice<'a>(ptr: *const u8, l: Length) -> &'a [u8] {
     std::slice::from_raw_parts(ptr, l as usize)
 }
 
 /// Converts a boxed byte slice to compact raw parts
 ///
-/// The caller is responsible for freeing the returned pointer and that the length of the slice does not overflow a u32!
-unsafe fn boxed_slice_into_compact_parts(slice: Box<[u8]>) -> (*mut u8, u32) {
+/// The caller is responsible for freeing the returned pointer and that the length of the slice does not overflow a `Length`!
+unsafe fn boxed_slice_into_compact_parts(slice: Box<[u8]>) -> (*mut u8, Length) {
     // wrap box in `ManuallyDrop` so it's not dropped at the end of the scope
     let mut slice = ManuallyDrop::new(slice);
     let len = slice.len();
     let ptr = slice.as_mut_ptr();
 
-    (ptr, len as u32)
+    (ptr, len as Length)
 }
 
 /// Clones a slice given its raw parts and returns the new, cloned parts
 #[inline]
-unsafe fn clone_compact_bytes_parts(ptr: *mut u8, len: u32) -> (*mut u8, u32) {
+unsafe fn clone_compact_bytes_parts(ptr: *mut u8, len: Length) -> (*mut u8, Length) {
     let slice = compact_bytes_to_slice(ptr, len).to_vec().into_boxed_slice();
     boxed_slice_into_compact_parts(slice)
 }
@@ -198,6 +221,22 @@ impl<'a> Bytes<'a> {
         }
     }
 
+    /// Points borrowed bytes into `to` if they borrow from `from`, at the same offset
+    ///
+    /// This is used when the buffer that the bytes borrow from has been moved to another allocation.
+    ///
+    /// ## Safety
+    /// `to` must contain the same data as `from` and outlive `'a`.
+    pub(crate) unsafe fn rebase(&mut self, from: &[u8], to: *const u8) {
+        if let BytesInner::Borrowed(ptr, _) = &mut self.data {
+            let offset = (*ptr as usize).wrapping_sub(from.as_ptr() as usize);
+
+            if offset <= from.len() {
+                *ptr = to.add(offset);
+            }
+        }
+    }
+
     /// Returns a read-only raw pointer to the inner data
     #[inline]
     pub fn as_ptr(&self) -> *const u8 {
@@ -207,13 +246,35 @@ impl<'a> Bytes<'a> {
         }
     }
 
+    /// Converts this into a `Bytes` that owns its data and no longer borrows from the input
+    ///
+    /// This does not allocate if the data is already owned.
+    pub fn into_owned(self) -> Bytes<'static> {
+        // the data is either copied or moved into the returned `Bytes`, so `self` must not be dropped
+        let this = ManuallyDrop::new(self);
+
+        let data = match this.data {
+            BytesInner::Borrowed(ptr, len) => {
+                let slice = unsafe { compact_bytes_to_slice(ptr, len) }
+                    .to_vec()
+                    .into_boxed_slice();
+                let (ptr, len) = unsafe { boxed_slice_into_compact_parts(slice) };
+                BytesInner::Owned(ptr, len)
+            }
+            BytesInner::Owned(ptr, len) => BytesInner::Owned(ptr, len),
+        };
+
+        Bytes {
+            data,
+            _lt: PhantomData,
+        }
+    }
+
     /// Sets the inner data to the given data and returns the old bytes
     pub fn set<B: IntoOwnedBytes>(&mut self, data: B) -> Result<Option<Box<[u8]>>, SetBytesError> {
-        const MAX: usize = u32::MAX as usize;
-
         let data = <B as IntoOwnedBytes>::into_bytes(data);
 
-        if data.len() > MAX {
+        if !is_valid_length(data.len()) {
             return Err(SetBytesError::LengthOverflow);
         }
 
@@ -224,7 +285,7 @@ impl<'a> Bytes<'a> {
     /// Sets the inner data to the given data without checking for validity of the data
     ///
     /// ## Safety
-    /// - Once `data` is converted to a `Box<[u8]>`, its length must not be greater than u32::MAX
+    /// - Once `data` is converted to a `Box<[u8]>`, its length must not be greater than u32::MAX (or u64::MAX with the `span64` feature)
     #[inline]
     pub unsafe fn set_unchecked<B: IntoOwnedBytes>(&mut self, data: B) -> Option<Box<[u8]>> {
         let data = <B as IntoOwnedBytes>::into_bytes(data);
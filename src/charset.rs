@@ -0,0 +1,591 @@
+use std::borrow::Cow;
+
+use backend::Encoding;
+
+/// Number of bytes that are scanned for a `<meta>` charset declaration
+const PRESCAN_LIMIT: usize = 1024;
+
+/// Determines the encoding of an HTML document and decodes it
+///
+/// The encoding is determined in this order, following the HTML specification:
+/// 1. A byte order mark
+/// 2. The transport layer hint, if it is a supported encoding label
+/// 3. A `<meta charset>` or `<meta http-equiv="content-type">` declaration in the first 1024 bytes
+/// 4. UTF-8 if the input is valid UTF-8, and windows-1252 otherwise
+///
+/// Returns the decoded text and the name of the encoding that was used.
+pub(crate) fn decode<'a>(input: &'a [u8], hint: Option<&str>) -> (Cow<'a, str>, &'static str) {
+    let hint = hint.and_then(|hint| backend::for_label(hint.as_bytes()));
+    let (encoding, bom) = determine(input, hint, true).unwrap_or((backend::UTF_8, 0));
+
+    (
+        backend::decode(encoding, &input[bom..]),
+        backend::name(encoding),
+    )
+}
+
+/// Determines the encoding from the start of the input, and returns it with the length of the byte order mark
+///
+/// Returns `None` if more input is needed. Unless `last` is set, the input is assumed to continue,
+/// and UTF-8 is chosen over windows-1252 if the first 1024 bytes are valid UTF-8.
+fn determine(input: &[u8], hint: Option<Encoding>, last: bool) -> Option<(Encoding, usize)> {
+    if let Some(bom) = sniff_bom(input) {
+        return Some(bom);
+    }
+
+    if input.len() < 3 && !last {
+        // the input may still start with a byte order mark
+        return None;
+    }
+
+    if let Some(hint) = hint {
+        return Some((hint, 0));
+    }
+
+    if input.len() < PRESCAN_LIMIT && !last {
+        return None;
+    }
+
+    let head = &input[..input.len().min(PRESCAN_LIMIT)];
+    let encoding = prescan(head).unwrap_or_else(|| {
+        let is_utf8 = match std::str::from_utf8(if last { input } else { head }) {
+            Ok(_) => true,
+            // a character may be cut off at the end of the first 1024 bytes
+            Err(error) => !last && error.error_len().is_none(),
+        };
+
+        if is_utf8 {
+            backend::UTF_8
+        } else {
+            backend::WINDOWS_1252
+        }
+    });
+
+    Some((encoding, 0))
+}
+
+/// Determines the encoding of an HTML document that arrives in chunks, and decodes it
+///
+/// The encoding is determined like in [`decode()`] once the first 1024 bytes have been received,
+/// except that only those bytes are checked to choose between UTF-8 and windows-1252.
+#[derive(Debug)]
+pub(crate) struct StreamDecoder {
+    hint: Option<Encoding>,
+    /// The start of the input, which is kept until the encoding is determined
+    pending: Vec<u8>,
+    decoder: Option<(backend::Decoder, Encoding)>,
+}
+
+impl StreamDecoder {
+    pub(crate) fn new(hint: Option<&str>) -> Self {
+        Self {
+            hint: hint.and_then(|hint| backend::for_label(hint.as_bytes())),
+            pending: Vec::new(),
+            decoder: None,
+        }
+    }
+
+    /// Decodes a chunk of input and appends the text to `out`
+    ///
+    /// Bytes that do not form a complete character are kept until the next chunk, or replaced with U+FFFD if `last` is set.
+    pub(crate) fn decode(&mut self, input: &[u8], last: bool, out: &mut String) {
+        if let Some((decoder, _)) = &mut self.decoder {
+            decoder.decode(input, last, out);
+            return;
+        }
+
+        self.pending.extend_from_slice(input);
+
+        if let Some((encoding, bom)) = determine(&self.pending, self.hint, last) {
+            let mut decoder = backend::Decoder::new(encoding);
+            decoder.decode(&self.pending[bom..], last, out);
+
+            self.pending = Vec::new();
+            self.decoder = Some((decoder, encoding));
+        }
+    }
+
+    /// Returns the name of the encoding, once it has been determined
+    pub(crate) fn encoding(&self) -> Option<&'static str> {
+        self.decoder
+            .as_ref()
+            .map(|&(_, encoding)| backend::name(encoding))
+    }
+}
+
+/// Returns the encoding that is indicated by a byte order mark, and the length of the byte order mark
+fn sniff_bom(input: &[u8]) -> Option<(Encoding, usize)> {
+    match input {
+        [0xEF, 0xBB, 0xBF, ..] => Some((backend::UTF_8, 3)),
+        [0xFE, 0xFF, ..] => Some((backend::UTF_16BE, 2)),
+        [0xFF, 0xFE, ..] => Some((backend::UTF_16LE, 2)),
+        _ => None,
+    }
+}
+
+#[inline]
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, 0x09 | 0x0A | 0x0C | 0x0D | 0x20)
+}
+
+/// Checks whether `input` starts with `prefix`, ignoring ASCII case
+fn starts_with_ignore_case(input: &[u8], prefix: &[u8]) -> bool {
+    input.len() >= prefix.len() && input[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+/// Scans the start of a document for a `<meta>` charset declaration
+///
+/// This implements the "prescan a byte stream to determine its encoding" algorithm of the HTML specification.
+fn prescan(input: &[u8]) -> Option<Encoding> {
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let rest = &input[pos..];
+
+        if rest.starts_with(b"<!--") {
+            // the end of the comment may overlap with its start (`<!-->`)
+            pos += rest[2..]
+                .windows(3)
+                .position(|w| w == b"-->")
+                .map_or(rest.len(), |idx| idx + 4);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|&b| is_whitespace(b) || b == b'/')
+        {
+            pos += 5;
+
+            if let Some(encoding) = prescan_meta(input, &mut pos) {
+                return Some(encoding);
+            }
+        } else if is_tag_start(rest) {
+            // skip the tag name and its attributes
+            pos += rest
+                .iter()
+                .position(|&b| is_whitespace(b) || b == b'>')
+                .unwrap_or(rest.len());
+
+            while get_attribute(input, &mut pos).is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            pos += rest.iter().position(|&b| b == b'>').unwrap_or(rest.len());
+        }
+
+        pos += 1;
+    }
+
+    None
+}
+
+/// Checks whether `input` starts with a start or end tag (`<` or `</`, followed by an ASCII letter)
+fn is_tag_start(input: &[u8]) -> bool {
+    match input {
+        [b'<', b'/', c, ..] | [b'<', c, ..] => c.is_ascii_alphabetic(),
+        _ => false,
+    }
+}
+
+/// Processes the attributes of a `<meta>` element during the prescan
+fn prescan_meta(input: &[u8], pos: &mut usize) -> Option<Encoding> {
+    let mut seen: Vec<Vec<u8>> = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Some((name, value)) = get_attribute(input, pos) {
+        if seen.contains(&name) {
+            continue;
+        }
+
+        match name.as_slice() {
+            b"http-equiv" if value == b"content-type" => got_pragma = true,
+            b"content" if charset.is_none() => {
+                if let Some(encoding) = extract_charset(&value).and_then(backend::for_label) {
+                    charset = Some(encoding);
+                    need_pragma = Some(true);
+                }
+            }
+            b"charset" => {
+                charset = backend::for_label(&value);
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+
+        seen.push(name);
+    }
+
+    match need_pragma {
+        None => return None,
+        Some(true) if !got_pragma => return None,
+        _ => {}
+    }
+
+    charset.map(|charset| {
+        // a document that contains a meta declaration cannot be UTF-16 encoded
+        if charset == backend::UTF_16BE || charset == backend::UTF_16LE {
+            backend::UTF_8
+        } else {
+            backend::replace_user_defined(charset)
+        }
+    })
+}
+
+/// Reads the next attribute of a tag during the prescan
+///
+/// Names are lowercased, and values are lowercased and unquoted.
+/// Returns `None` once the end of the tag is reached.
+fn get_attribute(input: &[u8], pos: &mut usize) -> Option<(Vec<u8>, Vec<u8>)> {
+    let current = |pos: &usize| input.get(*pos).copied();
+
+    while current(pos).is_some_and(|b| is_whitespace(b) || b == b'/') {
+        *pos += 1;
+    }
+
+    if current(pos)? == b'>' {
+        return None;
+    }
+
+    let mut name = Vec::new();
+    let mut value = Vec::new();
+
+    // attribute name
+    loop {
+        match current(pos)? {
+            b'=' if !name.is_empty() => break,
+            b if is_whitespace(b) => {
+                while current(pos).is_some_and(is_whitespace) {
+                    *pos += 1;
+                }
+
+                if current(pos)? != b'=' {
+                    return Some((name, value));
+                }
+
+                break;
+            }
+            b'/' | b'>' => return Some((name, value)),
+            b => name.push(b.to_ascii_lowercase()),
+        }
+
+        *pos += 1;
+    }
+
+    // skip the `=`
+    *pos += 1;
+
+    while current(pos).is_some_and(is_whitespace) {
+        *pos += 1;
+    }
+
+    // attribute value
+    match current(pos)? {
+        quote @ (b'"' | b'\'') => loop {
+            *pos += 1;
+
+            match current(pos)? {
+                b if b == quote => {
+                    *pos += 1;
+                    return Some((name, value));
+                }
+                b => value.push(b.to_ascii_lowercase()),
+            }
+        },
+        b'>' => Some((name, value)),
+        _ => {
+            while let Some(b) = current(pos) {
+                if is_whitespace(b) || b == b'>' {
+                    break;
+                }
+
+                value.push(b.to_ascii_lowercase());
+                *pos += 1;
+            }
+
+            Some((name, value))
+        }
+    }
+}
+
+/// Extracts the charset label from the value of a `content` attribute (i.e. `text/html; charset=utf-8`)
+///
+/// This implements the "algorithm for extracting a character encoding from a meta element" of the HTML specification.
+fn extract_charset(content: &[u8]) -> Option<&[u8]> {
+    let mut pos = 0;
+
+    loop {
+        let idx = content[pos..]
+            .windows(7)
+            .position(|w| w.eq_ignore_ascii_case(b"charset"))?;
+
+        pos += idx + 7;
+
+        while content.get(pos).copied().is_some_and(is_whitespace) {
+            pos += 1;
+        }
+
+        if content.get(pos) == Some(&b'=') {
+            pos += 1;
+            break;
+        }
+    }
+
+    while content.get(pos).copied().is_some_and(is_whitespace) {
+        pos += 1;
+    }
+
+    let rest = &content[pos..];
+
+    match rest.first()? {
+        &quote @ (b'"' | b'\'') => {
+            let end = rest[1..].iter().position(|&b| b == quote)?;
+            Some(&rest[1..end + 1])
+        }
+        _ => {
+            let end = rest
+                .iter()
+                .position(|&b| is_whitespace(b) || b == b';')
+                .unwrap_or(rest.len());
+            Some(&rest[..end])
+        }
+    }
+}
+
+#[cfg(feature = "encoding")]
+mod backend {
+    use std::borrow::Cow;
+
+    pub type Encoding = &'static encoding_rs::Encoding;
+
+    pub const UTF_8: Encoding = encoding_rs::UTF_8;
+    pub const UTF_16BE: Encoding = encoding_rs::UTF_16BE;
+    pub const UTF_16LE: Encoding = encoding_rs::UTF_16LE;
+    pub const WINDOWS_1252: Encoding = encoding_rs::WINDOWS_1252;
+
+    pub fn for_label(label: &[u8]) -> Option<Encoding> {
+        encoding_rs::Encoding::for_label(label)
+    }
+
+    pub fn replace_user_defined(encoding: Encoding) -> Encoding {
+        if encoding == encoding_rs::X_USER_DEFINED {
+            WINDOWS_1252
+        } else {
+            encoding
+        }
+    }
+
+    pub fn name(encoding: Encoding) -> &'static str {
+        encoding.name()
+    }
+
+    pub fn decode(encoding: Encoding, input: &[u8]) -> Cow<'_, str> {
+        encoding.decode_without_bom_handling(input).0
+    }
+
+    #[derive(Debug)]
+    pub struct Decoder(encoding_rs::Decoder);
+
+    impl Decoder {
+        pub fn new(encoding: Encoding) -> Self {
+            Self(encoding.new_decoder_without_bom_handling())
+        }
+
+        pub fn decode(&mut self, mut input: &[u8], last: bool, out: &mut String) {
+            loop {
+                let needed = self.0.max_utf8_buffer_length(input.len());
+                out.reserve(needed.unwrap_or(input.len()));
+
+                let (result, read, _) = self.0.decode_to_string(input, out, last);
+                input = &input[read..];
+
+                if result == encoding_rs::CoderResult::InputEmpty {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Without the `encoding` feature, only UTF-8, UTF-16 and windows-1252 (which includes ISO-8859-1 and ASCII) are supported.
+/// Labels of other encodings are ignored.
+#[cfg(not(feature = "encoding"))]
+mod backend {
+    use std::borrow::Cow;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum Encoding {
+        Utf8,
+        Utf16Be,
+        Utf16Le,
+        Windows1252,
+    }
+
+    pub const UTF_8: Encoding = Encoding::Utf8;
+    pub const UTF_16BE: Encoding = Encoding::Utf16Be;
+    pub const UTF_16LE: Encoding = Encoding::Utf16Le;
+    pub const WINDOWS_1252: Encoding = Encoding::Windows1252;
+
+    /// Labels of the supported encodings, as defined by the WHATWG Encoding Standard
+    static LABELS: &[(&str, Encoding)] = &[
+        ("unicode-1-1-utf-8", UTF_8),
+        ("unicode11utf8", UTF_8),
+        ("unicode20utf8", UTF_8),
+        ("utf-8", UTF_8),
+        ("utf8", UTF_8),
+        ("x-unicode20utf8", UTF_8),
+        ("unicodefffe", UTF_16BE),
+        ("utf-16be", UTF_16BE),
+        ("csunicode", UTF_16LE),
+        ("iso-10646-ucs-2", UTF_16LE),
+        ("ucs-2", UTF_16LE),
+        ("unicode", UTF_16LE),
+        ("unicodefeff", UTF_16LE),
+        ("utf-16", UTF_16LE),
+        ("utf-16le", UTF_16LE),
+        ("ansi_x3.4-1968", WINDOWS_1252),
+        ("ascii", WINDOWS_1252),
+        ("cp1252", WINDOWS_1252),
+        ("cp819", WINDOWS_1252),
+        ("csisolatin1", WINDOWS_1252),
+        ("ibm819", WINDOWS_1252),
+        ("iso-8859-1", WINDOWS_1252),
+        ("iso-ir-100", WINDOWS_1252),
+        ("iso8859-1", WINDOWS_1252),
+        ("iso88591", WINDOWS_1252),
+        ("iso_8859-1", WINDOWS_1252),
+        ("iso_8859-1:1987", WINDOWS_1252),
+        ("l1", WINDOWS_1252),
+        ("latin1", WINDOWS_1252),
+        ("us-ascii", WINDOWS_1252),
+        ("windows-1252", WINDOWS_1252),
+        ("x-cp1252", WINDOWS_1252),
+    ];
+
+    pub fn for_label(label: &[u8]) -> Option<Encoding> {
+        let label = label.trim_ascii();
+
+        LABELS
+            .iter()
+            .find(|(l, _)| l.as_bytes().eq_ignore_ascii_case(label))
+            .map(|&(_, encoding)| encoding)
+    }
+
+    pub fn replace_user_defined(encoding: Encoding) -> Encoding {
+        // x-user-defined is not supported, so its label is never recognized
+        encoding
+    }
+
+    pub fn name(encoding: Encoding) -> &'static str {
+        match encoding {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    pub fn decode(encoding: Encoding, input: &[u8]) -> Cow<'_, str> {
+        match encoding {
+            Encoding::Utf8 => String::from_utf8_lossy(input),
+            Encoding::Utf16Be => decode_utf16(input, u16::from_be_bytes),
+            Encoding::Utf16Le => decode_utf16(input, u16::from_le_bytes),
+            Encoding::Windows1252 => {
+                if input.is_ascii() {
+                    // SAFETY: ASCII is valid UTF-8
+                    return Cow::Borrowed(unsafe { std::str::from_utf8_unchecked(input) });
+                }
+
+                Cow::Owned(
+                    input
+                        .iter()
+                        .map(|&b| match b {
+                            0x80..=0x9F => crate::entities::C1_REPLACEMENTS[(b - 0x80) as usize],
+                            _ => char::from(b),
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Decodes input in chunks by keeping incomplete characters until the next chunk
+    #[derive(Debug)]
+    pub struct Decoder {
+        encoding: Encoding,
+        pending: Vec<u8>,
+    }
+
+    impl Decoder {
+        pub fn new(encoding: Encoding) -> Self {
+            Self {
+                encoding,
+                pending: Vec::new(),
+            }
+        }
+
+        pub fn decode(&mut self, input: &[u8], last: bool, out: &mut String) {
+            self.pending.extend_from_slice(input);
+
+            let complete = if last {
+                self.pending.len()
+            } else {
+                self.pending.len() - self.incomplete_suffix()
+            };
+
+            out.push_str(&decode(self.encoding, &self.pending[..complete]));
+            self.pending.drain(..complete);
+        }
+
+        /// Returns the number of bytes at the end of the pending input that may be completed by the next chunk
+        fn incomplete_suffix(&self) -> usize {
+            let input = self.pending.as_slice();
+
+            match self.encoding {
+                Encoding::Utf8 => {
+                    // find the start of the last character, which is at most 3 bytes from the end if it is incomplete
+                    let Some(start) = (1..=input.len().min(3))
+                        .map(|len| input.len() - len)
+                        .find(|&idx| input[idx] & 0xC0 != 0x80)
+                    else {
+                        return 0;
+                    };
+
+                    match std::str::from_utf8(&input[start..]) {
+                        Err(error) if error.error_len().is_none() => input.len() - start,
+                        _ => 0,
+                    }
+                }
+                Encoding::Utf16Be | Encoding::Utf16Le => {
+                    let odd = input.len() % 2;
+                    let units = &input[..input.len() - odd];
+
+                    // a high surrogate is completed by the next code unit
+                    let high_surrogate = units.rchunks_exact(2).next().is_some_and(|unit| {
+                        let unit = [unit[0], unit[1]];
+                        let unit = match self.encoding {
+                            Encoding::Utf16Be => u16::from_be_bytes(unit),
+                            _ => u16::from_le_bytes(unit),
+                        };
+
+                        (0xD800..0xDC00).contains(&unit)
+                    });
+
+                    odd + if high_surrogate { 2 } else { 0 }
+                }
+                Encoding::Windows1252 => 0,
+            }
+        }
+    }
+
+    fn decode_utf16(input: &[u8], to_unit: fn([u8; 2]) -> u16) -> Cow<'static, str> {
+        let units = input.chunks_exact(2).map(|c| to_unit([c[0], c[1]]));
+
+        let mut out = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect::<String>();
+
+        // a trailing odd byte is an incomplete code unit
+        if input.len() % 2 == 1 {
+            out.push(char::REPLACEMENT_CHARACTER);
+        }
+
+        Cow::Owned(out)
+    }
+}
@@ -0,0 +1,210 @@
+use std::borrow::Cow;
+
+use crate::HTMLTag;
+
+/// Named character references that are recognized by [`decode`]
+///
+/// This is not the full list of named references from the HTML specification,
+/// but it covers the ones that commonly appear in real world documents.
+static NAMED: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("shy", '\u{ad}'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("deg", '°'),
+    ("plusmn", '±'),
+    ("times", '×'),
+    ("divide", '÷'),
+    ("micro", 'µ'),
+    ("para", '¶'),
+    ("sect", '§'),
+    ("middot", '·'),
+    ("bull", '•'),
+    ("hellip", '…'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("bdquo", '„'),
+    ("laquo", '«'),
+    ("raquo", '»'),
+    ("lsaquo", '‹'),
+    ("rsaquo", '›'),
+    ("cent", '¢'),
+    ("pound", '£'),
+    ("yen", '¥'),
+    ("euro", '€'),
+    ("larr", '←'),
+    ("rarr", '→'),
+    ("uarr", '↑'),
+    ("darr", '↓'),
+    ("harr", '↔'),
+    ("ensp", '\u{2002}'),
+    ("emsp", '\u{2003}'),
+    ("thinsp", '\u{2009}'),
+    ("zwnj", '\u{200c}'),
+    ("zwj", '\u{200d}'),
+    ("lrm", '\u{200e}'),
+    ("rlm", '\u{200f}'),
+    ("Tab", '\t'),
+    ("NewLine", '\n'),
+    ("colon", ':'),
+    ("lpar", '('),
+    ("rpar", ')'),
+    ("sol", '/'),
+    ("bsol", '\\'),
+    ("num", '#'),
+    ("equals", '='),
+    ("grave", '`'),
+];
+
+/// Replacements for numeric references in the C1 control range, as mandated by the HTML specification
+/// (browsers interpret them as windows-1252)
+pub(crate) static C1_REPLACEMENTS: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// Decodes a numeric character reference, following the replacement rules of the HTML specification
+fn decode_numeric(code: u32) -> char {
+    match code {
+        0 => char::REPLACEMENT_CHARACTER,
+        0x80..=0x9f => C1_REPLACEMENTS[(code - 0x80) as usize],
+        _ => char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+/// Tries to decode a character reference at the start of `input` (which must start right after the `&`)
+///
+/// Returns the decoded character and the number of bytes consumed (not counting the `&`)
+fn decode_reference(input: &str) -> Option<(char, usize)> {
+    let bytes = input.as_bytes();
+
+    if let Some(rest) = input.strip_prefix('#') {
+        let (radix, digits_start) = match rest.as_bytes().first() {
+            Some(b'x' | b'X') => (16, 2),
+            _ => (10, 1),
+        };
+
+        let digits = bytes[digits_start..]
+            .iter()
+            .take_while(|b| b.is_ascii_hexdigit() && (radix == 16 || b.is_ascii_digit()))
+            .count();
+
+        if digits == 0 {
+            return None;
+        }
+
+        let end = digits_start + digits;
+        let code = u32::from_str_radix(&input[digits_start..end], radix)
+            .map(decode_numeric)
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+
+        // the trailing semicolon is optional for numeric references
+        let consumed = if bytes.get(end) == Some(&b';') {
+            end + 1
+        } else {
+            end
+        };
+
+        return Some((code, consumed));
+    }
+
+    let len = bytes
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .count();
+
+    if bytes.get(len) != Some(&b';') {
+        return None;
+    }
+
+    let name = &input[..len];
+
+    NAMED
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, c)| (c, len + 1))
+}
+
+/// Decodes HTML character references (`&amp;`, `&#39;`, `&#x27;`, ...) in the given string
+///
+/// Named references must be terminated by a semicolon, and unknown references are left as is.
+/// This does not allocate if the input does not contain any character references.
+pub(crate) fn decode(input: &str) -> Cow<'_, str> {
+    let Some(first) = input.find('&') else {
+        return Cow::Borrowed(input);
+    };
+
+    let mut out = String::with_capacity(input.len());
+    out.push_str(&input[..first]);
+
+    let mut rest = &input[first..];
+
+    while let Some(idx) = rest.find('&') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+
+        match decode_reference(rest) {
+            Some((c, consumed)) => {
+                out.push(c);
+                rest = &rest[consumed..];
+            }
+            None => out.push('&'),
+        }
+    }
+
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Returns the decoded value of an attribute of a tag, or an empty string if the attribute has no value
+pub(crate) fn decode_attribute(tag: &HTMLTag<'_>, name: &str) -> Option<String> {
+    let value = tag.attributes().get(name)?;
+
+    Some(match value {
+        Some(value) => decode(&value.as_utf8_str()).into_owned(),
+        None => String::new(),
+    })
+}
+
+/// Escapes a string so that it can be used as text content in HTML
+pub(crate) fn escape_text(input: &str) -> Cow<'_, str> {
+    escape(input, false)
+}
+
+/// Escapes a string so that it can be used as a double quoted attribute value in HTML
+pub(crate) fn escape_attribute(input: &str) -> Cow<'_, str> {
+    escape(input, true)
+}
+
+fn escape(input: &str, quotes: bool) -> Cow<'_, str> {
+    let needs_escape = |c: char| matches!(c, '&' | '<' | '>') || (quotes && c == '"');
+
+    if !input.contains(needs_escape) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut out = String::with_capacity(input.len() + 8);
+
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if quotes => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+
+    Cow::Owned(out)
+}
@@ -4,15 +4,43 @@ use std::error::Error;
 /// An error that occurred during parsing
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ParseError {
-    /// The input string length was too large to fit in a `u32`
+    /// The input string length was too large to fit in a `u32` (or `u64` with the `span64` feature)
     InvalidLength,
+    /// The context node of a fragment is not an HTML element
+    InvalidContext,
+    /// Elements are nested deeper than allowed by [`ParserOptions::max_depth`](crate::ParserOptions::max_depth)
+    DepthLimitExceeded,
+    /// The document has more nodes than allowed by [`ParserOptions::max_nodes`](crate::ParserOptions::max_nodes)
+    NodeLimitExceeded,
+    /// An element has more attributes than allowed by [`ParserOptions::max_attributes`](crate::ParserOptions::max_attributes)
+    AttributeLimitExceeded,
+    /// An attribute value is longer than allowed by [`ParserOptions::max_attribute_length`](crate::ParserOptions::max_attribute_length)
+    AttributeLengthExceeded,
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
             ParseError::InvalidLength => {
-                write!(f, "The input string length is too large to fit in a `u32`")
+                write!(f, "The input string length is too large to fit in a span")
+            }
+            ParseError::InvalidContext => {
+                write!(f, "The context node of a fragment is not an HTML element")
+            }
+            ParseError::DepthLimitExceeded => {
+                write!(f, "Elements are nested deeper than the configured limit")
+            }
+            ParseError::NodeLimitExceeded => {
+                write!(f, "The document has more nodes than the configured limit")
+            }
+            ParseError::AttributeLimitExceeded => {
+                write!(
+                    f,
+                    "An element has more attributes than the configured limit"
+                )
+            }
+            ParseError::AttributeLengthExceeded => {
+                write!(f, "An attribute value is longer than the configured limit")
             }
         }
     }
@@ -23,7 +51,7 @@ impl Error for ParseError {}
 /// An error that occurred during a call to `Bytes::set`
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SetBytesError {
-    /// The length of the given data would overflow a `u32`
+    /// The length of the given data would overflow a `u32` (or `u64` with the `span64` feature)
     LengthOverflow,
 }
 
@@ -31,10 +59,125 @@ impl fmt::Display for SetBytesError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
             SetBytesError::LengthOverflow => {
-                write!(f, "The string length is too large to fit in a `u32`")
+                write!(f, "The string length is too large to fit in a span")
             }
         }
     }
 }
 
 impl Error for SetBytesError {}
+
+/// An error that occurred while compiling or evaluating an XPath expression
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum XPathError {
+    /// The expression is not valid XPath 1.0 syntax
+    Syntax,
+    /// The expression calls a function that is not part of the XPath 1.0 core function library
+    UnknownFunction,
+    /// A function was called with the wrong number of arguments
+    InvalidArguments,
+    /// The expression uses an axis that is not supported, such as the namespace axis
+    UnsupportedAxis,
+    /// The expression references a variable, but variables cannot be bound
+    UnboundVariable,
+    /// An operation that requires a node-set was applied to a different type of value
+    NotANodeSet,
+    /// The expression is nested more deeply than the parser allows
+    NestingTooDeep,
+}
+
+impl fmt::Display for XPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            XPathError::Syntax => write!(f, "The expression is not valid XPath"),
+            XPathError::UnknownFunction => {
+                write!(f, "The expression calls an unknown function")
+            }
+            XPathError::InvalidArguments => {
+                write!(
+                    f,
+                    "A function was called with the wrong number of arguments"
+                )
+            }
+            XPathError::UnsupportedAxis => write!(f, "The expression uses an unsupported axis"),
+            XPathError::UnboundVariable => {
+                write!(f, "The expression references an unbound variable")
+            }
+            XPathError::NotANodeSet => write!(f, "A value is not a node-set"),
+            XPathError::NestingTooDeep => write!(f, "The expression is nested too deeply"),
+        }
+    }
+}
+
+impl Error for XPathError {}
+
+/// An error that occurred while editing a [`ClassList`](crate::ClassList)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClassListError {
+    /// The given class name is empty
+    EmptyToken,
+    /// The given class name contains whitespace or a double quote
+    InvalidCharacter,
+    /// The length of the new `class` attribute would overflow a `u32` (or `u64` with the `span64` feature)
+    LengthOverflow,
+}
+
+impl fmt::Display for ClassListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ClassListError::EmptyToken => write!(f, "The class name is empty"),
+            ClassListError::InvalidCharacter => {
+                write!(f, "The class name contains whitespace or a double quote")
+            }
+            ClassListError::LengthOverflow => {
+                write!(f, "The class attribute is too large to fit in a span")
+            }
+        }
+    }
+}
+
+impl Error for ClassListError {}
+
+impl From<SetBytesError> for ClassListError {
+    fn from(error: SetBytesError) -> Self {
+        match error {
+            SetBytesError::LengthOverflow => ClassListError::LengthOverflow,
+        }
+    }
+}
+
+/// An error that occurred while editing a [`Style`](crate::style::Style)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StyleError {
+    /// The given property name is not an identifier
+    InvalidProperty,
+    /// The given value is empty, or would change other declarations because it contains a `;` or `!`
+    /// outside of strings and brackets, a comment, or unbalanced brackets or quotes
+    InvalidValue,
+    /// The length of the new `style` attribute would overflow a `u32` (or `u64` with the `span64` feature)
+    LengthOverflow,
+}
+
+impl fmt::Display for StyleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            StyleError::InvalidProperty => write!(f, "The property name is not an identifier"),
+            StyleError::InvalidValue => {
+                write!(f, "The value is not a single declaration value")
+            }
+            StyleError::LengthOverflow => {
+                write!(f, "The style attribute is too large to fit in a span")
+            }
+        }
+    }
+}
+
+impl Error for StyleError {}
+
+impl From<SetBytesError> for StyleError {
+    fn from(error: SetBytesError) -> Self {
+        match error {
+            SetBytesError::LengthOverflow => StyleError::LengthOverflow,
+        }
+    }
+}
@@ -0,0 +1,648 @@
+use crate::entities::{self, decode_attribute};
+use crate::util::collapse_whitespace;
+use crate::{HTMLTag, Node, NodeHandle, Parser, VDom};
+
+/// The input types that browsers support, any other `type` is treated like `text`
+const INPUT_TYPES: [&str; 22] = [
+    "hidden",
+    "text",
+    "search",
+    "tel",
+    "url",
+    "email",
+    "password",
+    "date",
+    "month",
+    "week",
+    "time",
+    "datetime-local",
+    "number",
+    "range",
+    "color",
+    "checkbox",
+    "radio",
+    "file",
+    "submit",
+    "image",
+    "reset",
+    "button",
+];
+
+/// The HTTP method that a form is submitted with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Method {
+    /// `GET`, which is used if the method is missing or invalid
+    #[default]
+    Get,
+    /// `POST`
+    Post,
+    /// `dialog`, which closes the dialog that contains the form instead of submitting it
+    Dialog,
+}
+
+impl Method {
+    /// Returns the method for a `method` or `formmethod` attribute value, matched case-insensitively
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "get" => Some(Method::Get),
+            "post" => Some(Method::Post),
+            "dialog" => Some(Method::Dialog),
+            _ => None,
+        }
+    }
+
+    /// Returns the keyword of this method, as it appears in the `method` attribute
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "get",
+            Method::Post => "post",
+            Method::Dialog => "dialog",
+        }
+    }
+}
+
+/// The encoding of the entries of a form that is submitted with [`Method::Post`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Enctype {
+    /// `application/x-www-form-urlencoded`, which is used if the encoding is missing or invalid
+    #[default]
+    UrlEncoded,
+    /// `multipart/form-data`
+    Multipart,
+    /// `text/plain`
+    TextPlain,
+}
+
+impl Enctype {
+    /// Returns the encoding for an `enctype` or `formenctype` attribute value, matched case-insensitively
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "application/x-www-form-urlencoded" => Some(Enctype::UrlEncoded),
+            "multipart/form-data" => Some(Enctype::Multipart),
+            "text/plain" => Some(Enctype::TextPlain),
+            _ => None,
+        }
+    }
+
+    /// Returns the MIME type of this encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Enctype::UrlEncoded => "application/x-www-form-urlencoded",
+            Enctype::Multipart => "multipart/form-data",
+            Enctype::TextPlain => "text/plain",
+        }
+    }
+}
+
+/// A control that belongs to a [`Form`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Control {
+    /// The `<input>`, `<select>`, `<textarea>` or `<button>` element
+    pub handle: NodeHandle,
+    /// The type of the control, like the `type` property of the DOM
+    ///
+    /// This is the lowercase type of an `<input>` (`text` if it is missing or unknown),
+    /// `select-one` or `select-multiple` for a `<select>`, `textarea` for a `<textarea>`,
+    /// and `submit`, `reset` or `button` for a `<button>`.
+    pub control_type: String,
+    /// The `name` attribute, or an empty string if it is missing
+    pub name: String,
+    /// The values that the control submits if it is successful
+    ///
+    /// This is one value for most controls, and the values of the selected options for a `<select>`.
+    pub values: Vec<String>,
+    /// Whether a checkbox or radio button is checked
+    ///
+    /// Only the last checked radio button of a group is checked, like browsers do when parsing.
+    pub checked: bool,
+    /// Whether the control is disabled, either by its own `disabled` attribute or by a disabled `<fieldset>`
+    pub disabled: bool,
+    /// The `formaction`, `formmethod` and `formenctype` attributes of a submit button
+    overrides: Overrides,
+}
+
+impl Control {
+    /// Returns whether this control can submit its form, which is the case for submit buttons and image buttons
+    pub fn is_submit_button(&self) -> bool {
+        matches!(self.control_type.as_str(), "submit" | "image")
+    }
+}
+
+/// The attributes of a submit button that take precedence over the attributes of its form
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Overrides {
+    action: Option<String>,
+    method: Option<Method>,
+    enctype: Option<Enctype>,
+}
+
+/// The controls and attributes of a `<form>` element, see [`parse_form()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Form {
+    /// The `<form>` element
+    pub handle: NodeHandle,
+    /// The `action` attribute, or an empty string if it is missing
+    ///
+    /// This is not resolved, see [`VDom::resolve_url()`] to resolve it against the URL of the document.
+    pub action: String,
+    /// The `method` attribute
+    pub method: Method,
+    /// The `enctype` attribute
+    pub enctype: Enctype,
+    /// The controls of the form in document order
+    pub controls: Vec<Control>,
+}
+
+/// The data that a form submits, see [`Form::submission()`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Submission {
+    /// The URL that the form is submitted to, before it is resolved
+    pub action: String,
+    /// The HTTP method
+    pub method: Method,
+    /// The encoding of the entries
+    pub enctype: Enctype,
+    /// The names and values of the successful controls, in order
+    pub entries: Vec<(String, String)>,
+}
+
+impl Submission {
+    /// Serializes the entries as `application/x-www-form-urlencoded`, which is the query string of a `GET` submission
+    ///
+    /// Line breaks are normalized to `\r\n` before they are percent-encoded.
+    pub fn to_urlencoded(&self) -> String {
+        let mut output = String::new();
+
+        for (index, (name, value)) in self.entries.iter().enumerate() {
+            if index > 0 {
+                output.push('&');
+            }
+
+            urlencode(&mut output, name);
+            output.push('=');
+            urlencode(&mut output, value);
+        }
+
+        output
+    }
+}
+
+impl Form {
+    /// Returns the names and values that the form submits when it is submitted with the given submit button
+    ///
+    /// Disabled controls, unchecked checkboxes and radio buttons, controls without a name,
+    /// and buttons other than the submitter are skipped.
+    /// An image button adds the click coordinates `0`, `0` as `name.x` and `name.y`.
+    /// `submitter` is ignored if it is not an enabled submit button of this form.
+    pub fn entries(&self, submitter: Option<NodeHandle>) -> Vec<(String, String)> {
+        let submitter = self.submitter(submitter).map(|control| control.handle);
+        let mut entries = Vec::new();
+
+        for control in &self.controls {
+            if control.disabled {
+                continue;
+            }
+
+            match control.control_type.as_str() {
+                "checkbox" | "radio" if !control.checked => continue,
+                "submit" | "image" if submitter != Some(control.handle) => continue,
+                "reset" | "button" => continue,
+                "image" => {
+                    let (x, y) = if control.name.is_empty() {
+                        (String::from("x"), String::from("y"))
+                    } else {
+                        (format!("{}.x", control.name), format!("{}.y", control.name))
+                    };
+
+                    entries.push((x, String::from("0")));
+                    entries.push((y, String::from("0")));
+                    continue;
+                }
+                _ => {}
+            }
+
+            if control.name.is_empty() {
+                continue;
+            }
+
+            if control.control_type == "hidden" && control.name.eq_ignore_ascii_case("_charset_") {
+                entries.push((control.name.clone(), String::from("UTF-8")));
+                continue;
+            }
+
+            for value in &control.values {
+                entries.push((control.name.clone(), value.clone()));
+            }
+        }
+
+        entries
+    }
+
+    /// Returns what the form submits when it is submitted with the given submit button
+    ///
+    /// The `formaction`, `formmethod` and `formenctype` attributes of the submitter take precedence over the attributes of the form.
+    pub fn submission(&self, submitter: Option<NodeHandle>) -> Submission {
+        let overrides = self
+            .submitter(submitter)
+            .map(|control| control.overrides.clone())
+            .unwrap_or_default();
+
+        Submission {
+            action: overrides.action.unwrap_or_else(|| self.action.clone()),
+            method: overrides.method.unwrap_or(self.method),
+            enctype: overrides.enctype.unwrap_or(self.enctype),
+            entries: self.entries(submitter),
+        }
+    }
+
+    /// Returns the control of `submitter` if it is an enabled submit button of this form
+    fn submitter(&self, submitter: Option<NodeHandle>) -> Option<&Control> {
+        let submitter = submitter?;
+
+        self.controls.iter().find(|control| {
+            control.handle == submitter && control.is_submit_button() && !control.disabled
+        })
+    }
+}
+
+/// Collects the controls of a `<form>` element and its attributes
+///
+/// The controls of a form are the `<input>`, `<select>`, `<textarea>` and `<button>` elements inside of it,
+/// along with the ones anywhere in the document whose `form` attribute is the `id` of the form.
+/// Controls inside of the form whose `form` attribute refers to a different form are not part of it.
+/// Attribute values and text are entity-decoded.
+///
+/// Returns `None` if `handle` is not a `<form>` element.
+///
+/// # Example
+/// ```
+/// let input = r#"
+///   <form id="login" action="/login" method="post">
+///     <input name="user" value="admin">
+///     <input type="checkbox" name="remember" checked>
+///     <select name="lang"><option>en<option selected>de</select>
+///     <button name="go" value="1">Log in</button>
+///   </form>
+///   <input name="token" value="abc" form="login">"#;
+///
+/// let dom = tl::parse(input, Default::default()).unwrap();
+/// let handle = dom.query_selector("form").unwrap().next().unwrap();
+/// let form = tl::forms::parse_form(&dom, handle).unwrap();
+///
+/// let button = dom.query_selector("button").unwrap().next();
+/// let submission = form.submission(button);
+///
+/// assert_eq!(submission.method, tl::forms::Method::Post);
+/// assert_eq!(submission.to_urlencoded(), "user=admin&remember=on&lang=de&go=1&token=abc");
+/// ```
+pub fn parse_form(dom: &VDom<'_>, handle: NodeHandle) -> Option<Form> {
+    let parser = dom.parser();
+    let tag = handle.get(parser)?.as_tag()?;
+
+    if !tag.name().as_bytes().eq_ignore_ascii_case(b"form") {
+        return None;
+    }
+
+    // the `form` attribute refers to the first element with the id, which must be this form
+    let id = decode_attribute(tag, "id")
+        .filter(|id| !id.is_empty())
+        .filter(|id| {
+            let first = dom.elements().find(|&(handle, _)| {
+                handle
+                    .get(parser)
+                    .and_then(|node| node.as_tag())
+                    .and_then(|tag| decode_attribute(tag, "id"))
+                    .as_ref()
+                    == Some(id)
+            });
+
+            first.map(|(first, _)| first) == Some(handle)
+        });
+
+    let mut form = Form {
+        handle,
+        action: decode_attribute(tag, "action").unwrap_or_default(),
+        method: decode_attribute(tag, "method")
+            .and_then(|value| Method::parse(&value))
+            .unwrap_or_default(),
+        enctype: decode_attribute(tag, "enctype")
+            .and_then(|value| Enctype::parse(&value))
+            .unwrap_or_default(),
+        controls: Vec::new(),
+    };
+
+    // the open ancestors of the current element
+    let mut ancestors: Vec<(NodeHandle, usize)> = Vec::new();
+
+    for (element, depth) in dom.elements() {
+        while ancestors.last().is_some_and(|&(_, d)| d >= depth) {
+            ancestors.pop();
+        }
+        ancestors.push((element, depth));
+
+        let Some(tag) = element.get(parser).and_then(|node| node.as_tag()) else {
+            continue;
+        };
+
+        let name = tag.name().as_utf8_str().to_ascii_lowercase();
+        if !matches!(name.as_str(), "input" | "select" | "textarea" | "button") {
+            continue;
+        }
+
+        let ancestors = &ancestors[..ancestors.len() - 1];
+
+        let is_owned = match decode_attribute(tag, "form") {
+            Some(owner) => id.as_ref() == Some(&owner),
+            None => ancestors.iter().any(|&(ancestor, _)| ancestor == handle),
+        };
+
+        if !is_owned || has_ancestor(parser, ancestors, "datalist") {
+            continue;
+        }
+
+        if let Some(control) = control(parser, element, tag, &name, ancestors) {
+            form.controls.push(control);
+        }
+    }
+
+    // checking a radio button unchecks all other radio buttons of its group
+    for index in 0..form.controls.len() {
+        let control = &form.controls[index];
+
+        if control.control_type != "radio" || !control.checked || control.name.is_empty() {
+            continue;
+        }
+
+        let name = control.name.clone();
+        for other in &mut form.controls[..index] {
+            if other.control_type == "radio" && other.name == name {
+                other.checked = false;
+            }
+        }
+    }
+
+    Some(form)
+}
+
+/// Builds the control of an `<input>`, `<select>`, `<textarea>` or `<button>` element
+fn control(
+    parser: &Parser<'_>,
+    handle: NodeHandle,
+    tag: &HTMLTag<'_>,
+    name: &str,
+    ancestors: &[(NodeHandle, usize)],
+) -> Option<Control> {
+    let value = decode_attribute(tag, "value");
+
+    let (control_type, values) = match name {
+        "input" => {
+            let input_type = decode_attribute(tag, "type")
+                .map(|value| value.trim().to_ascii_lowercase())
+                .filter(|value| INPUT_TYPES.contains(&value.as_str()))
+                .unwrap_or_else(|| String::from("text"));
+
+            let value = match input_type.as_str() {
+                "checkbox" | "radio" => value.unwrap_or_else(|| String::from("on")),
+                "file" => String::new(),
+                "text" | "search" | "tel" | "password" => {
+                    value.unwrap_or_default().replace(['\r', '\n'], "")
+                }
+                "url" | "email" => value
+                    .unwrap_or_default()
+                    .replace(['\r', '\n'], "")
+                    .trim_matches(|c: char| c.is_ascii_whitespace())
+                    .to_string(),
+                _ => value.unwrap_or_default(),
+            };
+
+            (input_type, vec![value])
+        }
+        "button" => {
+            let button_type =
+                match decode_attribute(tag, "type").map(|value| value.to_ascii_lowercase()) {
+                    Some(value) if value == "reset" || value == "button" => value,
+                    _ => String::from("submit"),
+                };
+
+            (button_type, vec![value.unwrap_or_default()])
+        }
+        "select" => {
+            let multiple = tag.attributes().contains("multiple");
+            let control_type = if multiple {
+                "select-multiple"
+            } else {
+                "select-one"
+            };
+
+            (
+                control_type.to_string(),
+                selected_options(parser, handle, tag, multiple),
+            )
+        }
+        "textarea" => {
+            let mut text = text(parser, handle);
+
+            // a newline directly after the start tag is ignored
+            if text.starts_with("\r\n") {
+                text.drain(..2);
+            } else if text.starts_with('\n') {
+                text.remove(0);
+            }
+
+            (String::from("textarea"), vec![text])
+        }
+        _ => return None,
+    };
+
+    let is_submit_button = matches!(control_type.as_str(), "submit" | "image");
+    let overrides = if is_submit_button {
+        Overrides {
+            action: decode_attribute(tag, "formaction"),
+            method: decode_attribute(tag, "formmethod")
+                .map(|value| Method::parse(&value).unwrap_or_default()),
+            enctype: decode_attribute(tag, "formenctype")
+                .map(|value| Enctype::parse(&value).unwrap_or_default()),
+        }
+    } else {
+        Overrides::default()
+    };
+
+    Some(Control {
+        handle,
+        checked: matches!(control_type.as_str(), "checkbox" | "radio")
+            && tag.attributes().contains("checked"),
+        control_type,
+        name: decode_attribute(tag, "name").unwrap_or_default(),
+        values,
+        disabled: tag.attributes().contains("disabled")
+            || in_disabled_fieldset(parser, handle, ancestors),
+        overrides,
+    })
+}
+
+/// Returns the values of the selected and enabled options of a `<select>` element
+///
+/// If no option of a single-select element with a display size of 1 is selected, the first enabled option is,
+/// and if several are selected, only the last one is.
+fn selected_options(
+    parser: &Parser<'_>,
+    handle: NodeHandle,
+    tag: &HTMLTag<'_>,
+    multiple: bool,
+) -> Vec<String> {
+    let size = decode_attribute(tag, "size")
+        .and_then(|size| size.trim().parse::<usize>().ok())
+        .filter(|&size| size > 0)
+        .unwrap_or(if multiple { 4 } else { 1 });
+
+    // (value, selected, disabled) of every option
+    let mut options = Vec::new();
+    // the depths of the disabled <optgroup> elements that are open
+    let mut disabled_groups: Vec<usize> = Vec::new();
+
+    let mut elements = handle.elements(parser);
+
+    while let Some((element, depth)) = elements.next() {
+        while disabled_groups.last().is_some_and(|&d| d >= depth) {
+            disabled_groups.pop();
+        }
+
+        let Some(tag) = element.get(parser).and_then(|node| node.as_tag()) else {
+            continue;
+        };
+
+        let name = tag.name().as_bytes();
+        let disabled = tag.attributes().contains("disabled");
+
+        // the options of a nested list belong to that list
+        if name.eq_ignore_ascii_case(b"select") || name.eq_ignore_ascii_case(b"datalist") {
+            elements.skip_subtree();
+        } else if name.eq_ignore_ascii_case(b"optgroup") && disabled {
+            disabled_groups.push(depth);
+        } else if name.eq_ignore_ascii_case(b"option") {
+            let value = decode_attribute(tag, "value")
+                .unwrap_or_else(|| collapse_whitespace(&text(parser, element)));
+
+            options.push((
+                value,
+                tag.attributes().contains("selected"),
+                disabled || !disabled_groups.is_empty(),
+            ));
+        }
+    }
+
+    if !multiple {
+        // only the last selected option stays selected
+        match options.iter().rposition(|&(_, selected, _)| selected) {
+            Some(last) => {
+                for (index, option) in options.iter_mut().enumerate() {
+                    option.1 = index == last;
+                }
+            }
+            None if size == 1 => {
+                if let Some(option) = options.iter_mut().find(|(_, _, disabled)| !disabled) {
+                    option.1 = true;
+                }
+            }
+            None => {}
+        }
+    }
+
+    options
+        .into_iter()
+        .filter(|&(_, selected, disabled)| selected && !disabled)
+        .map(|(value, _, _)| value)
+        .collect()
+}
+
+/// Returns whether an element is disabled by a `<fieldset disabled>` ancestor
+///
+/// Descendants of the first `<legend>` child of the fieldset are not disabled by it.
+fn in_disabled_fieldset(
+    parser: &Parser<'_>,
+    handle: NodeHandle,
+    ancestors: &[(NodeHandle, usize)],
+) -> bool {
+    ancestors.iter().enumerate().any(|(index, &(ancestor, _))| {
+        let Some(tag) = ancestor.get(parser).and_then(|node| node.as_tag()) else {
+            return false;
+        };
+
+        if !tag.name().as_bytes().eq_ignore_ascii_case(b"fieldset")
+            || !tag.attributes().contains("disabled")
+        {
+            return false;
+        }
+
+        let first_legend = tag.children().top().iter().copied().find(|child| {
+            child
+                .get(parser)
+                .and_then(|node| node.as_tag())
+                .is_some_and(|tag| tag.name().as_bytes().eq_ignore_ascii_case(b"legend"))
+        });
+
+        let in_legend = first_legend.is_some_and(|legend| {
+            ancestors[index + 1..]
+                .first()
+                .map_or(legend == handle, |&(child, _)| child == legend)
+        });
+
+        !in_legend
+    })
+}
+
+/// Returns whether one of the ancestors is an element with the given name
+fn has_ancestor(parser: &Parser<'_>, ancestors: &[(NodeHandle, usize)], name: &str) -> bool {
+    ancestors.iter().any(|&(ancestor, _)| {
+        ancestor
+            .get(parser)
+            .and_then(|node| node.as_tag())
+            .is_some_and(|tag| tag.name().as_bytes().eq_ignore_ascii_case(name.as_bytes()))
+    })
+}
+
+/// Returns the decoded text of an element, without the text of options and controls nested inside of it
+///
+/// End tags are not implied, so with an omitted `</option>` the next option, and with that any following control,
+/// is nested in the previous option. Their text must not add to the text of the outer option.
+fn text(parser: &Parser<'_>, handle: NodeHandle) -> String {
+    let mut text = String::new();
+    let mut descendants = handle.descendants(parser);
+
+    while let Some((node, _)) = descendants.next() {
+        match node.get(parser) {
+            Some(Node::Raw(raw)) => text.push_str(&entities::decode(&raw.as_utf8_str())),
+            Some(Node::Tag(tag)) => {
+                let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+                if matches!(
+                    name.as_str(),
+                    "option" | "optgroup" | "input" | "select" | "textarea" | "button"
+                ) {
+                    descendants.skip_subtree();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    text
+}
+
+/// Appends the `application/x-www-form-urlencoded` form of a string
+fn urlencode(output: &mut String, value: &str) {
+    let value = value
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', "\r\n");
+
+    for byte in value.bytes() {
+        match byte {
+            b'*' | b'-' | b'.' | b'_' | b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' => {
+                output.push(char::from(byte))
+            }
+            b' ' => output.push('+'),
+            _ => output.push_str(&format!("%{byte:02X}")),
+        }
+    }
+}
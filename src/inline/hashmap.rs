@@ -74,6 +74,12 @@ where
         self.0.insert(key, value)
     }
 
+    /// Inserts an element into the map, replacing the value of an existing element with the same key
+    #[inline]
+    pub fn replace(&mut self, key: K, value: V) {
+        self.0.replace(key, value)
+    }
+
     /// Removes an element from the map, and returns ownership over the value
     #[inline]
     pub fn remove(&mut self, key: &K) -> Option<V> {
@@ -268,6 +274,16 @@ impl<K: Eq + Hash, V, const N: usize> InlineHashMapInner<K, V, N> {
         }
     }
 
+    pub fn replace(&mut self, k: K, v: V) {
+        // like `HashMap::insert`, an existing entry for the key is replaced instead of being duplicated
+        if let Some(value) = self.get_mut(&k) {
+            *value = v;
+            return;
+        }
+
+        self.insert(k, v);
+    }
+
     pub fn insert(&mut self, k: K, v: V) {
         let (array, len) = match self {
             Self::Inline { data, len } => (data, len),
@@ -441,6 +457,23 @@ mod tests {
         assert_eq!(x.len(), 0);
     }
 
+    #[test]
+    fn inlinehashmap_replace() {
+        let mut x = InlineHashMapInner::<&'static str, usize, 2>::new();
+        x.replace("foo", 1);
+        x.replace("foo", 2);
+        assert_eq!(x.len(), 1);
+        assert_eq!(x.get(&"foo"), Some(&2));
+        assert!(!x.is_heap_allocated());
+
+        x.replace("bar", 3);
+        x.replace("baz", 4);
+        x.replace("bar", 5);
+        assert_eq!(x.len(), 3);
+        assert_eq!(x.get(&"bar"), Some(&5));
+        assert!(x.is_heap_allocated());
+    }
+
     #[test]
     fn inlinehashmap_clone() {
         let mut x = InlineHashMapInner::<usize, usize, 4>::new();
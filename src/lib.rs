@@ -2,18 +2,45 @@
 #![deny(missing_docs)]
 
 mod bytes;
+mod charset;
+mod entities;
 /// Errors that occur throughout the crate
 pub mod errors;
+/// Form controls and the data that forms submit
+pub mod forms;
 /// Inline data structures
 pub mod inline;
+mod links;
+/// HTML to Markdown conversion (requires the `markdown` feature)
+#[cfg(feature = "markdown")]
+pub mod markdown;
+/// Extraction of document metadata, such as OpenGraph properties and JSON-LD
+pub mod metadata;
+/// Extraction of microdata and RDFa Lite items
+pub mod microdata;
 mod parser;
 /// Query selector API
 pub mod queryselector;
+/// HTML sanitization
+pub mod sanitize;
+/// Serialization of documents and subtrees with serde (requires the `serde` feature)
+#[cfg(feature = "serde")]
+pub mod serialize;
+/// Extraction of files from simple repository pages
+pub mod simple_index;
+/// Parsing of `srcset` and `sizes` attributes
+pub mod srcset;
 mod stream;
+/// Parsing and editing of inline `style` attributes
+pub mod style;
+/// Extraction of tables into rectangular grids
+pub mod table;
 #[cfg(test)]
 mod tests;
 mod util;
 mod vdom;
+/// XPath 1.0 evaluation
+pub mod xpath;
 
 #[doc(hidden)]
 #[cfg(feature = "__INTERNALS_DO_NOT_USE")]
@@ -23,22 +50,33 @@ mod simd;
 
 pub use bytes::Bytes;
 pub use errors::ParseError;
+pub use links::{BaseUrl, Link};
 pub use parser::*;
 use queryselector::Selector;
-pub use vdom::{VDom, VDomGuard};
+use std::io;
+#[allow(deprecated)]
+pub use vdom::VDomGuard;
+pub use vdom::{OwnedVDom, VDom};
 
 /// Parses the given input string
 ///
 /// This is the "entry point" and function that is called to parse HTML.
 /// The input string must be kept alive, and must outlive `VDom`.
 /// If you need an "owned" version that takes an input string and can be kept around forever,
-/// consider using `parse_owned()`.
+/// consider using [`OwnedVDom`].
+///
+/// The contents of the raw text elements `<iframe>`, `<noembed>`, `<noframes>`, `<plaintext>`, `<script>`, `<style>`,
+/// `<textarea>`, `<title>` and `<xmp>` are not parsed as markup. They become a single text node that ends at the
+/// matching end tag, or at the end of the input for `<plaintext>`, which has no end tag.
 ///
 /// # Errors
 /// Throughout the parser it is assumed that spans never overflow a `u32`.
 /// To prevent this, this function will return an error if the input string length would overflow a `u32`.
 /// If the input string length fits in a `u32`, then it is safe to assume that none of the substrings can overflow a `u32`.
 ///
+/// Inputs larger than 4 GiB can be parsed by enabling the `span64` feature,
+/// which uses `u64` for node handles and string lengths instead.
+///
 /// # Example
 /// ```
 /// # use tl::*;
@@ -51,6 +89,117 @@ pub fn parse(input: &str, options: ParserOptions) -> Result<VDom<'_>, ParseError
     Ok(VDom::from(parser))
 }
 
+/// Parses the given input in a single pass, calling the visitor for every node instead of building a tree
+///
+/// Only the currently open elements are kept in memory, so memory usage depends on the nesting depth
+/// rather than the size of the input. Elements are opened and closed exactly like [`parse()`] does,
+/// and the limits of the [`ParserOptions`] apply in the same way.
+///
+/// # Errors
+/// Returns an error if a parser limit with [`LimitPolicy::Error`] is exceeded.
+/// The visitor may have been called for some nodes already in that case.
+///
+/// # Example
+/// ```
+/// # use tl::*;
+/// struct Links<'a>(Vec<&'a str>);
+///
+/// impl<'a> Visitor<'a> for Links<'a> {
+///     fn enter_element(&mut self, tag: &HTMLTag<'a>, _depth: usize) {
+///         if let Some(Some(href)) = tag.attributes().get("href") {
+///             self.0.extend(href.as_bytes_borrowed().and_then(|href| std::str::from_utf8(href).ok()));
+///         }
+///     }
+/// }
+///
+/// let mut links = Links(Vec::new());
+/// visit(r#"<ul><li><a href="/a">a</a><li><a href="/b">b</a></ul>"#, ParserOptions::default(), &mut links).unwrap();
+/// assert_eq!(links.0, ["/a", "/b"]);
+/// ```
+pub fn visit<'a, V: Visitor<'a> + ?Sized>(
+    input: &'a str,
+    options: ParserOptions,
+    visitor: &mut V,
+) -> Result<(), ParseError> {
+    parser::drive(input, options, visitor)
+}
+
+/// Parses the given bytes, detecting their character encoding and decoding them if necessary
+///
+/// The encoding is determined by a byte order mark, or else by a `<meta charset>` (or `<meta http-equiv="content-type">`) declaration
+/// within the first 1024 bytes, following the prescan algorithm of the HTML specification.
+/// If neither is present, the input is decoded as UTF-8 if it is valid UTF-8, and as windows-1252 otherwise.
+/// Invalid byte sequences are replaced with U+FFFD.
+///
+/// The detected encoding is available through [`VDom::encoding()`].
+/// Since the decoded text has to be stored somewhere, this always returns an [`OwnedVDom`].
+///
+/// Without the `encoding` feature, only UTF-8, UTF-16 and windows-1252 (including its aliases ISO-8859-1 and US-ASCII) are supported,
+/// and declarations of other encodings are ignored.
+/// With the `encoding` feature, all encodings of the [WHATWG Encoding Standard](https://encoding.spec.whatwg.org/) are supported.
+///
+/// # Errors
+/// See [parse]
+///
+/// # Example
+/// ```
+/// # use tl::*;
+/// let input = b"<meta charset=\"iso-8859-1\"><p>caf\xe9</p>";
+/// let dom = parse_bytes(input, ParserOptions::default()).unwrap();
+/// let dom = dom.get_ref();
+///
+/// assert_eq!(dom.encoding(), Some("windows-1252"));
+/// assert_eq!(dom.query_selector("p").unwrap().next().unwrap().get(dom.parser()).unwrap().inner_text(dom.parser()), "café");
+/// ```
+pub fn parse_bytes(input: &[u8], options: ParserOptions) -> Result<OwnedVDom, ParseError> {
+    parse_bytes_with_hint(input, None, options)
+}
+
+/// Parses the given bytes like [`parse_bytes()`], taking an encoding label from the transport layer into account
+///
+/// `hint` is an encoding label such as the `charset` parameter of an HTTP `Content-Type` header.
+/// It takes precedence over `<meta>` declarations, but not over a byte order mark. Unsupported labels are ignored.
+///
+/// # Errors
+/// See [parse]
+pub fn parse_bytes_with_hint(
+    input: &[u8],
+    hint: Option<&str>,
+    options: ParserOptions,
+) -> Result<OwnedVDom, ParseError> {
+    let (text, encoding) = charset::decode(input, hint);
+
+    let mut dom = OwnedVDom::parse(text, options)?;
+    dom.with_mut(|dom| dom.parser_mut().encoding = Some(encoding));
+
+    Ok(dom)
+}
+
+/// Reads the input from a reader and parses it like [`parse_bytes()`], using a [`ChunkedParser`]
+///
+/// The input is parsed while it is read, instead of being read into a buffer first.
+/// Like with [`ChunkedParser`], the character encoding is determined from the first 1024 bytes.
+///
+/// # Errors
+/// Errors of the reader are returned as they are.
+/// Parse errors (see [parse]) are returned with the kind [`io::ErrorKind::InvalidData`] and can be retrieved with [`io::Error::get_ref()`].
+///
+/// # Example
+/// ```
+/// let input: &[u8] = b"<ul><li>tl</li><li>serde</li></ul>";
+/// let dom = tl::parse_reader(input, tl::ParserOptions::default()).unwrap();
+///
+/// assert_eq!(dom.get_ref().query_selector("li").unwrap().count(), 2);
+/// ```
+pub fn parse_reader<R: io::Read>(mut reader: R, options: ParserOptions) -> io::Result<OwnedVDom> {
+    let mut parser = ChunkedParser::new(options);
+    io::copy(&mut reader, &mut parser)?;
+
+    parser
+        .finish()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
 /// Parses a query selector
 ///
 /// # Example
@@ -81,6 +230,8 @@ pub fn parse_query_selector(input: &str) -> Option<Selector<'_>> {
 /// The given input string is first leaked and turned into raw pointer, and its lifetime will be promoted to 'static.
 /// Once `VDomGuard` goes out of scope, the string will be freed.
 /// It should not be possible to cause UB in its current form and might become a safe function in the future.
+#[deprecated(note = "use `OwnedVDom::parse()` instead")]
+#[allow(deprecated)]
 pub unsafe fn parse_owned(input: String, options: ParserOptions) -> Result<VDomGuard, ParseError> {
     VDomGuard::parse(input, options)
 }
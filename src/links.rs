@@ -0,0 +1,225 @@
+use crate::{entities, srcset, Node, NodeHandle, VDom};
+
+/// The attributes that contain URLs, in the order in which they are yielded by [`VDom::links()`]
+const URL_ATTRIBUTES: [&str; 6] = ["href", "src", "srcset", "imagesrcset", "action", "poster"];
+
+/// A URL in an attribute of an element, see [`VDom::links()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The element that the attribute belongs to
+    pub handle: NodeHandle,
+    /// The name of the attribute, one of `href`, `src`, `srcset`, `imagesrcset`, `action` and `poster`
+    pub attribute: &'static str,
+    /// The resolved URL
+    pub url: String,
+}
+
+/// The base URL of a document, which the URLs in it are resolved against, see [`VDom::base_url()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseUrl(pub(crate) String);
+
+impl BaseUrl {
+    /// Returns the base URL as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Resolves a URL from an attribute value against this base URL, see [`VDom::resolve_url()`]
+    pub fn resolve(&self, value: &str) -> Option<String> {
+        join(&self.0, value)
+    }
+}
+
+/// Returns the base URL of a document
+///
+/// This is the `href` of the first `<base>` element that has one resolved against `document_url`, or `document_url` itself.
+pub(crate) fn base_url(dom: &VDom<'_>, document_url: &str) -> String {
+    let parser = dom.parser();
+
+    let href = dom.elements().find_map(|(handle, _)| {
+        let tag = handle.get(parser)?.as_tag()?;
+
+        if !tag.name().as_bytes().eq_ignore_ascii_case(b"base") {
+            return None;
+        }
+
+        tag.attributes().get("href")?
+    });
+
+    href.and_then(|href| join(document_url, &entities::decode(&href.as_utf8_str())))
+        .unwrap_or_else(|| document_url.to_string())
+}
+
+/// Returns all URLs in the attributes of the elements of a document, resolved against its base URL
+pub(crate) fn links(dom: &VDom<'_>, document_url: &str) -> Vec<Link> {
+    let parser = dom.parser();
+    let base = base_url(dom, document_url);
+
+    let mut links = Vec::new();
+
+    for (handle, _) in dom.elements() {
+        let Some(Node::Tag(tag)) = handle.get(parser) else {
+            continue;
+        };
+
+        // the base URL itself is not a link
+        if tag.name().as_bytes().eq_ignore_ascii_case(b"base") {
+            continue;
+        }
+
+        for attribute in URL_ATTRIBUTES {
+            let Some(Some(value)) = tag.attributes().get(attribute) else {
+                continue;
+            };
+
+            let value = value.as_utf8_str();
+            let value = entities::decode(&value);
+
+            let mut push = |reference: &str| {
+                if let Some(url) = join(&base, reference) {
+                    links.push(Link {
+                        handle,
+                        attribute,
+                        url,
+                    });
+                }
+            };
+
+            if attribute.ends_with("srcset") {
+                for (url, _) in srcset::parse_srcset(&value) {
+                    push(&url);
+                }
+            } else {
+                push(&value);
+            }
+        }
+    }
+
+    links
+}
+
+/// Resolves a (possibly relative) URL reference against a base URL
+///
+/// With the `url` feature, absolute base URLs are handled as specified by the WHATWG URL Standard,
+/// which also normalizes the result, and `None` is returned if the reference is not a valid URL.
+/// Otherwise, the reference resolution algorithm of RFC 3986 is used, which never fails.
+pub(crate) fn join(base: &str, reference: &str) -> Option<String> {
+    #[cfg(feature = "url")]
+    if let Ok(base) = url::Url::parse(base) {
+        return base.join(reference).ok().map(String::from);
+    }
+
+    Some(join_url(base, reference))
+}
+
+/// Returns the scheme of a URL, if it has one
+fn url_scheme(url: &str) -> Option<&str> {
+    let end = url.find(':')?;
+    let scheme = &url[..end];
+
+    let mut chars = scheme.bytes();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, b'+' | b'-' | b'.'));
+
+    valid.then_some(scheme)
+}
+
+/// Splits a URL into `(scheme://authority, path, query and fragment)`
+fn split_url(url: &str) -> (&str, &str, &str) {
+    let after_scheme = url_scheme(url).map_or(0, |s| s.len() + 1);
+
+    let path_start = if url[after_scheme..].starts_with("//") {
+        url[after_scheme + 2..]
+            .find(['/', '?', '#'])
+            .map_or(url.len(), |i| after_scheme + 2 + i)
+    } else {
+        after_scheme
+    };
+
+    let path_end = url[path_start..]
+        .find(['?', '#'])
+        .map_or(url.len(), |i| path_start + i);
+
+    (
+        &url[..path_start],
+        &url[path_start..path_end],
+        &url[path_end..],
+    )
+}
+
+/// Removes `.` and `..` segments from a URL path (RFC 3986, section 5.2.4)
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let absolute = path.starts_with('/');
+    let segments: Vec<&str> = path.split('/').skip(absolute as usize).collect();
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let last = idx == segments.len() - 1;
+
+        match *segment {
+            "." => {
+                if last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                output.pop();
+                if last {
+                    output.push("");
+                }
+            }
+            s => output.push(s),
+        }
+    }
+
+    let joined = output.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Resolves a (possibly relative) URL reference against a base URL
+///
+/// This implements the reference resolution algorithm of RFC 3986 for hierarchical URLs,
+/// which is what browsers do for the common `http(s)` cases.
+pub(crate) fn join_url(base: &str, reference: &str) -> String {
+    let reference = reference.trim();
+
+    if url_scheme(reference).is_some() {
+        return reference.to_string();
+    }
+
+    let (base_origin, base_path, base_rest) = split_url(base);
+
+    if let Some(rest) = reference.strip_prefix("//") {
+        let scheme = url_scheme(base).map_or("", |s| &base[..s.len() + 1]);
+        return format!("{}//{}", scheme, rest);
+    }
+
+    let (_, ref_path, ref_rest) = split_url(reference);
+
+    if ref_path.is_empty() {
+        let base_query = base_rest.find('#').map_or(base_rest, |i| &base_rest[..i]);
+
+        return if ref_rest.is_empty() {
+            format!("{}{}{}", base_origin, base_path, base_query)
+        } else if ref_rest.starts_with('#') {
+            format!("{}{}{}{}", base_origin, base_path, base_query, ref_rest)
+        } else {
+            format!("{}{}{}", base_origin, base_path, ref_rest)
+        };
+    }
+
+    let path = if ref_path.starts_with('/') {
+        remove_dot_segments(ref_path)
+    } else if base_path.is_empty() && !base_origin.is_empty() {
+        remove_dot_segments(&format!("/{}", ref_path))
+    } else {
+        let dir = base_path.rfind('/').map_or("", |i| &base_path[..=i]);
+        remove_dot_segments(&format!("{}{}", dir, ref_path))
+    };
+
+    format!("{}{}{}", base_origin, path, ref_rest)
+}
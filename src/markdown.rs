@@ -0,0 +1,778 @@
+use std::cell::Cell;
+
+use crate::entities;
+use crate::util::collapse_whitespace;
+use crate::{links, HTMLTag, Node, NodeHandle, Parser, VDom};
+
+/// The maximum nesting depth of elements that are converted to Markdown syntax
+///
+/// The contents of elements that are nested deeper are converted to plain text,
+/// so that deeply nested documents cannot overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Specifies what to do with elements that have no Markdown equivalent
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum UnknownElements {
+    /// Drop the element itself, but convert its contents
+    #[default]
+    Unwrap,
+    /// Drop the element and all of its contents
+    Remove,
+    /// Keep the element as raw HTML, which CommonMark allows inline
+    Html,
+}
+
+/// Options for the Markdown converter
+///
+/// # Example
+/// ```
+/// use tl::markdown::{MarkdownOptions, UnknownElements};
+///
+/// let options = MarkdownOptions::new()
+///     .base_url("https://example.com/docs/")
+///     .unknown_elements(UnknownElements::Remove);
+///
+/// let dom = tl::parse(r#"<p>See <a href="intro.html">the intro</a><marquee>!</marquee></p>"#, Default::default()).unwrap();
+/// assert_eq!(
+///     tl::markdown::to_markdown(&dom, &options),
+///     "See [the intro](https://example.com/docs/intro.html)"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownOptions {
+    base_url: Option<String>,
+    unknown_elements: UnknownElements,
+}
+
+impl MarkdownOptions {
+    /// Creates a new [MarkdownOptions] with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the URL that relative `href` and `src` attributes are resolved against
+    pub fn base_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Sets how elements without a Markdown equivalent are converted
+    pub fn unknown_elements(mut self, unknown: UnknownElements) -> Self {
+        self.unknown_elements = unknown;
+        self
+    }
+}
+
+/// Converts all of the nodes of a [`VDom`] to CommonMark
+///
+/// Tables are converted to the [GitHub Flavored Markdown](https://github.github.com/gfm/#tables-extension-) syntax.
+pub fn to_markdown(dom: &VDom<'_>, options: &MarkdownOptions) -> String {
+    let converter = Converter::new(dom.parser(), options);
+
+    join_blocks(converter.blocks(dom.children()))
+}
+
+/// Converts the node referenced by `handle`, including the node itself, to CommonMark
+///
+/// # Example
+/// ```
+/// let dom = tl::parse("<div><h2>Title</h2><ul><li>one</li><li>two</li></ul></div>", Default::default()).unwrap();
+/// let list = dom.query_selector("ul").unwrap().next().unwrap();
+///
+/// assert_eq!(tl::markdown::node_to_markdown(dom.parser(), list, &Default::default()), "- one\n- two");
+/// ```
+pub fn node_to_markdown(
+    parser: &Parser<'_>,
+    handle: NodeHandle,
+    options: &MarkdownOptions,
+) -> String {
+    let converter = Converter::new(parser, options);
+
+    join_blocks(converter.blocks(&[handle]))
+}
+
+/// A rendered block, such as a paragraph or a list
+struct Block {
+    text: String,
+    is_list: bool,
+}
+
+impl Block {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            is_list: false,
+        }
+    }
+}
+
+fn join_blocks(blocks: Vec<Block>) -> String {
+    blocks
+        .into_iter()
+        .map(|b| b.text)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// How a given element is converted
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    /// Elements that are dropped, including their contents
+    Ignored,
+    /// Block elements that only group their contents
+    Container,
+    /// Inline elements that only group their contents
+    Transparent,
+    /// Elements that are converted to Markdown blocks
+    Block,
+    /// Elements that are converted to inline Markdown
+    Inline,
+    /// Elements that do not have a Markdown equivalent
+    Unknown,
+}
+
+fn classify(name: &str) -> Kind {
+    match name {
+        "head" | "title" | "script" | "style" | "template" | "noscript" | "meta" | "link"
+        | "base" => Kind::Ignored,
+        "html" | "body" | "div" | "section" | "article" | "main" | "header" | "footer" | "nav"
+        | "aside" | "figure" | "figcaption" | "address" | "details" | "summary" | "form"
+        | "fieldset" | "center" | "dl" | "dt" | "dd" | "hgroup" => Kind::Container,
+        "span" | "small" | "sub" | "sup" | "abbr" | "cite" | "q" | "mark" | "u" | "label"
+        | "time" | "font" | "big" | "ins" | "bdi" | "bdo" | "data" | "var" | "dfn" | "acronym" => {
+            Kind::Transparent
+        }
+        "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "pre" | "blockquote"
+        | "hr" | "table" => Kind::Block,
+        "a" | "img" | "em" | "i" | "strong" | "b" | "code" | "kbd" | "samp" | "tt" | "del"
+        | "s" | "strike" | "br" => Kind::Inline,
+        _ => Kind::Unknown,
+    }
+}
+
+/// Accumulates blocks while walking the children of an element
+#[derive(Default)]
+struct Flow {
+    blocks: Vec<Block>,
+    inline: String,
+}
+
+impl Flow {
+    fn flush(&mut self) {
+        let paragraph = normalize_inline(&self.inline);
+        self.inline.clear();
+
+        if !paragraph.is_empty() {
+            self.blocks.push(Block::new(escape_block_start(paragraph)));
+        }
+    }
+}
+
+struct Converter<'p, 'a, 'o> {
+    parser: &'p Parser<'a>,
+    options: &'o MarkdownOptions,
+    /// The number of nested calls to `flow()` and `inline_into()`
+    depth: Cell<usize>,
+}
+
+impl<'p, 'a, 'o> Converter<'p, 'a, 'o> {
+    fn new(parser: &'p Parser<'a>, options: &'o MarkdownOptions) -> Self {
+        Self {
+            parser,
+            options,
+            depth: Cell::new(0),
+        }
+    }
+
+    /// Enters a nested element, or returns `false` if that would exceed [`MAX_DEPTH`]
+    fn enter(&self) -> bool {
+        let depth = self.depth.get();
+        self.depth.set(depth + 1);
+        depth < MAX_DEPTH
+    }
+
+    fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    /// Pushes the text of the given nodes without converting any markup
+    fn push_plain_text(&self, handles: &[NodeHandle], out: &mut String) {
+        for handle in handles {
+            if let Some(node) = handle.get(self.parser) {
+                push_text(out, &entities::decode(&node.inner_text(self.parser)));
+            }
+        }
+    }
+
+    fn tag(&self, handle: NodeHandle) -> Option<&'p HTMLTag<'a>> {
+        handle.get(self.parser).and_then(Node::as_tag)
+    }
+
+    fn resolve(&self, url: &str) -> String {
+        let url = entities::decode(url);
+
+        match &self.options.base_url {
+            Some(base) => links::join(base, &url).unwrap_or_else(|| url.into_owned()),
+            None => url.into_owned(),
+        }
+    }
+
+    fn blocks(&self, handles: &[NodeHandle]) -> Vec<Block> {
+        let mut flow = Flow::default();
+        self.flow(handles, &mut flow);
+        flow.flush();
+        flow.blocks
+    }
+
+    fn flow(&self, handles: &[NodeHandle], flow: &mut Flow) {
+        if self.enter() {
+            self.flow_nodes(handles, flow);
+        } else {
+            self.push_plain_text(handles, &mut flow.inline);
+        }
+        self.leave();
+    }
+
+    fn flow_nodes(&self, handles: &[NodeHandle], flow: &mut Flow) {
+        for &handle in handles {
+            let Some(node) = handle.get(self.parser) else {
+                continue;
+            };
+
+            let tag = match node {
+                Node::Raw(text) => {
+                    push_text(&mut flow.inline, &entities::decode(&text.as_utf8_str()));
+                    continue;
+                }
+                Node::Comment(_) => continue,
+                Node::Tag(tag) => tag,
+            };
+
+            let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+            match classify(&name) {
+                Kind::Ignored => {}
+                Kind::Container => {
+                    flow.flush();
+                    self.flow(tag.children().top().as_slice(), flow);
+                    flow.flush();
+                }
+                Kind::Transparent => self.flow(tag.children().top().as_slice(), flow),
+                Kind::Block => {
+                    flow.flush();
+                    flow.blocks.extend(self.block(&name, tag));
+                }
+                Kind::Inline => self.inline_element(&name, tag, &mut flow.inline),
+                Kind::Unknown => match self.options.unknown_elements {
+                    UnknownElements::Unwrap => self.flow(tag.children().top().as_slice(), flow),
+                    UnknownElements::Remove => {}
+                    UnknownElements::Html => {
+                        push_html(&mut flow.inline, &tag.outer_html(self.parser))
+                    }
+                },
+            }
+        }
+    }
+
+    /// Converts the given nodes to inline Markdown, converting block elements to their inline content
+    fn inline(&self, handles: &[NodeHandle]) -> String {
+        let mut out = String::new();
+        self.inline_into(handles, &mut out);
+        out
+    }
+
+    fn inline_into(&self, handles: &[NodeHandle], out: &mut String) {
+        if self.enter() {
+            self.inline_nodes(handles, out);
+        } else {
+            self.push_plain_text(handles, out);
+        }
+        self.leave();
+    }
+
+    fn inline_nodes(&self, handles: &[NodeHandle], out: &mut String) {
+        for &handle in handles {
+            let Some(node) = handle.get(self.parser) else {
+                continue;
+            };
+
+            let tag = match node {
+                Node::Raw(text) => {
+                    push_text(out, &entities::decode(&text.as_utf8_str()));
+                    continue;
+                }
+                Node::Comment(_) => continue,
+                Node::Tag(tag) => tag,
+            };
+
+            let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+            match classify(&name) {
+                Kind::Ignored => {}
+                Kind::Inline => self.inline_element(&name, tag, out),
+                Kind::Unknown => match self.options.unknown_elements {
+                    UnknownElements::Unwrap => {
+                        self.inline_into(tag.children().top().as_slice(), out)
+                    }
+                    UnknownElements::Remove => {}
+                    UnknownElements::Html => push_html(out, &tag.outer_html(self.parser)),
+                },
+                Kind::Container | Kind::Block => {
+                    push_space(out);
+                    self.inline_into(tag.children().top().as_slice(), out);
+                    push_space(out);
+                }
+                Kind::Transparent => self.inline_into(tag.children().top().as_slice(), out),
+            }
+        }
+    }
+
+    fn inline_element(&self, name: &str, tag: &HTMLTag<'a>, out: &mut String) {
+        let children = tag.children().top().as_slice();
+
+        match name {
+            "br" => out.push_str("\\\n"),
+            "em" | "i" => wrap(out, &self.inline(children), "*"),
+            "strong" | "b" => wrap(out, &self.inline(children), "**"),
+            "del" | "s" | "strike" => wrap(out, &self.inline(children), "~~"),
+            "code" | "kbd" | "samp" | "tt" => {
+                let text = entities::decode(&tag.inner_text(self.parser)).into_owned();
+                push_code_span(out, &collapse_whitespace(&text));
+            }
+            "a" => {
+                let text = normalize_inline(&self.inline(children));
+
+                let Some(href) = attribute_value(tag, "href") else {
+                    out.push_str(&text);
+                    return;
+                };
+
+                out.push('[');
+                out.push_str(&text);
+                out.push_str("](");
+                push_destination(out, &self.resolve(&href));
+                push_title(out, tag);
+                out.push(')');
+            }
+            "img" => {
+                let Some(src) = attribute_value(tag, "src") else {
+                    return;
+                };
+
+                let alt = attribute_value(tag, "alt").unwrap_or_default();
+
+                out.push_str("![");
+                push_text(out, &alt);
+                out.push_str("](");
+                push_destination(out, &self.resolve(&src));
+                push_title(out, tag);
+                out.push(')');
+            }
+            _ => self.inline_into(children, out),
+        }
+    }
+
+    fn block(&self, name: &str, tag: &HTMLTag<'a>) -> Option<Block> {
+        let children = tag.children().top().as_slice();
+
+        let block = match name {
+            "p" => {
+                let text = normalize_inline(&self.inline(children));
+                Block::new(escape_block_start(text))
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = (name.as_bytes()[1] - b'0') as usize;
+                let text = normalize_inline(&self.inline(children).replace("\\\n", " "));
+                Block::new(format!("{} {}", "#".repeat(level), text))
+            }
+            "hr" => Block::new(String::from("---")),
+            "ul" | "ol" => Block {
+                text: self.list(tag, name == "ol"),
+                is_list: true,
+            },
+            "pre" => Block::new(self.code_block(tag)),
+            "blockquote" => {
+                let inner = join_blocks(self.blocks(children));
+                let quoted = inner
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            String::from(">")
+                        } else {
+                            format!("> {}", line)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Block::new(quoted)
+            }
+            "table" => Block::new(self.table(tag)),
+            _ => return None,
+        };
+
+        (!block.text.is_empty()).then_some(block)
+    }
+
+    fn list(&self, tag: &HTMLTag<'a>, ordered: bool) -> String {
+        let mut number = attribute_value(tag, "start")
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let mut items: Vec<String> = Vec::new();
+        let mut item_width = 0;
+
+        for &handle in tag.children().top().iter() {
+            let Some(child) = self.tag(handle) else {
+                continue;
+            };
+
+            let is_item = child.name().as_bytes().eq_ignore_ascii_case(b"li");
+
+            let marker = if ordered {
+                format!("{}. ", number)
+            } else {
+                String::from("- ")
+            };
+
+            let blocks = if is_item {
+                self.blocks(child.children().top().as_slice())
+            } else {
+                // Lists directly nested in another list are invalid, but common.
+                // Attach them to the previous item.
+                self.blocks(&[handle])
+            };
+
+            let mut body = String::new();
+            for (idx, block) in blocks.iter().enumerate() {
+                if idx > 0 {
+                    body.push_str(if block.is_list { "\n" } else { "\n\n" });
+                }
+                body.push_str(&block.text);
+            }
+
+            match items.last_mut() {
+                Some(last) if !is_item => {
+                    if !body.is_empty() {
+                        last.push('\n');
+                        last.push_str(&" ".repeat(item_width));
+                        last.push_str(&indent(&body, item_width));
+                    }
+                }
+                _ => {
+                    items.push(format!("{}{}", marker, indent(&body, marker.len())));
+                    item_width = marker.len();
+                    number += 1;
+                }
+            }
+        }
+
+        items.join("\n")
+    }
+
+    fn code_block(&self, tag: &HTMLTag<'a>) -> String {
+        let code = tag
+            .children()
+            .top()
+            .iter()
+            .filter_map(|&h| self.tag(h))
+            .find(|t| t.name().as_bytes().eq_ignore_ascii_case(b"code"));
+
+        let language = code
+            .and_then(language_class)
+            .or_else(|| language_class(tag))
+            .unwrap_or_default();
+
+        let text = entities::decode(&tag.inner_text(self.parser)).into_owned();
+        let text = text
+            .strip_prefix('\n')
+            .unwrap_or(&text)
+            .trim_end_matches('\n');
+
+        let fence = "`".repeat(longest_run(text, '`').max(2) + 1);
+
+        format!("{}{}\n{}\n{}", fence, language, text, fence)
+    }
+
+    fn table(&self, tag: &HTMLTag<'a>) -> String {
+        let mut caption = None;
+        let mut rows: Vec<Vec<String>> = Vec::new();
+
+        let mut row_handles = Vec::new();
+        for &handle in tag.children().top().iter() {
+            let Some(child) = self.tag(handle) else {
+                continue;
+            };
+
+            match child.name().as_utf8_str().to_ascii_lowercase().as_str() {
+                "caption" => {
+                    caption = Some(normalize_inline(
+                        &self.inline(child.children().top().as_slice()),
+                    ))
+                }
+                "thead" | "tbody" | "tfoot" => row_handles.extend(
+                    child
+                        .children()
+                        .top()
+                        .iter()
+                        .filter_map(|&h| self.tag(h))
+                        .filter(|t| t.name().as_bytes().eq_ignore_ascii_case(b"tr")),
+                ),
+                "tr" => row_handles.push(child),
+                _ => {}
+            }
+        }
+
+        for row in row_handles {
+            let cells = row
+                .children()
+                .top()
+                .iter()
+                .filter_map(|&h| self.tag(h))
+                .filter(|t| {
+                    let name = t.name().as_bytes();
+                    name.eq_ignore_ascii_case(b"td") || name.eq_ignore_ascii_case(b"th")
+                })
+                .map(|cell| {
+                    let text = self.inline(cell.children().top().as_slice());
+                    normalize_inline(&text.replace("\\\n", " ")).replace('|', "\\|")
+                })
+                .collect::<Vec<_>>();
+
+            rows.push(cells);
+        }
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return caption.unwrap_or_default();
+        }
+
+        let format_row = |row: &[String]| {
+            let mut line = String::from("|");
+            for idx in 0..columns {
+                line.push(' ');
+                line.push_str(row.get(idx).map(String::as_str).unwrap_or(""));
+                line.push_str(" |");
+            }
+            line
+        };
+
+        let mut lines = Vec::with_capacity(rows.len() + 2);
+        if let Some(caption) = caption.filter(|c| !c.is_empty()) {
+            lines.push(caption);
+            lines.push(String::new());
+        }
+
+        lines.push(format_row(&rows[0]));
+        lines.push(format!("|{}", " --- |".repeat(columns)));
+        lines.extend(rows[1..].iter().map(|row| format_row(row)));
+
+        lines.join("\n")
+    }
+}
+
+/// Extracts the language from a `language-*` or `lang-*` class
+fn language_class(tag: &HTMLTag<'_>) -> Option<String> {
+    tag.attributes()
+        .class_iter()?
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .map(String::from)
+}
+
+fn longest_run(text: &str, needle: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+
+    for c in text.chars() {
+        if c == needle {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+
+    longest
+}
+
+fn push_space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with(' ') && !out.ends_with('\n') {
+        out.push(' ');
+    }
+}
+
+/// Pushes text, collapsing whitespace and escaping Markdown syntax characters
+///
+/// `<` is always escaped, since it could start inline HTML or an autolink, and `&` is
+/// escaped where it could start an entity or character reference.
+fn push_text(out: &mut String, text: &str) {
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_ascii_whitespace() => push_space(out),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '&' if chars
+                .peek()
+                .is_some_and(|&n| n == '#' || n.is_ascii_alphanumeric()) =>
+            {
+                out.push_str("\\&");
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+/// Pushes raw HTML into inline content, joining its lines so that it stays within one line
+/// and cannot end the paragraph or start a different kind of block
+fn push_html(out: &mut String, html: &str) {
+    for (idx, line) in html.lines().enumerate() {
+        let line = line.trim_matches([' ', '\t']);
+        if idx > 0 {
+            push_space(out);
+        }
+        out.push_str(line);
+    }
+}
+
+fn push_code_span(out: &mut String, code: &str) {
+    if code.is_empty() {
+        return;
+    }
+
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let pad = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+
+    out.push_str(&fence);
+    out.push_str(pad);
+    out.push_str(code);
+    out.push_str(pad);
+    out.push_str(&fence);
+}
+
+fn push_destination(out: &mut String, url: &str) {
+    if url.contains([' ', '(', ')', '<', '>']) {
+        out.push('<');
+        out.push_str(&url.replace('<', "%3C").replace('>', "%3E"));
+        out.push('>');
+    } else {
+        out.push_str(url);
+    }
+}
+
+fn push_title(out: &mut String, tag: &HTMLTag<'_>) {
+    if let Some(title) = attribute_value(tag, "title").filter(|t| !t.is_empty()) {
+        out.push_str(" \"");
+        out.push_str(&title.replace('"', "\\\""));
+        out.push('"');
+    }
+}
+
+/// Returns the decoded value of an attribute, or `None` if the attribute is absent or has no value
+fn attribute_value(tag: &HTMLTag<'_>, name: &str) -> Option<String> {
+    tag.attributes()
+        .get(name)
+        .flatten()
+        .map(|value| entities::decode(&value.as_utf8_str()).into_owned())
+}
+
+/// Wraps inline content in emphasis delimiters, moving surrounding whitespace outside of them
+fn wrap(out: &mut String, content: &str, delimiter: &str) {
+    let trimmed = content.trim_matches(' ');
+    if trimmed.is_empty() {
+        out.push_str(content);
+        return;
+    }
+
+    if content.starts_with(' ') {
+        push_space(out);
+    }
+
+    out.push_str(delimiter);
+    out.push_str(trimmed);
+    out.push_str(delimiter);
+
+    if content.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+/// Collapses whitespace in rendered inline content and trims it
+fn normalize_inline(inline: &str) -> String {
+    let mut out = String::with_capacity(inline.len());
+
+    for line in inline.split("\\\n") {
+        let line = line.trim_matches(' ');
+        if !out.is_empty() && !line.is_empty() {
+            out.push_str("\\\n");
+        }
+        out.push_str(line);
+    }
+
+    out
+}
+
+/// Escapes characters at the start of each line of a paragraph that would otherwise start a different kind of block
+fn escape_block_start(text: String) -> String {
+    text.split("\\\n")
+        .map(escape_line_start)
+        .collect::<Vec<_>>()
+        .join("\\\n")
+}
+
+fn escape_line_start(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+
+    if digits > 0 && matches!(bytes.get(digits), Some(b'.' | b')')) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+
+    let escape = match bytes.first() {
+        Some(b'#' | b'>' | b'-' | b'+' | b'=' | b'|') => true,
+        // code fences, where a backtick fence cannot have backticks after it
+        Some(b'~') => line.starts_with("~~~"),
+        Some(b'`') => {
+            let run = bytes.iter().take_while(|&&b| b == b'`').count();
+            run >= 3 && !line[run..].contains('`')
+        }
+        // thematic breaks
+        Some(&c @ (b'*' | b'_')) => {
+            bytes.iter().all(|&b| b == c || b == b' ' || b == b'\t')
+                && bytes.iter().filter(|&&b| b == c).count() >= 3
+        }
+        _ => false,
+    };
+
+    if escape {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let pad = " ".repeat(width);
+
+    text.lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            if idx == 0 || line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{}{}", pad, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
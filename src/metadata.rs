@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use crate::entities::{self, decode_attribute};
+use crate::util::collapse_whitespace;
+use crate::{Node, NodeHandle, VDom};
+
+/// Property values by property name, where repeated properties keep all of their values in document order
+pub type Properties = BTreeMap<String, Vec<String>>;
+
+/// The namespaces of OpenGraph properties other than `og:` itself, which are kept as is in [`Metadata::open_graph`]
+const OPEN_GRAPH_NAMESPACES: [&str; 6] = [
+    "article:", "book:", "books:", "music:", "profile:", "video:",
+];
+
+/// A `<link>` element with a `rel` and an `href` attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkElement {
+    /// The `<link>` element
+    pub handle: NodeHandle,
+    /// The lowercase link types of the `rel` attribute
+    pub rel: Vec<String>,
+    /// The `href` attribute, which is not resolved
+    pub href: String,
+    /// The `type` attribute
+    pub media_type: Option<String>,
+    /// The `hreflang` attribute
+    pub hreflang: Option<String>,
+    /// The `media` attribute
+    pub media: Option<String>,
+    /// The `sizes` attribute
+    pub sizes: Option<String>,
+    /// The `title` attribute
+    pub title: Option<String>,
+}
+
+impl LinkElement {
+    /// Returns whether one of the link types of the `rel` attribute is `rel`, compared case-insensitively
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rel.iter().any(|r| r.eq_ignore_ascii_case(rel))
+    }
+}
+
+/// The metadata of a document, see [`parse_metadata()`]
+///
+/// Attribute values and text are entity-decoded, and URLs are not resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Metadata {
+    /// The text of the first `<title>` element, with whitespace collapsed
+    pub title: Option<String>,
+    /// The `charset` attribute of the first `<meta charset>` element
+    pub charset: Option<String>,
+    /// The `content` of `<meta name>` elements by lowercase name
+    pub names: Properties,
+    /// The `content` of `<meta property>` elements by property
+    pub properties: Properties,
+    /// The `content` of `<meta http-equiv>` elements by lowercase header name
+    pub http_equiv: Properties,
+    /// The OpenGraph properties
+    ///
+    /// These are the `og:` properties without their prefix, such as `title` and `image:width`,
+    /// and the properties of the object types, such as `article:published_time`, which keep their prefix.
+    pub open_graph: Properties,
+    /// The Twitter card properties without their `twitter:` prefix, such as `card` and `title`
+    ///
+    /// These come from both `<meta name>` and `<meta property>` elements, since sites use either.
+    pub twitter: Properties,
+    /// The `<link>` elements that have a `rel` and an `href` attribute, in document order
+    pub links: Vec<LinkElement>,
+    /// The trimmed contents of `<script type="application/ld+json">` elements, which are not parsed
+    pub json_ld: Vec<String>,
+}
+
+impl Metadata {
+    /// Returns the links with the given link type, see [`LinkElement::has_rel()`]
+    pub fn links_with_rel<'m>(
+        &'m self,
+        rel: &'m str,
+    ) -> impl Iterator<Item = &'m LinkElement> + 'm {
+        self.links.iter().filter(move |link| link.has_rel(rel))
+    }
+
+    /// Returns the `href` of the first `<link rel="canonical">`
+    pub fn canonical(&self) -> Option<&str> {
+        self.links_with_rel("canonical")
+            .next()
+            .map(|link| link.href.as_str())
+    }
+
+    /// Returns the `<link rel="icon">` elements, which includes `rel="shortcut icon"`
+    pub fn icons(&self) -> impl Iterator<Item = &LinkElement> + '_ {
+        self.links_with_rel("icon")
+    }
+
+    /// Returns the `<link rel="alternate">` elements, such as feeds and translations
+    pub fn alternates(&self) -> impl Iterator<Item = &LinkElement> + '_ {
+        self.links_with_rel("alternate")
+    }
+
+    /// Returns the `href` of the first `<link rel="manifest">`
+    pub fn manifest(&self) -> Option<&str> {
+        self.links_with_rel("manifest")
+            .next()
+            .map(|link| link.href.as_str())
+    }
+}
+
+/// Collects the metadata of a document from its `<title>`, `<meta>`, `<link>` and JSON-LD `<script>` elements
+///
+/// Elements are found anywhere in the document, not just in the `<head>`,
+/// since documents without end tags often end up with them in the `<body>`.
+///
+/// # Example
+/// ```
+/// let input = r#"<html><head>
+///   <title>Example &amp; Co</title>
+///   <meta name="description" content="An example">
+///   <meta property="og:image" content="https://example.com/a.png">
+///   <meta property="og:image" content="https://example.com/b.png">
+///   <meta name="twitter:card" content="summary">
+///   <link rel="canonical" href="https://example.com/">
+///   <script type="application/ld+json">{"@type": "Organization"}</script>
+/// </head></html>"#;
+///
+/// let dom = tl::parse(input, Default::default()).unwrap();
+/// let metadata = tl::metadata::parse_metadata(&dom);
+///
+/// assert_eq!(metadata.title.as_deref(), Some("Example & Co"));
+/// assert_eq!(metadata.names["description"], ["An example"]);
+/// assert_eq!(metadata.open_graph["image"], ["https://example.com/a.png", "https://example.com/b.png"]);
+/// assert_eq!(metadata.twitter["card"], ["summary"]);
+/// assert_eq!(metadata.canonical(), Some("https://example.com/"));
+/// assert_eq!(metadata.json_ld, [r#"{"@type": "Organization"}"#]);
+/// ```
+pub fn parse_metadata(dom: &VDom<'_>) -> Metadata {
+    let parser = dom.parser();
+    let mut metadata = Metadata::default();
+
+    let mut elements = dom.elements();
+
+    while let Some((handle, _)) = elements.next() {
+        let Some(Node::Tag(tag)) = handle.get(parser) else {
+            continue;
+        };
+
+        let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+        match name.as_str() {
+            "title" => {
+                if metadata.title.is_none() {
+                    let text = entities::decode(&tag.inner_text(parser)).into_owned();
+                    metadata.title = Some(collapse_whitespace(&text));
+                }
+                elements.skip_subtree();
+            }
+            "meta" => {
+                if let Some(charset) = decode_attribute(tag, "charset") {
+                    metadata
+                        .charset
+                        .get_or_insert_with(|| charset.trim().to_string());
+                }
+
+                let Some(content) = decode_attribute(tag, "content") else {
+                    continue;
+                };
+
+                if let Some(name) = decode_attribute(tag, "name") {
+                    let name = name.trim().to_ascii_lowercase();
+
+                    if let Some(property) = name.strip_prefix("twitter:") {
+                        push(&mut metadata.twitter, property, &content);
+                    }
+
+                    push(&mut metadata.names, &name, &content);
+                }
+
+                if let Some(property) = decode_attribute(tag, "property") {
+                    let property = property.trim();
+
+                    if let Some(property) = property.strip_prefix("og:") {
+                        push(&mut metadata.open_graph, property, &content);
+                    } else if OPEN_GRAPH_NAMESPACES
+                        .iter()
+                        .any(|namespace| property.starts_with(namespace))
+                    {
+                        push(&mut metadata.open_graph, property, &content);
+                    } else if let Some(property) = property.strip_prefix("twitter:") {
+                        push(&mut metadata.twitter, property, &content);
+                    }
+
+                    push(&mut metadata.properties, property, &content);
+                }
+
+                if let Some(header) = decode_attribute(tag, "http-equiv") {
+                    push(
+                        &mut metadata.http_equiv,
+                        &header.trim().to_ascii_lowercase(),
+                        &content,
+                    );
+                }
+            }
+            "link" => {
+                let (Some(rel), Some(href)) =
+                    (decode_attribute(tag, "rel"), decode_attribute(tag, "href"))
+                else {
+                    continue;
+                };
+
+                metadata.links.push(LinkElement {
+                    handle,
+                    rel: rel
+                        .split_ascii_whitespace()
+                        .map(str::to_ascii_lowercase)
+                        .collect(),
+                    href: href.trim().to_string(),
+                    media_type: decode_attribute(tag, "type"),
+                    hreflang: decode_attribute(tag, "hreflang"),
+                    media: decode_attribute(tag, "media"),
+                    sizes: decode_attribute(tag, "sizes"),
+                    title: decode_attribute(tag, "title"),
+                });
+            }
+            "script" => {
+                let is_json_ld = decode_attribute(tag, "type").is_some_and(|media_type| {
+                    let essence = media_type.split(';').next().unwrap_or_default();
+                    essence.trim().eq_ignore_ascii_case("application/ld+json")
+                });
+
+                if is_json_ld {
+                    // the contents of scripts are raw text, so they are not entity-decoded
+                    metadata
+                        .json_ld
+                        .push(tag.inner_text(parser).trim().to_string());
+                }
+                elements.skip_subtree();
+            }
+            // an SVG <title> is not the title of the document
+            "svg" => elements.skip_subtree(),
+            _ => {}
+        }
+    }
+
+    metadata
+}
+
+/// Appends a value to the values of a property
+fn push(properties: &mut Properties, name: &str, value: &str) {
+    properties
+        .entry(name.to_string())
+        .or_default()
+        .push(value.to_string());
+}
@@ -0,0 +1,525 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::entities::{self, decode_attribute};
+use crate::{links, HTMLTag, Node, NodeHandle, Parser, VDom};
+
+/// Items nested deeper than this are left out, so that deeply nested documents cannot overflow the stack
+const MAX_DEPTH: usize = 256;
+
+/// An item, which has types and a list of named properties
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    /// The element that the item was created from, which has the `itemscope` or `typeof` attribute
+    pub handle: NodeHandle,
+    /// The types of the item, such as `https://schema.org/Product`
+    pub types: Vec<String>,
+    /// The global identifier of the item, from the `itemid` or `resource` attribute
+    pub id: Option<String>,
+    /// The values of the properties by name, where every name has at least one value
+    pub properties: BTreeMap<String, Vec<Value>>,
+}
+
+impl Item {
+    /// Returns the first value of a property
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.properties.get(name)?.first()
+    }
+
+    /// Returns all values of a property, or an empty slice if the item does not have it
+    pub fn get_all(&self, name: &str) -> &[Value] {
+        self.properties.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns whether `item_type` is one of the types of this item
+    pub fn has_type(&self, item_type: &str) -> bool {
+        self.types.iter().any(|t| t == item_type)
+    }
+}
+
+/// The value of a property of an [`Item`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// A text value, such as the text of an element or the `content` of a `<meta>` element
+    Text(String),
+    /// A URL value, such as the `href` of an `<a>` element, resolved if a document URL was given
+    Url(String),
+    /// A nested item
+    Item(Item),
+}
+
+impl Value {
+    /// Returns the text or URL of this value, or `None` if it is an item
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(value) | Value::Url(value) => Some(value),
+            Value::Item(_) => None,
+        }
+    }
+
+    /// Returns the nested item of this value
+    pub fn as_item(&self) -> Option<&Item> {
+        match self {
+            Value::Item(item) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+/// Extracts the top-level microdata items of a document
+///
+/// This follows the [microdata algorithm] of the HTML standard: top-level items are the elements with an `itemscope`
+/// and without an `itemprop` attribute. The properties of an item are found in its descendants and in the elements
+/// that its `itemref` attribute refers to, but not inside of nested items.
+/// The value of a property depends on the element, which is the `content` of a `<meta>`, the URL in the `href` of
+/// an `<a>` or the `src` of an `<img>`, the `datetime` of a `<time>`, a nested item for an element with an `itemscope`,
+/// and the text otherwise.
+///
+/// If `document_url` is given, URLs are resolved against it, or against the `<base href>` of the document if it has one.
+/// Properties that would make an item contain itself are left out.
+///
+/// [microdata algorithm]: https://html.spec.whatwg.org/multipage/microdata.html
+///
+/// # Example
+/// ```
+/// use tl::microdata::Value;
+///
+/// let input = r#"
+///   <div itemscope itemtype="https://schema.org/SoftwareApplication" itemref="rating">
+///     <span itemprop="name">tl</span>
+///     <a itemprop="url" href="/tl">Homepage</a>
+///   </div>
+///   <div id="rating" itemprop="aggregateRating" itemscope itemtype="https://schema.org/AggregateRating">
+///     <meta itemprop="ratingValue" content="4.9">
+///   </div>"#;
+///
+/// let dom = tl::parse(input, Default::default()).unwrap();
+/// let items = tl::microdata::parse_microdata(&dom, Some("https://example.com/"));
+///
+/// assert_eq!(items.len(), 1);
+/// assert_eq!(items[0].types, ["https://schema.org/SoftwareApplication"]);
+/// assert_eq!(items[0].get("name"), Some(&Value::Text("tl".into())));
+/// assert_eq!(items[0].get("url"), Some(&Value::Url("https://example.com/tl".into())));
+///
+/// let rating = items[0].get("aggregateRating").and_then(Value::as_item).unwrap();
+/// assert_eq!(rating.get("ratingValue").and_then(Value::as_str), Some("4.9"));
+/// ```
+pub fn parse_microdata(dom: &VDom<'_>, document_url: Option<&str>) -> Vec<Item> {
+    let parser = dom.parser();
+    let base_url = document_url.map(|url| links::base_url(dom, url));
+
+    let mut order = HashMap::new();
+    let mut ids = HashMap::new();
+    let mut roots = Vec::new();
+
+    for (index, (handle, _)) in dom.elements().enumerate() {
+        order.insert(handle, index);
+
+        let Some(tag) = handle.get(parser).and_then(Node::as_tag) else {
+            continue;
+        };
+
+        if let Some(id) = decode_attribute(tag, "id") {
+            ids.entry(id).or_insert(handle);
+        }
+
+        if tag.attributes().contains("itemscope") && !tag.attributes().contains("itemprop") {
+            roots.push(handle);
+        }
+    }
+
+    let extractor = Microdata {
+        parser,
+        base_url: base_url.as_deref(),
+        order,
+        ids,
+    };
+
+    roots
+        .into_iter()
+        .filter_map(|root| extractor.item(root, &mut Vec::new()))
+        .collect()
+}
+
+/// The state that is shared by all items of a document
+struct Microdata<'p, 'a> {
+    parser: &'p Parser<'a>,
+    base_url: Option<&'p str>,
+    /// The position of every element in document order
+    order: HashMap<NodeHandle, usize>,
+    /// The first element with each id
+    ids: HashMap<String, NodeHandle>,
+}
+
+impl<'p, 'a> Microdata<'p, 'a> {
+    /// Builds the item of an element with an `itemscope` attribute
+    ///
+    /// `ancestors` are the items that are currently being built, which this item must not contain.
+    fn item(&self, handle: NodeHandle, ancestors: &mut Vec<NodeHandle>) -> Option<Item> {
+        let tag = handle.get(self.parser)?.as_tag()?;
+
+        if ancestors.len() >= MAX_DEPTH || ancestors.contains(&handle) {
+            return None;
+        }
+
+        let types = decode_attribute(tag, "itemtype")
+            .map(|types| unique_tokens(&types))
+            .unwrap_or_default();
+
+        // an item only has a global identifier if it has a type
+        let id = decode_attribute(tag, "itemid")
+            .filter(|_| !types.is_empty())
+            .map(|id| self.resolve(&id));
+
+        let mut item = Item {
+            handle,
+            types,
+            id,
+            properties: BTreeMap::new(),
+        };
+
+        ancestors.push(handle);
+
+        for property in self.crawl(handle, tag) {
+            let Some(tag) = property.get(self.parser).and_then(Node::as_tag) else {
+                continue;
+            };
+
+            let value = if tag.attributes().contains("itemscope") {
+                match self.item(property, ancestors) {
+                    Some(nested) => Value::Item(nested),
+                    None => continue,
+                }
+            } else {
+                element_value(self.parser, property, tag, |url| self.resolve(url))
+            };
+
+            let names = decode_attribute(tag, "itemprop").unwrap_or_default();
+            for name in unique_tokens(&names) {
+                item.properties.entry(name).or_default().push(value.clone());
+            }
+        }
+
+        ancestors.pop();
+
+        Some(item)
+    }
+
+    /// Returns the elements with an `itemprop` attribute that are properties of an item, in document order
+    fn crawl(&self, root: NodeHandle, tag: &HTMLTag<'_>) -> Vec<NodeHandle> {
+        let mut pending = children(self.parser, tag);
+
+        if let Some(refs) = decode_attribute(tag, "itemref") {
+            for id in refs.split_ascii_whitespace() {
+                if let Some(&element) = self.ids.get(id) {
+                    pending.push(element);
+                }
+            }
+        }
+
+        let mut memory = HashSet::from([root]);
+        let mut results = Vec::new();
+
+        while let Some(element) = pending.pop() {
+            if !memory.insert(element) {
+                continue;
+            }
+
+            let Some(tag) = element.get(self.parser).and_then(Node::as_tag) else {
+                continue;
+            };
+
+            if !tag.attributes().contains("itemscope") {
+                pending.extend(children(self.parser, tag));
+            }
+
+            if decode_attribute(tag, "itemprop").is_some_and(|names| !names.trim().is_empty()) {
+                results.push(element);
+            }
+        }
+
+        results.sort_by_key(|element| self.order.get(element).copied().unwrap_or(usize::MAX));
+        results
+    }
+
+    /// Resolves a URL against the base URL of the document, if there is one
+    fn resolve(&self, url: &str) -> String {
+        match self.base_url {
+            Some(base) => links::join(base, url).unwrap_or_else(|| url.to_string()),
+            None => url.to_string(),
+        }
+    }
+}
+
+/// Extracts the top-level RDFa Lite items of a document
+///
+/// [RDFa Lite] describes items with the `vocab`, `typeof`, `property`, `resource` and `prefix` attributes.
+/// Elements with a `typeof` attribute are items, whose types are expanded with the `vocab` or `prefix` in scope,
+/// so `typeof="Product"` in `vocab="https://schema.org/"` has the type `https://schema.org/Product`.
+/// Elements with a `property` attribute are properties of the closest item around them,
+/// and those that are items themselves are nested items. Property names are kept as written.
+///
+/// Values follow the same rules as [`parse_microdata()`], except that the `content` and `resource` attributes
+/// take precedence on any element.
+///
+/// [RDFa Lite]: https://www.w3.org/TR/rdfa-lite/
+///
+/// # Example
+/// ```
+/// let input = r#"
+///   <div vocab="https://schema.org/" typeof="Person">
+///     <span property="name">Jane</span>
+///     <div property="address" typeof="PostalAddress">
+///       <span property="addressLocality">Berlin</span>
+///     </div>
+///   </div>"#;
+///
+/// let dom = tl::parse(input, Default::default()).unwrap();
+/// let items = tl::microdata::parse_rdfa_lite(&dom, None);
+///
+/// assert_eq!(items[0].types, ["https://schema.org/Person"]);
+/// assert_eq!(items[0].get("name").and_then(|value| value.as_str()), Some("Jane"));
+///
+/// let address = items[0].get("address").and_then(|value| value.as_item()).unwrap();
+/// assert_eq!(address.types, ["https://schema.org/PostalAddress"]);
+/// ```
+pub fn parse_rdfa_lite(dom: &VDom<'_>, document_url: Option<&str>) -> Vec<Item> {
+    let parser = dom.parser();
+    let base_url = document_url.map(|url| links::base_url(dom, url));
+    let resolve = |url: &str| match &base_url {
+        Some(base) => links::join(base, url).unwrap_or_else(|| url.to_string()),
+        None => url.to_string(),
+    };
+
+    // items are collected in a flat list first, and nested when all of their properties are known
+    let mut items: Vec<Item> = Vec::new();
+    // the nested items of every item, by property name and position in the property values
+    let mut nested: Vec<Vec<(String, usize, usize)>> = Vec::new();
+    let mut roots = Vec::new();
+
+    // the scopes of the open elements that change the vocabulary, prefixes or current item
+    let mut scopes: Vec<(usize, Scope)> = Vec::new();
+
+    for (handle, depth) in dom.elements() {
+        while scopes.last().is_some_and(|&(d, _)| d >= depth) {
+            scopes.pop();
+        }
+
+        let Some(tag) = handle.get(parser).and_then(Node::as_tag) else {
+            continue;
+        };
+
+        let mut scope = scopes
+            .last()
+            .map(|(_, scope)| scope.clone())
+            .unwrap_or_default();
+        let mut changed = false;
+
+        if let Some(vocab) = decode_attribute(tag, "vocab") {
+            let vocab = vocab.trim();
+            scope.vocab = (!vocab.is_empty()).then(|| resolve(vocab));
+            changed = true;
+        }
+
+        if let Some(prefix) = decode_attribute(tag, "prefix") {
+            let mut tokens = prefix.split_ascii_whitespace();
+
+            while let (Some(name), Some(iri)) = (tokens.next(), tokens.next()) {
+                if let Some(name) = name.strip_suffix(':') {
+                    scope
+                        .prefixes
+                        .insert(name.to_ascii_lowercase(), iri.to_string());
+                }
+            }
+            changed = true;
+        }
+
+        let names = decode_attribute(tag, "property")
+            .map(|names| unique_tokens(&names))
+            .unwrap_or_default();
+        let parent = scope.item;
+
+        let value = if let Some(types) = decode_attribute(tag, "typeof") {
+            let index = items.len();
+
+            items.push(Item {
+                handle,
+                types: unique_tokens(&types)
+                    .iter()
+                    .map(|t| scope.expand(t))
+                    .collect(),
+                id: decode_attribute(tag, "resource").map(|id| resolve(id.trim())),
+                properties: BTreeMap::new(),
+            });
+            nested.push(Vec::new());
+
+            if names.is_empty() || parent.is_none() {
+                roots.push(index);
+            }
+
+            scope.item = Some(index);
+            changed = true;
+
+            Some(Err(index))
+        } else if !names.is_empty() {
+            let value = if let Some(content) = decode_attribute(tag, "content") {
+                Value::Text(content)
+            } else if let Some(resource) = decode_attribute(tag, "resource") {
+                Value::Url(resolve(resource.trim()))
+            } else {
+                element_value(parser, handle, tag, resolve)
+            };
+
+            Some(Ok(value))
+        } else {
+            None
+        };
+
+        if let (Some(value), Some(parent)) = (value, parent) {
+            for name in names {
+                let values = items[parent].properties.entry(name.clone()).or_default();
+
+                match &value {
+                    Ok(value) => values.push(value.clone()),
+                    Err(index) => {
+                        // a placeholder until the nested item is complete
+                        nested[parent].push((name, values.len(), *index));
+                        values.push(Value::Text(String::new()));
+                    }
+                }
+            }
+        }
+
+        if changed {
+            scopes.push((depth, scope));
+        }
+    }
+
+    let mut items: Vec<Option<Item>> = items.into_iter().map(Some).collect();
+
+    // nested items always come after the items that contain them, so they are complete when they are taken
+    for index in (0..items.len()).rev() {
+        for (name, position, child) in std::mem::take(&mut nested[index]) {
+            let Some(child) = items[child].take() else {
+                continue;
+            };
+
+            if let Some(item) = &mut items[index] {
+                if let Some(value) = item
+                    .properties
+                    .get_mut(&name)
+                    .and_then(|v| v.get_mut(position))
+                {
+                    *value = Value::Item(child);
+                }
+            }
+        }
+    }
+
+    roots
+        .into_iter()
+        .filter_map(|index| items[index].take())
+        .collect()
+}
+
+/// The vocabulary, prefixes and current item of an element in a document with RDFa Lite
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    vocab: Option<String>,
+    prefixes: BTreeMap<String, String>,
+    /// The index of the closest item around the element
+    item: Option<usize>,
+}
+
+impl Scope {
+    /// Expands a term or compact IRI of a `typeof` attribute
+    fn expand(&self, term: &str) -> String {
+        if let Some((prefix, reference)) = term.split_once(':') {
+            if let Some(iri) = self.prefixes.get(&prefix.to_ascii_lowercase()) {
+                return format!("{iri}{reference}");
+            }
+
+            // an absolute IRI
+            return term.to_string();
+        }
+
+        match &self.vocab {
+            Some(vocab) => format!("{vocab}{term}"),
+            None => term.to_string(),
+        }
+    }
+}
+
+/// Returns the value of a property element that is not an item
+fn element_value(
+    parser: &Parser<'_>,
+    handle: NodeHandle,
+    tag: &HTMLTag<'_>,
+    resolve: impl Fn(&str) -> String,
+) -> Value {
+    let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+    let url = |attribute: &str| {
+        let value = decode_attribute(tag, attribute).unwrap_or_default();
+        let value = value.trim();
+
+        // an empty URL has no value instead of the URL of the document
+        Value::Url(if value.is_empty() {
+            String::new()
+        } else {
+            resolve(value)
+        })
+    };
+
+    match name.as_str() {
+        "meta" => Value::Text(decode_attribute(tag, "content").unwrap_or_default()),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => url("src"),
+        "a" | "area" | "link" => url("href"),
+        "object" => url("data"),
+        "data" | "meter" => Value::Text(decode_attribute(tag, "value").unwrap_or_default()),
+        "time" => match decode_attribute(tag, "datetime") {
+            Some(datetime) => Value::Text(datetime),
+            None => Value::Text(text_content(parser, handle)),
+        },
+        _ => Value::Text(text_content(parser, handle)),
+    }
+}
+
+/// Returns the decoded text of all text nodes inside of an element
+fn text_content(parser: &Parser<'_>, handle: NodeHandle) -> String {
+    handle
+        .text_nodes(parser)
+        .filter_map(|(node, _)| node.get(parser).and_then(Node::as_raw))
+        .map(|raw| entities::decode(&raw.as_utf8_str()).into_owned())
+        .collect()
+}
+
+/// Returns the child elements of a tag in reverse order, so that they can be popped in document order
+fn children(parser: &Parser<'_>, tag: &HTMLTag<'_>) -> Vec<NodeHandle> {
+    let mut children: Vec<NodeHandle> = tag
+        .children()
+        .top()
+        .iter()
+        .copied()
+        .filter(|child| {
+            child
+                .get(parser)
+                .is_some_and(|node| node.as_tag().is_some())
+        })
+        .collect();
+
+    children.reverse();
+    children
+}
+
+/// Splits an attribute value on whitespace, without duplicate tokens
+fn unique_tokens(value: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+
+    for token in value.split_ascii_whitespace() {
+        if !tokens.iter().any(|t| t == token) {
+            tokens.push(token.to_string());
+        }
+    }
+
+    tokens
+}
@@ -1,12 +1,13 @@
 use super::{
-    constants,
     handle::NodeHandle,
+    limits::{Admission, Limits},
     tag::{Attributes, HTMLTag, Node},
+    tokenizer::{Token, Tokenizer},
 };
 use crate::InnerNodeHandle;
+use crate::ParserOptions;
 use crate::{bytes::Bytes, inline::vec::InlineVec, simd, ParseError};
-use crate::{stream::Stream, ParserOptions};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// A list of HTML nodes
 pub type Tree<'a> = Vec<Node<'a>>;
@@ -14,6 +15,9 @@ pub type Tree<'a> = Vec<Node<'a>>;
 /// Inline class vector
 pub type ClassVec = InlineVec<NodeHandle, 2>;
 
+/// Inline vector of the elements that have an id, ordered by handle
+pub(crate) type IdVec = InlineVec<NodeHandle, 1>;
+
 /// HTML Version (<!DOCTYPE>)
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[repr(C)]
@@ -27,14 +31,15 @@ pub enum HTMLVersion {
     /// Frameset HTML 4.01:
     FramesetHTML401,
 }
+
 /// The main HTML parser
 ///
 /// Users of this library are not supposed to directly construct this struct.
 /// Instead, users must call `tl::parse()` and use the returned `VDom`.
 #[derive(Debug)]
 pub struct Parser<'a> {
-    /// The inner stream that is used to iterate through the HTML source
-    pub(crate) stream: Stream<'a, u8>,
+    /// The tokenizer that reads the HTML source
+    pub(crate) tokenizer: Tokenizer<'a>,
     pub(crate) stack: Vec<NodeHandle>,
     /// Specified options for this HTML parser
     pub(crate) options: ParserOptions,
@@ -44,12 +49,21 @@ pub struct Parser<'a> {
     pub(crate) tags: Tree<'a>,
     /// The topmost HTML nodes
     pub(crate) ast: Vec<NodeHandle>,
-    /// A HashMap that maps Tag ID to a Node ID
-    pub(crate) ids: HashMap<Bytes<'a>, NodeHandle>,
-    /// A HashMap that maps Tag Class to a Node ID
+    /// A HashMap that maps Tag ID to the Node IDs that have it, ordered by handle
+    ///
+    /// The id refers to the first one.
+    pub(crate) ids: HashMap<Bytes<'a>, IdVec>,
+    /// A HashMap that maps Tag Class to the Node IDs that have it, ordered by handle
     pub(crate) classes: HashMap<Bytes<'a>, ClassVec>,
+    /// The nodes that were replaced by [`VDom::parse_fragment()`](crate::VDom::parse_fragment),
+    /// which are not found by id or class until they are removed by [`VDom::compact()`](crate::VDom::compact)
+    pub(crate) replaced: HashSet<NodeHandle>,
     /// The current HTML version, if set
     pub(crate) version: Option<HTMLVersion>,
+    /// The name of the character encoding that the input was decoded from, if parsed from bytes
+    pub(crate) encoding: Option<&'static str>,
+    /// The depth and node limits
+    limits: Limits<'a>,
 }
 
 impl<'a> Parser<'a> {
@@ -58,348 +72,588 @@ impl<'a> Parser<'a> {
             stack: Vec::with_capacity(4),
             options,
             tags: Vec::new(),
-            stream: Stream::new(input.as_bytes()),
+            tokenizer: Tokenizer::new(input, options),
             ast: Vec::new(),
             ids: HashMap::new(),
             classes: HashMap::new(),
+            replaced: HashSet::new(),
             version: None,
+            encoding: None,
+            limits: Limits::new(options),
         }
     }
 
     #[inline(always)]
-    fn register_tag(&mut self, node: Node<'a>) -> NodeHandle {
+    pub(crate) fn register_tag(&mut self, node: Node<'a>) -> NodeHandle {
         self.tags.push(node);
-        NodeHandle::new((self.tags.len() - 1) as u32)
+        NodeHandle::new((self.tags.len() - 1) as InnerNodeHandle)
     }
 
-    #[inline(always)]
-    fn skip_whitespaces(&mut self) {
-        self.read_while2(b' ', b'\n');
+    /// Stops parsing by skipping the rest of the input, optionally recording an error
+    #[cold]
+    fn stop(&mut self, error: Option<ParseError>) {
+        self.tokenizer.stop(error);
     }
 
-    fn read_to(&mut self, needle: u8) -> &'a [u8] {
-        let start = self.stream.idx;
-        let bytes = &self.stream.data()[start..];
-
-        let end = simd::find(bytes, needle).unwrap_or_else(|| self.stream.len() - start);
+    #[inline]
+    fn add_to_parent(&mut self, handle: NodeHandle) {
+        if let Some(last) = self.stack.last() {
+            let last = self
+                .tags
+                .get_mut(last.get_inner() as usize)
+                .unwrap()
+                .as_tag_mut()
+                .unwrap();
 
-        self.stream.idx += end;
-        self.stream.slice(start, start + end)
+            last._children.push(handle);
+        } else {
+            self.ast.push(handle);
+        }
     }
 
-    fn read_to3(&mut self, needle: [u8; 3]) -> &'a [u8] {
-        let start = self.stream.idx;
-        let bytes = &self.stream.data()[start..];
+    /// Closes the currently open element if its name matches `name`
+    ///
+    /// Returns the handle of the element that was closed.
+    fn close(&mut self, name: &Bytes<'a>) -> Option<NodeHandle> {
+        if self.limits.close(name) {
+            return None;
+        }
+
+        let closing_tag_matches_parent = self
+            .stack
+            .last()
+            .and_then(|last_handle| last_handle.get(self))
+            .and_then(|last_item| last_item.as_tag())
+            .is_some_and(|last_tag| last_tag.name() == name);
 
-        let end = simd::find3(bytes, needle).unwrap_or_else(|| self.stream.len() - start);
+        if !closing_tag_matches_parent {
+            return None;
+        }
 
-        self.stream.idx += end;
-        self.stream.slice(start, start + end)
+        self.stack.pop()
     }
 
-    fn read_while2(&mut self, needle1: u8, needle2: u8) -> Option<()> {
-        loop {
-            let ch = self.stream.current_cpy()?;
+    /// Registers a node and adds it to the currently open element
+    fn push_node(&mut self, node: Node<'a>) -> NodeHandle {
+        let handle = self.register_tag(node);
+        self.add_to_parent(handle);
+        handle
+    }
 
-            let eq1 = ch == needle1;
-            let eq2 = ch == needle2;
+    /// Adds a token to the tree
+    ///
+    /// `raw` is the source of the token, which is only used for start tags.
+    /// Returns the handle of the element that was completed by this token, if any.
+    /// That is the element closed by an end tag, or a void or self-closing element.
+    pub(crate) fn apply(&mut self, token: Token<'a>, raw: Bytes<'a>) -> Option<NodeHandle> {
+        let admission = match &token {
+            Token::Doctype { name, .. } => {
+                if simd::matches_case_insensitive(name.as_bytes(), *b"html") {
+                    self.version = Some(HTMLVersion::HTML5);
+                }
 
-            if !eq1 & !eq2 {
-                return Some(());
+                return None;
             }
+            // end tags never add nodes, so they are not limited
+            Token::EndTag { .. } => Admission::Node,
+            token => self.limits.admit(token, self.stack.len(), self.tags.len()),
+        };
 
-            self.stream.advance();
+        match admission {
+            Admission::Skip => return None,
+            Admission::Stop(error) => {
+                self.stop(error);
+                return None;
+            }
+            _ => {}
         }
-    }
 
-    fn read_ident(&mut self) -> Option<&'a [u8]> {
-        let start = self.stream.idx;
-        let bytes = &self.stream.data()[start..];
+        match token {
+            Token::Text { text, .. } => {
+                self.push_node(Node::Raw(text));
+            }
+            Token::Comment { comment, .. } => {
+                self.push_node(Node::Comment(comment));
+            }
+            Token::Doctype { .. } => {}
+            Token::StartTag {
+                name, attributes, ..
+            } => {
+                let this = self.push_node(Node::Tag(HTMLTag::new(
+                    name,
+                    attributes,
+                    InlineVec::new(),
+                    raw,
+                )));
+                self.track(this);
 
-        // If we do not find any characters that are not identifiers
-        // then we are probably at the end of the stream
-        let end = simd::search_non_ident(bytes).unwrap_or_else(|| self.stream.len() - start);
+                match admission {
+                    Admission::Open => self.stack.push(this),
+                    Admission::Node => return Some(this),
+                    _ => {}
+                }
+            }
+            Token::EndTag { name, span } => {
+                let handle = self.close(&name)?;
+                let tag = self
+                    .tags
+                    .get_mut(handle.get_inner() as usize)
+                    .unwrap()
+                    .as_tag_mut()
+                    .unwrap();
+
+                // extend the raw source of the element from its start tag to the end tag that was just read
+                if tag._raw.as_bytes_borrowed().is_some() {
+                    let input = self.tokenizer.input();
+                    let offset = tag._raw.as_ptr() as usize - input.as_ptr() as usize;
+
+                    tag._raw = input[offset..span.end].into();
+                }
 
-        // If we don't find any identifier characters, return `None`.
-        if end == 0 {
-            return None;
+                return Some(handle);
+            }
         }
 
-        self.stream.idx += end;
-        Some(self.stream.slice(start, start + end))
+        None
     }
 
-    fn skip_comment_with_start(&mut self, start: usize) -> &'a [u8] {
-        while !self.stream.is_eof() {
-            let idx = self.stream.idx;
+    pub(crate) fn parse_single(&mut self) -> Option<()> {
+        while !self.tokenizer.stream.is_eof() {
+            if let Some(token) = self.tokenizer.read_token() {
+                let raw = self.tokenizer.input()[token.span()].into();
+                self.apply(token, raw);
+            }
+        }
 
-            if self
-                .stream
-                .slice_len(idx, constants::COMMENT.len())
-                .eq(constants::COMMENT)
-            {
-                self.stream.advance_by(constants::COMMENT.len());
+        None
+    }
 
-                let is_end_of_comment = self.stream.expect_and_skip_cond(b'>');
+    /// Resolves an internal Node ID obtained from a NodeHandle to a Node
+    #[inline]
+    pub fn resolve_node_id(&self, id: InnerNodeHandle) -> Option<&Node<'a>> {
+        self.tags.get(id as usize)
+    }
 
-                if is_end_of_comment {
-                    return self.stream.slice(start, self.stream.idx);
+    /// Resolves an internal Node ID obtained from a NodeHandle to a Node
+    #[inline]
+    pub fn resolve_node_id_mut(&mut self, id: InnerNodeHandle) -> Option<&mut Node<'a>> {
+        self.tags.get_mut(id as usize)
+    }
+
+    /// Moves all nodes of another parser into this parser and returns the handles of its topmost nodes
+    ///
+    /// The handles of the moved nodes are offset by the number of nodes that are already in this parser,
+    /// and the id and class tracking tables are merged.
+    pub(crate) fn adopt(&mut self, other: Parser<'a>) -> Vec<NodeHandle> {
+        let offset = self.tags.len() as InnerNodeHandle;
+        let remap = |handle: NodeHandle| NodeHandle::new(handle.get_inner() + offset);
+
+        self.tags.reserve(other.tags.len());
+
+        for mut node in other.tags {
+            if let Node::Tag(tag) = &mut node {
+                let mut children = InlineVec::new();
+                for &child in tag._children.iter() {
+                    children.push(remap(child));
                 }
+                tag._children = children;
             }
 
-            self.stream.advance();
+            self.tags.push(node);
         }
 
-        &[]
-    }
-
-    fn parse_attribute(&mut self) -> Option<(&'a [u8], Option<&'a [u8]>)> {
-        let name = self.read_ident()?;
-        self.skip_whitespaces();
-
-        let has_value = self.stream.expect_and_skip_cond(b'=');
-        if !has_value {
-            return Some((name, None));
+        for (id, handles) in other.ids {
+            for &handle in handles.iter() {
+                track_handle(&mut self.ids, id.clone(), remap(handle));
+            }
         }
 
-        self.skip_whitespaces();
-
-        let value = if let Some(quote) = self.stream.expect_oneof_and_skip(b"\"'") {
-            self.read_to(quote)
-        } else {
-            self.read_to3([b' ', b'\n', b'>'])
-        };
+        for (class, handles) in other.classes {
+            for &handle in handles.iter() {
+                track_class(&mut self.classes, class.clone(), remap(handle));
+            }
+        }
 
-        Some((name, Some(value)))
+        other.ast.into_iter().map(remap).collect()
     }
 
-    fn parse_attributes(&mut self) -> Option<Attributes<'a>> {
-        let mut attributes = Attributes::new();
+    /// Copies the subtree rooted at `root` from another parser into this one
+    ///
+    /// The copied nodes own all of their data and are appended in document order.
+    /// Returns the handle of the copied root, or `None` if `root` does not exist in `other`.
+    pub(crate) fn import(&mut self, other: &Parser<'_>, root: NodeHandle) -> Option<NodeHandle> {
+        other.tags.get(root.get_inner() as usize)?;
 
-        loop {
-            self.skip_whitespaces();
+        // Collect the subtree in document order first so that the new handles are known before copying
+        let mut order = Vec::new();
+        let mut stack = vec![root];
 
-            let cur = self.stream.current_cpy()?;
+        while let Some(handle) = stack.pop() {
+            order.push(handle);
 
-            if simd::is_closing(cur) {
-                break;
+            if let Some(tag) = handle.get(other).and_then(Node::as_tag) {
+                stack.extend(tag._children.as_slice().iter().rev().copied());
             }
+        }
 
-            if let Some((key, value)) = self.parse_attribute() {
-                let has_value = value.is_some();
-                let value: Option<Bytes<'a>> = value.map(Into::into);
-
-                match key {
-                    b"id" => attributes.id = value,
-                    b"class" => attributes.class = value,
-                    _ => attributes.raw.insert(key.into(), value),
-                };
-
-                // Only advance past the delimiter if we read a value.
-                if has_value && !simd::is_closing(self.stream.current_cpy()?) {
-                    self.stream.advance();
+        let offset = self.tags.len() as InnerNodeHandle;
+        let remap: HashMap<NodeHandle, NodeHandle> = order
+            .iter()
+            .enumerate()
+            .map(|(idx, &handle)| (handle, NodeHandle::new(offset + idx as InnerNodeHandle)))
+            .collect();
+
+        self.tags.reserve(order.len());
+
+        for handle in order {
+            let mut node = match handle.get(other) {
+                Some(node) => node.clone().into_owned(),
+                None => Node::Raw(Bytes::new()),
+            };
+
+            if let Node::Tag(tag) = &mut node {
+                let mut children = InlineVec::new();
+                for child in tag._children.iter() {
+                    if let Some(&child) = remap.get(child) {
+                        children.push(child);
+                    }
                 }
-            } else {
-                // No valid attribute found; skip this character.
-                self.stream.advance();
+                tag._children = children;
             }
+
+            let handle = self.register_tag(node);
+            self.track(handle);
         }
 
-        Some(attributes)
+        remap.get(&root).copied()
     }
 
-    #[inline]
-    fn add_to_parent(&mut self, handle: NodeHandle) {
-        if let Some(last) = self.stack.last() {
-            let last = self
-                .tags
-                .get_mut(last.get_inner() as usize)
-                .unwrap()
-                .as_tag_mut()
-                .unwrap();
+    /// Adds the given node to the id and class tracking tables, if enabled
+    ///
+    /// An id refers to the element with the lowest handle that has it, so nodes added in document order
+    /// do not take over ids from earlier elements.
+    pub(crate) fn track(&mut self, handle: NodeHandle) {
+        let Some(tag) = self
+            .tags
+            .get(handle.get_inner() as usize)
+            .and_then(Node::as_tag)
+        else {
+            return;
+        };
 
-            last._children.push(handle);
-        } else {
-            self.ast.push(handle);
+        if self.options.is_tracking_classes() {
+            if let Some(class) = &tag._attributes.class {
+                track_class_names(&mut self.classes, class, handle);
+            }
+        }
+
+        if self.options.is_tracking_ids() {
+            if let Some(id) = &tag._attributes.id {
+                track_handle(&mut self.ids, id.clone(), handle);
+            }
         }
     }
 
-    fn read_end(&mut self) {
-        self.stream.advance();
+    /// Rebuilds the id and class tracking tables from all elements, except for the ones that were replaced
+    pub(crate) fn reindex(&mut self) {
+        self.ids.clear();
+        self.classes.clear();
 
-        let closing_tag_name = self.read_to(b'>');
+        if !self.options.is_tracking() {
+            return;
+        }
 
-        self.stream.expect_and_skip_cond(b'>');
+        for idx in 0..self.tags.len() {
+            let handle = NodeHandle::new(idx as InnerNodeHandle);
 
-        let closing_tag_matches_parent = self
-            .stack
-            .last()
-            .and_then(|last_handle| last_handle.get(self))
-            .and_then(|last_item| last_item.as_tag())
-            .is_some_and(|last_tag| last_tag.name() == closing_tag_name);
+            if !self.replaced.contains(&handle) {
+                self.track(handle);
+            }
+        }
+    }
 
-        if !closing_tag_matches_parent {
-            return;
+    /// Calls `f` with the attributes of the given element and updates the id and class tracking tables for its changes
+    ///
+    /// Returns `None` if `handle` does not refer to an element.
+    pub(crate) fn update_attributes<R, F>(&mut self, handle: NodeHandle, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Attributes<'a>) -> R,
+    {
+        let attributes = &mut self
+            .tags
+            .get_mut(handle.get_inner() as usize)?
+            .as_tag_mut()?
+            ._attributes;
+
+        // replaced nodes are not tracked
+        if !self.options.is_tracking() || self.replaced.contains(&handle) {
+            return Some(f(attributes));
         }
 
-        if let Some(handle) = self.stack.pop() {
-            let tag = self
-                .tags
-                .get_mut(handle.get_inner() as usize)
-                .unwrap()
-                .as_tag_mut()
-                .unwrap();
+        let old_id = attributes.id.clone();
+        let old_class = attributes.class.clone();
 
-            let ptr = self.stream.data().as_ptr() as usize;
-            let offset = tag._raw.as_ptr() as usize;
-            let offset = offset - ptr;
+        let result = f(attributes);
 
-            tag._raw = self.stream.slice(offset, self.stream.idx).into();
+        let new_id = attributes.id.clone();
+        let new_class = attributes.class.clone();
 
-            let (track_classes, track_ids) = (
-                self.options.is_tracking_classes(),
-                self.options.is_tracking_ids(),
-            );
-
-            if let (true, Some(bytes)) = (track_classes, &tag._attributes.class) {
-                let s = bytes
-                    .as_bytes_borrowed()
-                    .and_then(|x| std::str::from_utf8(x).ok())
-                    .map(|x| x.split_ascii_whitespace());
-
-                if let Some(s) = s {
-                    for class in s {
-                        self.classes
-                            .entry(class.into())
-                            .or_insert_with(InlineVec::new)
-                            .push(handle);
-                    }
+        if self.options.is_tracking_classes() && old_class != new_class {
+            if let Some(class) = old_class.as_ref().and_then(Bytes::try_as_utf8_str) {
+                for class in class.split_ascii_whitespace() {
+                    untrack_class(&mut self.classes, class.as_bytes(), handle);
                 }
             }
 
-            if let (true, Some(bytes)) = (track_ids, &tag._attributes.id) {
-                self.ids.insert(bytes.clone(), handle);
+            if let Some(class) = &new_class {
+                track_class_names(&mut self.classes, class, handle);
             }
         }
-    }
-
-    #[cold]
-    #[inline(never)]
-    fn read_markdown(&mut self) -> Option<()> {
-        let start = self.stream.idx - 1; // position of the < which is needed when registering the comment
-
-        self.stream.advance(); // skip !
 
-        let is_comment = self
-            .stream
-            .slice_len(self.stream.idx, 2)
-            .eq(constants::COMMENT);
-
-        if is_comment {
-            let comment = self.skip_comment_with_start(start);
-            let comment = self.register_tag(Node::Comment(comment.into()));
-            self.add_to_parent(comment);
-        } else {
-            let tag = self.read_ident()?;
+        if self.options.is_tracking_ids() && old_id != new_id {
+            if let Some(id) = &old_id {
+                untrack_handle(&mut self.ids, id.as_bytes(), handle);
+            }
 
-            self.skip_whitespaces();
+            if let Some(id) = new_id {
+                track_handle(&mut self.ids, id, handle);
+            }
+        }
 
-            if simd::matches_case_insensitive(tag, *b"doctype") {
-                let doctype = self.read_ident()?;
+        Some(result)
+    }
 
-                let html5 = simd::matches_case_insensitive(doctype, *b"html");
+    /// Removes all nodes that are not reachable from the topmost nodes and renumbers the remaining nodes in document order
+    ///
+    /// Returns a table that maps each old handle (by index) to its new handle, or `None` if the node was removed.
+    pub(crate) fn compact(&mut self) -> Vec<Option<NodeHandle>> {
+        let mut remap = vec![None; self.tags.len()];
+        let mut order = Vec::new();
+        let mut stack: Vec<NodeHandle> = self.ast.iter().rev().copied().collect();
+
+        while let Some(handle) = stack.pop() {
+            let idx = handle.get_inner() as usize;
+
+            // a node that is referenced more than once (or a dangling handle) is only kept once
+            match remap.get(idx) {
+                Some(None) => {}
+                _ => continue,
+            }
 
-                if html5 {
-                    self.version = Some(HTMLVersion::HTML5);
-                }
+            remap[idx] = Some(NodeHandle::new(order.len() as InnerNodeHandle));
+            order.push(idx);
 
-                self.skip_whitespaces();
-                self.stream.advance(); // skip >
+            if let Some(tag) = self.tags[idx].as_tag() {
+                stack.extend(tag._children.as_slice().iter().rev().copied());
             }
         }
 
-        Some(())
-    }
+        let resolve =
+            |handle: &NodeHandle| remap.get(handle.get_inner() as usize).copied().flatten();
+
+        let mut old: Vec<Option<Node<'a>>> = std::mem::take(&mut self.tags)
+            .into_iter()
+            .map(Some)
+            .collect();
 
-    fn parse_tag(&mut self) -> Option<()> {
-        let start = self.stream.idx;
+        self.tags.reserve(order.len());
 
-        self.stream.advance();
-        self.skip_whitespaces();
-        let cur = self.stream.current_cpy()?;
+        for idx in order {
+            let Some(mut node) = old[idx].take() else {
+                continue;
+            };
 
-        match cur {
-            b'/' => self.read_end(),
-            b'!' => {
-                self.read_markdown();
+            if let Node::Tag(tag) = &mut node {
+                let mut children = InlineVec::new();
+                for child in tag._children.iter().filter_map(resolve) {
+                    children.push(child);
+                }
+                tag._children = children;
             }
-            _ => {
-                let name = self.read_ident()?;
-                self.skip_whitespaces();
 
-                let attr = self.parse_attributes()?;
+            self.tags.push(node);
+        }
 
-                let is_self_closing = self.stream.expect_and_skip_cond(b'/');
+        self.ast = self.ast.iter().filter_map(resolve).collect();
 
-                self.stream.expect_and_skip(b'>')?;
+        // replaced nodes are unreachable, so all of them were removed,
+        // and the tables are rebuilt because the remaining nodes were reordered
+        self.replaced.clear();
+        self.reindex();
 
-                let this = self.register_tag(Node::Tag(HTMLTag::new(
-                    name.into(),
-                    attr,
-                    InlineVec::new(),
-                    self.stream.slice(start, self.stream.idx).into(),
-                )));
+        remap
+    }
 
-                self.add_to_parent(this);
+    /// Removes the given node and all of its descendants from the id and class tracking tables,
+    /// and excludes them from lookups until they are removed by [`Parser::compact()`]
+    pub(crate) fn untrack_subtree(&mut self, root: NodeHandle) {
+        let mut stack = vec![root];
 
-                // some tags are self closing, so even though there might not be a /,
-                // we don't always want to push them to the stack
-                // e.g. <br><p>Hello</p>
-                // <p> should not be a subtag of <br>
-                if !is_self_closing && !constants::VOID_TAGS.contains(&name) {
-                    self.stack.push(this);
-                }
+        while let Some(handle) = stack.pop() {
+            let Some(tag) = self
+                .tags
+                .get(handle.get_inner() as usize)
+                .and_then(Node::as_tag)
+            else {
+                continue;
+            };
+
+            if !self.replaced.insert(handle) {
+                continue;
             }
-        };
 
-        Some(())
-    }
+            stack.extend(tag._children.iter().copied());
 
-    pub(crate) fn parse_single(&mut self) -> Option<()> {
-        loop {
-            let cur = self.stream.current()?;
-
-            if *cur == b'<' {
-                self.parse_tag();
-            } else {
-                let raw = Node::Raw(self.read_to(b'<').into());
-                let handle = self.register_tag(raw);
-                self.add_to_parent(handle);
+            if let (true, Some(id)) = (self.options.is_tracking_ids(), &tag._attributes.id) {
+                untrack_handle(&mut self.ids, id.as_bytes(), handle);
+            }
+
+            if let (true, Some(classes)) = (
+                self.options.is_tracking_classes(),
+                tag._attributes.class_iter(),
+            ) {
+                for class in classes {
+                    untrack_class(&mut self.classes, class.as_bytes(), handle);
+                }
             }
         }
     }
 
-    /// Resolves an internal Node ID obtained from a NodeHandle to a Node
-    #[inline]
-    pub fn resolve_node_id(&self, id: InnerNodeHandle) -> Option<&Node<'a>> {
-        self.tags.get(id as usize)
-    }
+    /// Points all nodes and lookup tables into `to` if they borrow from `from`, see [`Bytes::rebase()`]
+    ///
+    /// The tokenizer is not changed.
+    pub(crate) unsafe fn rebase(&mut self, from: &[u8], to: *const u8) {
+        for node in &mut self.tags {
+            node.rebase(from, to);
+        }
 
-    /// Resolves an internal Node ID obtained from a NodeHandle to a Node
-    #[inline]
-    pub fn resolve_node_id_mut(&mut self, id: InnerNodeHandle) -> Option<&mut Node<'a>> {
-        self.tags.get_mut(id as usize)
+        self.limits.rebase(from, to);
+
+        self.ids = std::mem::take(&mut self.ids)
+            .into_iter()
+            .map(|(mut id, handle)| {
+                id.rebase(from, to);
+                (id, handle)
+            })
+            .collect();
+
+        self.classes = std::mem::take(&mut self.classes)
+            .into_iter()
+            .map(|(mut class, handles)| {
+                class.rebase(from, to);
+                (class, handles)
+            })
+            .collect();
     }
 
     pub(crate) fn parse(&mut self) -> Result<(), ParseError> {
-        if self.stream.len() > u32::MAX as usize {
+        // node handles have the same width as `Bytes` lengths, and every node spans at least one byte,
+        // so checking the input length is enough for both
+        if !crate::bytes::is_valid_length(self.tokenizer.input().len()) {
             return Err(ParseError::InvalidLength);
         }
 
-        while !self.stream.is_eof() {
+        while !self.tokenizer.stream.is_eof() {
             self.parse_single();
         }
 
-        Ok(())
+        self.finish()
+    }
+
+    /// Completes the tree once all tokens have been added, and returns the error that stopped parsing, if any
+    pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
+        match self.tokenizer.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Adds an element to the elements of a class name, which are kept ordered by handle and without duplicates
+pub(crate) fn track_class<'a>(
+    classes: &mut HashMap<Bytes<'a>, ClassVec>,
+    class: Bytes<'a>,
+    handle: NodeHandle,
+) {
+    track_handle(classes, class, handle);
+}
+
+/// Removes an element from the elements of a class name, and removes the class name if no elements are left
+pub(crate) fn untrack_class(
+    classes: &mut HashMap<Bytes<'_>, ClassVec>,
+    class: &[u8],
+    handle: NodeHandle,
+) {
+    untrack_handle(classes, class, handle);
+}
+
+/// Adds an element to the elements of an id or class name, which are kept ordered by handle and without duplicates
+///
+/// Elements are usually tracked in document order, so the handle is appended without moving any others.
+fn track_handle<'a, const N: usize>(
+    table: &mut HashMap<Bytes<'a>, InlineVec<NodeHandle, N>>,
+    key: Bytes<'a>,
+    handle: NodeHandle,
+) {
+    let handles = table.entry(key).or_insert_with(InlineVec::new);
+
+    let Err(position) = handles.as_slice().binary_search(&handle) else {
+        return;
+    };
+
+    handles.push(handle);
+
+    for idx in (position + 1..handles.len()).rev() {
+        let previous = handles[idx - 1];
+        if let Some(slot) = handles.get_mut(idx) {
+            *slot = previous;
+        }
+    }
+
+    if let Some(slot) = handles.get_mut(position) {
+        *slot = handle;
+    }
+}
+
+/// Removes an element from the elements of an id or class name, and removes the key if no elements are left
+fn untrack_handle<const N: usize>(
+    table: &mut HashMap<Bytes<'_>, InlineVec<NodeHandle, N>>,
+    key: &[u8],
+    handle: NodeHandle,
+) {
+    let Some(handles) = table.get_mut(key) else {
+        return;
+    };
+
+    if let Ok(idx) = handles.as_slice().binary_search(&handle) {
+        handles.remove(idx);
+    }
+
+    if handles.is_empty() {
+        table.remove(key);
+    }
+}
+
+/// Adds an element to the elements of each of its class names
+///
+/// The class names borrow from the input if the attribute value does.
+fn track_class_names<'a>(
+    classes: &mut HashMap<Bytes<'a>, ClassVec>,
+    class: &Bytes<'a>,
+    handle: NodeHandle,
+) {
+    if let Some(bytes) = class.as_bytes_borrowed() {
+        let names = std::str::from_utf8(bytes)
+            .ok()
+            .map(str::split_ascii_whitespace);
+
+        for name in names.into_iter().flatten() {
+            track_class(classes, name.into(), handle);
+        }
+    } else if let Some(names) = class.try_as_utf8_str() {
+        // attributes of nodes that were not read from the input own their data
+        for name in names.split_ascii_whitespace() {
+            track_class(classes, Bytes::from(name).into_owned(), handle);
+        }
     }
 }
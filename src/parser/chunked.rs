@@ -0,0 +1,330 @@
+use super::{
+    base::Parser,
+    tag::HTMLTag,
+    tokenizer::{find_raw_text_end, Token, Tokenizer},
+};
+use crate::charset::StreamDecoder;
+use crate::{OwnedVDom, ParseError, ParserOptions};
+use std::{io, sync::Arc};
+
+/// The number of bytes the tokenizer may look at past the end of a token
+///
+/// A token is only complete if the input extends beyond this, since more input could otherwise change it.
+const LOOKAHEAD: usize = 2;
+
+type ElementCallback<'cb> = Box<dyn FnMut(&HTMLTag<'_>, &Parser<'_>) + 'cb>;
+
+/// A push-based HTML parser that accepts its input in chunks
+///
+/// This is useful when the input arrives over time, for example when reading an HTTP response.
+/// Tags, attributes and comments may be split across chunk boundaries arbitrarily.
+///
+/// The document returned by [`ChunkedParser::finish()`] is built from the tokens that were read as the chunks arrived,
+/// so the input is only parsed once. It is the same as the one returned by [`parse_bytes()`](crate::parse_bytes)
+/// for the concatenated input, except that the character encoding is determined from the first 1024 bytes:
+/// without a byte order mark or a `<meta>` declaration, the input is decoded as UTF-8 if those bytes are valid UTF-8.
+///
+/// A callback can be registered with [`ChunkedParser::on_element()`] to inspect elements
+/// as soon as they are complete, before the rest of the input has been received.
+///
+/// `ChunkedParser` implements [`std::io::Write`], so a reader can be copied into it with [`std::io::copy`].
+/// [`parse_reader()`](crate::parse_reader) does exactly that.
+///
+/// # Example
+/// ```
+/// let mut links = Vec::new();
+///
+/// let mut parser = tl::ChunkedParser::new(tl::ParserOptions::default()).on_element(|tag, _| {
+///     if let Some(Some(href)) = tag.attributes().get("href") {
+///         links.push(href.as_utf8_str().into_owned());
+///     }
+/// });
+///
+/// parser.feed(b"<a href=\"/simple/t");
+/// parser.feed(b"l/\">tl</a><a hr");
+/// parser.feed(b"ef=\"/simple/serde/\">serde</a>");
+///
+/// let dom = parser.finish().unwrap();
+/// assert_eq!(dom.get_ref().children().len(), 2);
+/// assert_eq!(links, ["/simple/tl/", "/simple/serde/"]);
+/// ```
+pub struct ChunkedParser<'cb> {
+    /// The document read so far, whose nodes borrow from `text`
+    ///
+    /// Like in [`OwnedVDom`], the `'static` lifetime is not real and must never be exposed.
+    /// Whenever `text` is moved to a new allocation, the nodes are moved along with it.
+    tree: Parser<'static>,
+    /// The input decoded so far
+    text: String,
+    decoder: StreamDecoder,
+    /// The error that stopped parsing, if any
+    error: Option<ParseError>,
+    /// Whether a limit stopped parsing, in which case the rest of the input is ignored
+    stopped: bool,
+    /// The position in `text` up to which tokens have been added to `tree`
+    position: usize,
+    /// The offset and length of the name of the raw text element whose contents are read next
+    raw_text: Option<(usize, usize)>,
+    /// The position from which to look for the end of the text at `position`, if it continued past the end of the input
+    ///
+    /// The text is only read again once its end has been received, so that long text is not read again for every chunk.
+    pending_text: Option<usize>,
+    callback: Option<ElementCallback<'cb>>,
+}
+
+impl<'cb> ChunkedParser<'cb> {
+    /// Creates a new chunked parser
+    pub fn new(options: ParserOptions) -> Self {
+        Self {
+            tree: Parser::new("", options),
+            text: String::new(),
+            decoder: StreamDecoder::new(None),
+            error: None,
+            stopped: false,
+            position: 0,
+            raw_text: None,
+            pending_text: None,
+            callback: None,
+        }
+    }
+
+    /// Sets an encoding label from the transport layer, like [`parse_bytes_with_hint()`](crate::parse_bytes_with_hint)
+    ///
+    /// Unless the input starts with a byte order mark, a supported label determines the encoding right away,
+    /// so the first 1024 bytes do not have to be received before elements are passed to the callback.
+    pub fn encoding_hint(mut self, hint: &str) -> Self {
+        self.decoder = StreamDecoder::new(Some(hint));
+        self
+    }
+
+    /// Registers a callback that is called for every element as soon as it is complete
+    ///
+    /// An element is complete once its end tag has been read, or its start tag if it is a void or self-closing element.
+    /// Elements that are never closed are not passed to the callback.
+    /// Input is only read once its character encoding is known, see [`ChunkedParser::encoding_hint()`].
+    ///
+    /// The parser passed to the callback contains the elements read so far and can be used to inspect the children of the element.
+    /// Its input is the text decoded so far (see [`ChunkedParser::input()`]), which [`HTMLTag::boundaries()`] refers to.
+    pub fn on_element<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&HTMLTag<'_>, &Parser<'_>) + 'cb,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Appends a chunk of input
+    ///
+    /// If a callback is registered, it is called for all elements that are completed by this chunk.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.decode(chunk, false);
+        self.advance(false);
+    }
+
+    /// Returns the input decoded so far
+    ///
+    /// The input is only decoded once its character encoding is known, which may require up to 1024 bytes.
+    #[inline]
+    pub fn input(&self) -> &str {
+        &self.text
+    }
+
+    /// Completes the document
+    ///
+    /// Remaining elements are passed to the callback before the document is returned.
+    ///
+    /// # Errors
+    /// See [`parse()`](crate::parse)
+    pub fn finish(mut self) -> Result<OwnedVDom, ParseError> {
+        self.decode(&[], true);
+        self.advance(true);
+
+        let Self {
+            mut tree,
+            text,
+            decoder,
+            error,
+            ..
+        } = self;
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        tree.finish()?;
+        tree.encoding = decoder.encoding();
+
+        // move the text into the document, along with the nodes that borrow from it
+        let source: Arc<str> = Arc::from(text.as_str());
+
+        // SAFETY: `source` contains the same data as `text`, and the document keeps it alive
+        unsafe { tree.rebase(text.as_bytes(), source.as_ptr()) };
+
+        // SAFETY: see `OwnedVDom::parse()`
+        let input: &'static str = unsafe { &*Arc::as_ptr(&source) };
+        tree.tokenizer = Tokenizer::new(input, tree.options);
+        tree.tokenizer.stream.idx = input.len();
+
+        Ok(OwnedVDom::from_parts(tree, source))
+    }
+
+    /// Decodes a chunk of input and appends it to the text, moving the nodes along if the text is moved
+    fn decode(&mut self, chunk: &[u8], last: bool) {
+        let mut decoded = String::new();
+        self.decoder.decode(chunk, last, &mut decoded);
+
+        if self.text.capacity() - self.text.len() < decoded.len() {
+            // the text is moved by hand instead of letting it grow,
+            // so that the old allocation is still alive while the nodes are moved along
+            let capacity = (self.text.len() + decoded.len()).max(self.text.capacity() * 2);
+            let mut text = String::with_capacity(capacity);
+            text.push_str(&self.text);
+
+            // SAFETY: the new text starts with the same data, and replaces the old one below
+            unsafe { self.tree.rebase(self.text.as_bytes(), text.as_ptr()) };
+            self.text = text;
+        }
+
+        self.text.push_str(&decoded);
+    }
+
+    /// Points the tokenizer of the tree at the text, at the position where it stopped
+    fn attach(&mut self) {
+        // SAFETY: the tokenizer is detached again before the text is changed or dropped
+        let input: &'static [u8] = unsafe { &*(self.text.as_bytes() as *const [u8]) };
+
+        let mut tokenizer = Tokenizer::from_bytes(input, self.tree.options);
+        tokenizer.stream.idx = self.position;
+        tokenizer.raw_text = self
+            .raw_text
+            .map(|(offset, len)| &input[offset..offset + len]);
+
+        self.tree.tokenizer = tokenizer;
+    }
+
+    /// Detaches the tokenizer of the tree from the text, which may be moved afterwards
+    fn detach(&mut self) {
+        let tokenizer = &self.tree.tokenizer;
+        self.position = tokenizer.position();
+        // the name of a raw text element is kept as an offset, since the text may be moved
+        self.raw_text = tokenizer.raw_text.map(|name| {
+            (
+                name.as_ptr() as usize - self.text.as_ptr() as usize,
+                name.len(),
+            )
+        });
+
+        self.tree.tokenizer = Tokenizer::new("", self.tree.options);
+    }
+
+    /// Adds all complete tokens since the last call to the tree and passes completed elements to the callback
+    fn advance(&mut self, eof: bool) {
+        if self.error.is_some() || self.stopped {
+            return;
+        }
+
+        if !crate::bytes::is_valid_length(self.text.len()) {
+            self.error = Some(ParseError::InvalidLength);
+            return;
+        }
+
+        self.attach();
+        self.read_tokens(eof);
+        self.detach();
+    }
+
+    /// Reads tokens from the attached tokenizer until the end of the text, or until a token may be incomplete
+    fn read_tokens(&mut self, eof: bool) {
+        let Self {
+            tree,
+            error,
+            stopped,
+            pending_text,
+            callback,
+            ..
+        } = self;
+
+        if let Some(scanned) = pending_text.take() {
+            let input = tree.tokenizer.input();
+
+            let ended = match tree.tokenizer.raw_text {
+                Some(name) => find_raw_text_end(input, scanned, name).is_some(),
+                None => memchr::memchr(b'<', &input[scanned..]).is_some(),
+            };
+
+            if !eof && !ended {
+                *pending_text = Some(resume_position(input, scanned, tree.tokenizer.raw_text));
+                return;
+            }
+        }
+
+        while !tree.tokenizer.stream.is_eof() {
+            let start = tree.tokenizer.position();
+            let raw_text = tree.tokenizer.raw_text;
+            let token = tree.tokenizer.read_token();
+
+            if tree.tokenizer.error.is_some() {
+                // the input exceeds a limit, which more input cannot change
+                *error = tree.tokenizer.error.take();
+                return;
+            }
+
+            let end = tree.tokenizer.position();
+
+            let len = tree.tokenizer.input().len();
+
+            if !eof && end + LOOKAHEAD >= len {
+                // the token may continue in the next chunk
+                if end == len && matches!(token, Some(Token::Text { .. })) {
+                    *pending_text = Some(resume_position(tree.tokenizer.input(), start, raw_text));
+                }
+
+                tree.tokenizer.stream.idx = start;
+                tree.tokenizer.raw_text = raw_text;
+                return;
+            }
+
+            let Some(token) = token else {
+                continue;
+            };
+
+            let raw = tree.tokenizer.input()[token.span()].into();
+            let completed = tree.apply(token, raw);
+
+            if tree.tokenizer.position() != end {
+                // a limit stopped parsing
+                *error = tree.tokenizer.error.take();
+                *stopped = true;
+                return;
+            }
+
+            if let (Some(on_element), Some(tag)) = (
+                callback.as_mut(),
+                completed
+                    .and_then(|handle| handle.get(tree))
+                    .and_then(|node| node.as_tag()),
+            ) {
+                on_element(tag, tree);
+            }
+        }
+    }
+}
+
+/// Returns the position from which to look for the end of text that starts at `start` and continues past the end of the input
+///
+/// The end tag of a raw text element may already have begun.
+fn resume_position(input: &[u8], start: usize, raw_text: Option<&[u8]>) -> usize {
+    let overlap = raw_text.map_or(0, |name| name.len() + 2);
+    start.max(input.len().saturating_sub(overlap))
+}
+
+impl io::Write for ChunkedParser<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
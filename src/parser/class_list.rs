@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use super::{
+    base::{track_class, untrack_class},
+    handle::NodeHandle,
+    ClassVec,
+};
+use crate::{errors::ClassListError, Bytes};
+
+/// A mutable view of the class names of an HTML element, similar to `Element.classList` in browsers
+///
+/// Every change rewrites the `class` attribute canonically: each class name appears once,
+/// in the order of first appearance, separated by single spaces.
+/// Class names are compared to the raw attribute value like [`Attributes::is_class_member()`](crate::Attributes::is_class_member),
+/// so they must not contain whitespace or double quotes.
+///
+/// A class list obtained from [`Attributes::class_list_mut()`](crate::Attributes::class_list_mut) does not update the class tracking table of the parser.
+/// Use [`VDom::class_list_mut()`](crate::VDom::class_list_mut) for that.
+#[derive(Debug)]
+pub struct ClassList<'c, 'a> {
+    class: &'c mut Option<Bytes<'a>>,
+    index: Option<(NodeHandle, &'c mut HashMap<Bytes<'a>, ClassVec>)>,
+}
+
+impl<'c, 'a> ClassList<'c, 'a> {
+    /// Creates a class list for the given `class` attribute, which keeps the class tracking table in sync if `index` is set
+    pub(crate) fn new(
+        class: &'c mut Option<Bytes<'a>>,
+        index: Option<(NodeHandle, &'c mut HashMap<Bytes<'a>, ClassVec>)>,
+    ) -> Self {
+        Self { class, index }
+    }
+
+    /// Returns the class names without duplicates, in the order of first appearance
+    pub fn tokens(&self) -> Vec<String> {
+        let Some(class) = self.class.as_ref() else {
+            return Vec::new();
+        };
+
+        let mut tokens: Vec<String> = Vec::new();
+        for token in class.as_utf8_str().split_ascii_whitespace() {
+            if !tokens.iter().any(|t| t == token) {
+                tokens.push(token.to_string());
+            }
+        }
+
+        tokens
+    }
+
+    /// Returns the number of distinct class names
+    pub fn len(&self) -> usize {
+        self.tokens().len()
+    }
+
+    /// Checks whether there are no class names
+    pub fn is_empty(&self) -> bool {
+        self.tokens().is_empty()
+    }
+
+    /// Checks whether the given class name is present
+    pub fn contains(&self, token: &str) -> bool {
+        self.class.as_ref().is_some_and(|class| {
+            class
+                .as_utf8_str()
+                .split_ascii_whitespace()
+                .any(|t| t == token)
+        })
+    }
+
+    /// Adds a class name, and returns whether it was not present before
+    ///
+    /// # Example
+    /// ```
+    /// let mut dom = tl::parse(r#"<div class="  a b  a"></div>"#, Default::default()).unwrap();
+    /// let element = dom.nodes_mut()[0].as_tag_mut().unwrap();
+    ///
+    /// assert_eq!(element.attributes_mut().class_list_mut().add("c"), Ok(true));
+    /// assert_eq!(dom.outer_html(), r#"<div class="a b c"></div>"#);
+    /// ```
+    pub fn add(&mut self, token: &str) -> Result<bool, ClassListError> {
+        validate(token)?;
+
+        let mut tokens = self.tokens();
+        let added = !tokens.iter().any(|t| t == token);
+        if added {
+            tokens.push(token.to_string());
+        }
+
+        self.update(&tokens)?;
+        Ok(added)
+    }
+
+    /// Removes a class name, and returns whether it was present before
+    pub fn remove(&mut self, token: &str) -> Result<bool, ClassListError> {
+        validate(token)?;
+
+        let mut tokens = self.tokens();
+        let length = tokens.len();
+        tokens.retain(|t| t != token);
+        let removed = tokens.len() != length;
+
+        self.update(&tokens)?;
+        Ok(removed)
+    }
+
+    /// Removes a class name if it is present and adds it otherwise, and returns whether it is present afterwards
+    pub fn toggle(&mut self, token: &str) -> Result<bool, ClassListError> {
+        if self.contains(token) {
+            self.remove(token).map(|_| false)
+        } else {
+            self.add(token).map(|_| true)
+        }
+    }
+
+    /// Replaces a class name with another one in the same position, and returns whether it was present
+    ///
+    /// If `new_token` is already present, whichever of both comes first is replaced by `new_token` and the other one is removed.
+    /// The attribute is left unchanged if `token` is not present.
+    pub fn replace(&mut self, token: &str, new_token: &str) -> Result<bool, ClassListError> {
+        validate(token)?;
+        validate(new_token)?;
+
+        let mut tokens = self.tokens();
+        let Some(position) = tokens.iter().position(|t| t == token) else {
+            return Ok(false);
+        };
+
+        match tokens.iter().position(|t| t == new_token) {
+            // replacing a token with itself only rewrites the attribute
+            Some(existing) if existing == position => {}
+            // the first of both tokens is replaced by the new token, and the other one is removed
+            Some(existing) if existing < position => {
+                tokens.remove(position);
+            }
+            Some(existing) => {
+                let new_token = tokens.remove(existing);
+                tokens[position] = new_token;
+            }
+            None => tokens[position] = new_token.to_string(),
+        }
+
+        self.update(&tokens)?;
+        Ok(true)
+    }
+
+    /// Writes the given class names to the attribute and updates the class tracking table
+    fn update(&mut self, tokens: &[String]) -> Result<(), ClassListError> {
+        // an element without a class attribute does not get an empty one
+        if self.class.is_none() && tokens.is_empty() {
+            return Ok(());
+        }
+
+        let previous = self.tokens();
+        let value = tokens.join(" ");
+
+        match self.class.as_mut() {
+            Some(class) => {
+                class.set(value)?;
+            }
+            None => {
+                let mut class = Bytes::new();
+                class.set(value)?;
+                *self.class = Some(class);
+            }
+        }
+
+        if let Some((handle, classes)) = self.index.as_mut() {
+            for token in &previous {
+                untrack_class(classes, token.as_bytes(), *handle);
+            }
+
+            for token in tokens {
+                track_class(classes, Bytes::from(token.as_str()).into_owned(), *handle);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether a class name can be written to the `class` attribute
+fn validate(token: &str) -> Result<(), ClassListError> {
+    if token.is_empty() {
+        Err(ClassListError::EmptyToken)
+    } else if token.bytes().any(|b| b.is_ascii_whitespace() || b == b'"') {
+        Err(ClassListError::InvalidCharacter)
+    } else {
+        Ok(())
+    }
+}
@@ -3,3 +3,14 @@ pub const VOID_TAGS: &[&[u8]; 15] = &[
     b"area", b"base", b"br", b"col", b"embed", b"hr", b"img", b"input", b"keygen", b"link",
     b"meta", b"param", b"source", b"track", b"wbr",
 ];
+pub const RAW_TEXT_TAGS: &[&[u8]; 9] = &[
+    b"iframe",
+    b"noembed",
+    b"noframes",
+    b"plaintext",
+    b"script",
+    b"style",
+    b"textarea",
+    b"title",
+    b"xmp",
+];
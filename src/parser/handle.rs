@@ -1,9 +1,16 @@
 use crate::Node;
 
-use super::Parser;
+use super::{
+    traverse::{Descendants, Elements, TextNodes, Traverse},
+    Parser,
+};
 
 /// The inner type of a NodeHandle, used to represent an index into the tags table
+#[cfg(not(feature = "span64"))]
 pub type InnerNodeHandle = u32;
+/// The inner type of a NodeHandle, used to represent an index into the tags table
+#[cfg(feature = "span64")]
+pub type InnerNodeHandle = u64;
 
 /// A detached, external handle to a HTML node, originally obtained from a [Parser]
 ///
@@ -49,4 +56,56 @@ impl NodeHandle {
     pub fn get_inner(&self) -> InnerNodeHandle {
         self.0
     }
+
+    /// Returns an iterator over the [`Edge`](super::Edge)s of this node and all of its descendants
+    ///
+    /// This node has a depth of 0, its children have a depth of 1, and so on.
+    ///
+    /// # Example
+    /// ```
+    /// use tl::Edge;
+    ///
+    /// let dom = tl::parse("<p>a<b>b</b></p>", Default::default()).unwrap();
+    /// let p = dom.children()[0];
+    ///
+    /// let edges: Vec<_> = p.traverse(dom.parser()).collect();
+    /// assert_eq!(edges.len(), 8);
+    /// assert_eq!(edges[0], (Edge::Open(p), 0));
+    /// assert_eq!(edges[7], (Edge::Close(p), 0));
+    /// ```
+    pub fn traverse<'p, 'buf>(&self, parser: &'p Parser<'buf>) -> Traverse<'p, 'buf> {
+        Traverse::new(parser, std::slice::from_ref(self), 0)
+    }
+
+    /// Returns an iterator over all descendants of this node in document order
+    ///
+    /// The node itself is not included. Its children have a depth of 1, their children a depth of 2, and so on.
+    ///
+    /// # Example
+    /// ```
+    /// let dom = tl::parse("<ul><li>a</li><li>b</li></ul>", Default::default()).unwrap();
+    /// let ul = dom.children()[0];
+    ///
+    /// let depths: Vec<_> = ul.descendants(dom.parser()).map(|(_, depth)| depth).collect();
+    /// assert_eq!(depths, [1, 2, 1, 2]);
+    /// ```
+    pub fn descendants<'p, 'buf>(&self, parser: &'p Parser<'buf>) -> Descendants<'p, 'buf> {
+        let children = self
+            .get(parser)
+            .and_then(Node::as_tag)
+            .map(|tag| tag.children().top().as_slice())
+            .unwrap_or(&[]);
+
+        Descendants::new(parser, children, 1)
+    }
+
+    /// Returns an iterator over all tags among the descendants of this node, see [`NodeHandle::descendants()`]
+    pub fn elements<'p, 'buf>(&self, parser: &'p Parser<'buf>) -> Elements<'p, 'buf> {
+        Elements(self.descendants(parser))
+    }
+
+    /// Returns an iterator over all text nodes among the descendants of this node, see [`NodeHandle::descendants()`]
+    pub fn text_nodes<'p, 'buf>(&self, parser: &'p Parser<'buf>) -> TextNodes<'p, 'buf> {
+        TextNodes(self.descendants(parser))
+    }
 }
@@ -0,0 +1,126 @@
+use super::{constants, tokenizer::Token};
+use crate::{Bytes, LimitPolicy, ParseError, ParserOptions};
+
+/// How a token is added to the tree, as decided by [`Limits::admit()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Admission {
+    /// Add the node; it has no content, so it is complete
+    Node,
+    /// Add the element and make it the innermost open element
+    Open,
+    /// Add the element, but add its contents to the current parent because it exceeds the depth limit
+    Flatten,
+    /// Discard the token
+    Skip,
+    /// Stop parsing, optionally with an error
+    Stop(Option<ParseError>),
+}
+
+/// Applies the depth and node limits of [`ParserOptions`] while a tree is built from tokens
+///
+/// The attribute limits are applied by the tokenizer.
+#[derive(Debug)]
+pub(crate) struct Limits<'a> {
+    options: ParserOptions,
+    /// The names of the currently open elements that exceed the depth limit, innermost last
+    overflow: Vec<Bytes<'a>>,
+}
+
+impl<'a> Limits<'a> {
+    pub(crate) fn new(options: ParserOptions) -> Self {
+        Self {
+            options,
+            overflow: Vec::new(),
+        }
+    }
+
+    /// Decides how a text, comment or start tag token is added, given the number of open elements and of nodes so far
+    pub(crate) fn admit(&mut self, token: &Token<'a>, depth: usize, nodes: usize) -> Admission {
+        // some tags are self closing, so even though there might not be a /,
+        // we don't always want to push them to the stack
+        // e.g. <br><p>Hello</p>
+        // <p> should not be a subtag of <br>
+        let (name, has_content) = match token {
+            Token::StartTag {
+                name, self_closing, ..
+            } => (
+                Some(name),
+                !self_closing && !constants::VOID_TAGS.contains(&name.as_bytes()),
+            ),
+            _ => (None, false),
+        };
+
+        let exceeds_depth = has_content
+            && (!self.overflow.is_empty()
+                || self
+                    .options
+                    .depth_limit()
+                    .is_some_and(|(max, _)| depth >= max));
+
+        if exceeds_depth {
+            match self.options.depth_limit() {
+                Some((_, LimitPolicy::Error)) => {
+                    return Admission::Stop(Some(ParseError::DepthLimitExceeded))
+                }
+                Some((_, LimitPolicy::Truncate)) => {
+                    self.overflow.extend(name.cloned());
+                    return Admission::Skip;
+                }
+                _ => {}
+            }
+        }
+
+        if self.is_discarding() {
+            return Admission::Skip;
+        }
+
+        if let Some((max, policy)) = self.options.node_limit() {
+            if nodes >= max {
+                return Admission::Stop(
+                    (policy == LimitPolicy::Error).then_some(ParseError::NodeLimitExceeded),
+                );
+            }
+        }
+
+        if exceeds_depth {
+            // flattened: the contents of this element are added to the current parent
+            self.overflow.extend(name.cloned());
+            Admission::Flatten
+        } else if has_content {
+            Admission::Open
+        } else {
+            Admission::Node
+        }
+    }
+
+    /// Handles an end tag while elements that exceed the depth limit are open
+    ///
+    /// Returns `true` if the end tag must not close any of the open elements in the tree,
+    /// because the innermost open element exceeds the depth limit.
+    /// That element is closed if the names match.
+    pub(crate) fn close(&mut self, name: &Bytes<'a>) -> bool {
+        let Some(last) = self.overflow.last() else {
+            return false;
+        };
+
+        if last == name {
+            self.overflow.pop();
+        }
+
+        true
+    }
+
+    /// Points the names of the open elements into `to` if they borrow from `from`, see [`Bytes::rebase()`]
+    pub(crate) unsafe fn rebase(&mut self, from: &[u8], to: *const u8) {
+        for name in &mut self.overflow {
+            name.rebase(from, to);
+        }
+    }
+
+    /// Checks whether nodes are currently discarded because they are nested deeper than the depth limit
+    #[inline]
+    fn is_discarding(&self) -> bool {
+        !self.overflow.is_empty()
+            && matches!(self.options.depth_limit(), Some((_, LimitPolicy::Truncate)))
+    }
+}
@@ -1,10 +1,21 @@
 mod base;
+mod chunked;
+mod class_list;
 pub(crate) mod constants;
 mod handle;
+mod limits;
 mod options;
 mod tag;
+mod tokenizer;
+mod traverse;
+mod visitor;
 
 pub use base::*;
+pub use chunked::*;
+pub use class_list::*;
 pub use handle::*;
 pub use options::*;
 pub use tag::*;
+pub use tokenizer::*;
+pub use traverse::*;
+pub use visitor::*;
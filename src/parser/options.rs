@@ -4,15 +4,39 @@ mod flags {
     pub const HIGHEST: u8 = TRACK_CLASSES;
 }
 
+/// Specifies what the parser does when a limit set in [`ParserOptions`] is exceeded
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LimitPolicy {
+    /// Stop parsing and return an error
+    Error,
+    /// Discard whatever exceeds the limit:
+    /// elements that are nested too deeply (together with their contents), all nodes after the node limit,
+    /// attributes after the attribute limit, and the end of attribute values that are too long
+    Truncate,
+    /// Keep elements that are nested too deeply, but add them and their contents to the deepest allowed element
+    /// instead of nesting them.
+    ///
+    /// This only applies to the depth limit and behaves like [`LimitPolicy::Truncate`] for all other limits.
+    Flatten,
+}
+
 /// Options for the HTML Parser
 ///
 /// This allows users of this library to configure the parser.
 /// The default options (`ParserOptions::default()`) are optimized for raw parsing.
 /// If you need to do HTML tag lookups by ID or class names, you can enable tracking.
 /// This will cache HTML nodes as they appear in the source code on the fly.
+///
+/// For untrusted input, limits can be set on the nesting depth, the number of nodes,
+/// the number of attributes per element and the length of attribute values.
+/// By default, there are no limits.
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct ParserOptions {
     flags: u8,
+    max_depth: Option<(usize, LimitPolicy)>,
+    max_nodes: Option<(usize, LimitPolicy)>,
+    max_attributes: Option<(usize, LimitPolicy)>,
+    max_attribute_length: Option<(usize, LimitPolicy)>,
 }
 
 impl ParserOptions {
@@ -22,15 +46,22 @@ impl ParserOptions {
     }
 
     /// Creates a [ParserOptions] from a bitset
+    ///
+    /// Limits are not part of the bitset, so the returned options have no limits.
     pub fn from_raw_checked(flags: u8) -> Option<Self> {
         if flags > flags::HIGHEST * 2 - 1 {
             None
         } else {
-            Some(Self { flags })
+            Some(Self {
+                flags,
+                ..Self::default()
+            })
         }
     }
 
     /// Returns the raw flags of this bitset
+    ///
+    /// Limits are not part of the bitset.
     pub fn to_raw(&self) -> u8 {
         self.flags
     }
@@ -80,4 +111,62 @@ impl ParserOptions {
         // self.is_tracking_ids() || self.is_tracking_classes()
         self.flags != 0
     }
+
+    /// Limits the nesting depth of elements, i.e. the number of elements that can be open at the same time.
+    ///
+    /// An end tag inside an element that exceeds this limit only closes that element if the names match, like any other end tag.
+    pub fn max_depth(mut self, depth: usize, policy: LimitPolicy) -> Self {
+        self.max_depth = Some((depth, policy));
+        self
+    }
+
+    /// Limits the total number of nodes (elements, text and comments) in the document.
+    ///
+    /// [`LimitPolicy::Flatten`] has nothing to flatten here, so it behaves like [`LimitPolicy::Truncate`].
+    pub fn max_nodes(mut self, nodes: usize, policy: LimitPolicy) -> Self {
+        self.max_nodes = Some((nodes, policy));
+        self
+    }
+
+    /// Limits the number of attributes per element.
+    ///
+    /// [`LimitPolicy::Flatten`] has nothing to flatten here, so it behaves like [`LimitPolicy::Truncate`].
+    pub fn max_attributes(mut self, attributes: usize, policy: LimitPolicy) -> Self {
+        self.max_attributes = Some((attributes, policy));
+        self
+    }
+
+    /// Limits the length of attribute values in bytes.
+    ///
+    /// Truncated values are cut at a character boundary, so they may be shorter than the limit.
+    ///
+    /// [`LimitPolicy::Flatten`] has nothing to flatten here, so it behaves like [`LimitPolicy::Truncate`].
+    pub fn max_attribute_length(mut self, length: usize, policy: LimitPolicy) -> Self {
+        self.max_attribute_length = Some((length, policy));
+        self
+    }
+
+    /// Returns the nesting depth limit and its policy, if set.
+    #[inline]
+    pub fn depth_limit(&self) -> Option<(usize, LimitPolicy)> {
+        self.max_depth
+    }
+
+    /// Returns the node limit and its policy, if set.
+    #[inline]
+    pub fn node_limit(&self) -> Option<(usize, LimitPolicy)> {
+        self.max_nodes
+    }
+
+    /// Returns the limit of attributes per element and its policy, if set.
+    #[inline]
+    pub fn attribute_limit(&self) -> Option<(usize, LimitPolicy)> {
+        self.max_attributes
+    }
+
+    /// Returns the attribute value length limit and its policy, if set.
+    #[inline]
+    pub fn attribute_length_limit(&self) -> Option<(usize, LimitPolicy)> {
+        self.max_attribute_length
+    }
 }
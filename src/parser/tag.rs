@@ -1,11 +1,14 @@
 use crate::{
+    entities,
+    errors::{SetBytesError, StyleError},
     inline::{hashmap::InlineHashMap, vec::InlineVec},
     queryselector::{self, QuerySelectorIterator},
+    style::{Declaration, Style},
     Bytes, InnerNodeHandle,
 };
 use std::{borrow::Cow, mem};
 
-use super::{handle::NodeHandle, Parser};
+use super::{handle::NodeHandle, ClassList, Parser};
 
 const INLINED_ATTRIBUTES: usize = 2;
 const INLINED_SUBNODES: usize = 2;
@@ -41,6 +44,42 @@ impl<'a> Attributes<'a> {
         }
     }
 
+    /// Converts these attributes into attributes that own all of their data
+    pub(crate) fn into_owned(self) -> Attributes<'static> {
+        let mut raw = InlineHashMap::new();
+        for (key, value) in self.raw.iter() {
+            raw.insert(
+                key.clone().into_owned(),
+                value.clone().map(Bytes::into_owned),
+            );
+        }
+
+        Attributes {
+            raw,
+            id: self.id.map(Bytes::into_owned),
+            class: self.class.map(Bytes::into_owned),
+        }
+    }
+
+    /// Points the attributes into `to` if they borrow from `from`, see [`Bytes::rebase()`]
+    pub(crate) unsafe fn rebase(&mut self, from: &[u8], to: *const u8) {
+        let mut raw = InlineHashMap::new();
+        for (key, value) in self.raw.iter() {
+            let mut key = key.clone();
+            let mut value = value.clone();
+            key.rebase(from, to);
+            if let Some(value) = &mut value {
+                value.rebase(from, to);
+            }
+            raw.insert(key, value);
+        }
+
+        self.raw = raw;
+        for bytes in [&mut self.id, &mut self.class].into_iter().flatten() {
+            bytes.rebase(from, to);
+        }
+    }
+
     /// Counts the number of attributes
     pub fn len(&self) -> usize {
         let mut raw = self.raw.len();
@@ -153,7 +192,7 @@ impl<'a> Attributes<'a> {
         }
     }
 
-    /// Inserts a new attribute into this attributes collection
+    /// Inserts a new attribute into this attributes collection, replacing the value of an attribute with the same name
     pub fn insert<K, V>(&mut self, key: K, value: Option<V>)
     where
         K: Into<Bytes<'a>>,
@@ -165,7 +204,7 @@ impl<'a> Attributes<'a> {
         match key.as_bytes() {
             b"id" => self.id = value,
             b"class" => self.class = value,
-            _ => self.raw.insert(key, value),
+            _ => self.raw.replace(key, value),
         };
     }
 
@@ -210,6 +249,109 @@ impl<'a> Attributes<'a> {
             .map(str::split_ascii_whitespace)
     }
 
+    /// Returns a mutable view of the class names of this HTML tag, see [`ClassList`]
+    ///
+    /// This does not update the class tracking table of the parser, so lookups by class name may be stale afterwards.
+    /// Use [`VDom::class_list_mut()`](crate::VDom::class_list_mut) to keep it in sync.
+    pub fn class_list_mut(&mut self) -> ClassList<'_, 'a> {
+        ClassList::new(&mut self.class, None)
+    }
+
+    /// Parses the inline `style` attribute of this HTML tag, if present
+    ///
+    /// Character references in the attribute value are decoded before parsing it. See [`Style::parse()`].
+    ///
+    /// # Example
+    /// ```
+    /// let dom = tl::parse(r#"<div style="color: red; display:none"></div>"#, Default::default()).unwrap();
+    /// let element = dom.nodes()[0].as_tag().unwrap();
+    /// let style = element.attributes().style().unwrap();
+    ///
+    /// assert_eq!(style.value("color"), Some("red"));
+    /// assert!(style.is_hidden());
+    /// ```
+    pub fn style(&self) -> Option<Style> {
+        let value = self.get("style")?;
+        let value = value.map(|value| value.as_utf8_str()).unwrap_or_default();
+
+        Some(Style::parse(&entities::decode(&value)))
+    }
+
+    /// Replaces the inline `style` attribute of this HTML tag with the serialization of `style`
+    ///
+    /// The attribute is added if it is not present. The serialization is escaped,
+    /// so that quotes in values such as `font-family: "Fira Sans"` do not end the attribute value.
+    ///
+    /// # Errors
+    /// Returns an error if the serialized style is too long, see [`Bytes::set()`].
+    ///
+    /// # Example
+    /// ```
+    /// let mut dom = tl::parse(r#"<div style="color: red"></div>"#, Default::default()).unwrap();
+    /// let element = dom.nodes_mut()[0].as_tag_mut().unwrap();
+    /// let mut style = element.attributes().style().unwrap();
+    /// style.set("display", "none", true).unwrap();
+    /// element.attributes_mut().set_style(&style).unwrap();
+    ///
+    /// assert_eq!(
+    ///     dom.nodes()[0].outer_html(dom.parser()),
+    ///     r#"<div style="color: red; display: none !important;"></div>"#
+    /// );
+    /// ```
+    pub fn set_style(&mut self, style: &Style) -> Result<(), SetBytesError> {
+        let value = style.to_string();
+        let value = entities::escape_attribute(&value);
+
+        match self.get_mut("style") {
+            Some(Some(bytes)) => bytes.set(value.as_ref()).map(drop),
+            _ => {
+                let mut bytes = Bytes::new();
+                bytes.set(value.as_ref())?;
+                self.insert("style", Some(bytes));
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets a property of the inline `style` attribute of this HTML tag, see [`Style::set()`]
+    ///
+    /// # Errors
+    /// Returns an error if the property or value is invalid, see [`Style::set()`],
+    /// or if the serialized style is too long, see [`Attributes::set_style()`].
+    pub fn set_style_property(
+        &mut self,
+        property: &str,
+        value: &str,
+        important: bool,
+    ) -> Result<(), StyleError> {
+        let mut style = self.style().unwrap_or_default();
+        style.set(property, value, important)?;
+        Ok(self.set_style(&style)?)
+    }
+
+    /// Removes a property from the inline `style` attribute of this HTML tag and returns the declaration that applied,
+    /// see [`Style::remove()`]
+    ///
+    /// The attribute is left unchanged if it does not declare the property.
+    ///
+    /// # Errors
+    /// See [`Attributes::set_style()`]
+    pub fn remove_style_property(
+        &mut self,
+        property: &str,
+    ) -> Result<Option<Declaration>, SetBytesError> {
+        let Some(mut style) = self.style() else {
+            return Ok(None);
+        };
+
+        let removed = style.remove(property);
+        if removed.is_some() {
+            self.set_style(&style)?;
+        }
+
+        Ok(removed)
+    }
+
     /// Returns the underlying raw map for attributes
     ///
     /// ## A note on stability
@@ -248,6 +390,23 @@ impl<'a> HTMLTag<'a> {
         }
     }
 
+    /// Converts this tag into a tag that owns all of its data
+    pub(crate) fn into_owned(self) -> HTMLTag<'static> {
+        HTMLTag {
+            _name: self._name.into_owned(),
+            _attributes: self._attributes.into_owned(),
+            _children: self._children,
+            _raw: self._raw.into_owned(),
+        }
+    }
+
+    /// Points this tag into `to` if it borrows from `from`, see [`Bytes::rebase()`]
+    pub(crate) unsafe fn rebase(&mut self, from: &[u8], to: *const u8) {
+        self._name.rebase(from, to);
+        self._attributes.rebase(from, to);
+        self._raw.rebase(from, to);
+    }
+
     /// Returns a wrapper around the children of this HTML tag
     #[inline]
     pub fn children(&self) -> Children<'a, '_> {
@@ -266,6 +425,9 @@ impl<'a> HTMLTag<'a> {
     }
 
     /// Returns a mutable reference to the name of this HTML tag
+    ///
+    /// Like [`HTMLTag::attributes_mut()`], changes made through it bypass the id and class tracking tables of the parser,
+    /// see [`VDom::update_attributes()`](crate::VDom::update_attributes) and [`VDom::reindex()`](crate::VDom::reindex).
     #[inline]
     pub fn name_mut(&mut self) -> &mut Bytes<'a> {
         &mut self._name
@@ -278,6 +440,10 @@ impl<'a> HTMLTag<'a> {
     }
 
     /// Returns a mutable reference to the attributes of this HTML tag
+    ///
+    /// Changes made through it bypass the id and class tracking tables of the parser.
+    /// Use [`VDom::update_attributes()`](crate::VDom::update_attributes) to keep them in sync,
+    /// or call [`VDom::reindex()`](crate::VDom::reindex) afterwards.
     #[inline]
     pub fn attributes_mut(&mut self) -> &mut Attributes<'a> {
         &mut self._attributes
@@ -291,63 +457,96 @@ impl<'a> HTMLTag<'a> {
     ///
     /// Equivalent to [Element#outerHTML](https://developer.mozilla.org/en-US/docs/Web/API/Element/outerHTML) in browsers.
     pub fn outer_html<'p>(&'p self, parser: &'p Parser<'a>) -> String {
-        let tag_name = self._name.as_utf8_str();
-        let is_void_element = HTML_VOID_ELEMENTS.contains(&tag_name.as_ref());
-        let mut outer_html = format!("<{}", &tag_name);
+        let mut outer_html = String::new();
+        self.write_html(parser, &mut outer_html, true);
+        outer_html
+    }
 
-        #[inline]
-        fn write_attribute(dest: &mut String, k: Cow<str>, v: Option<Cow<str>>) {
-            dest.push(' ');
+    /// Returns the contained markup
+    ///
+    /// ## Limitations
+    /// - The order of tag attributes is not guaranteed
+    /// - Spaces within the tag are not preserved (i.e. `<img      src="">` may become `<img src="">`)
+    ///
+    /// Equivalent to [Element#innerHTML](https://developer.mozilla.org/en-US/docs/Web/API/Element/innerHTML) in browsers.
+    pub fn inner_html<'p>(&'p self, parser: &'p Parser<'a>) -> String {
+        let mut inner_html = String::new();
+        self.write_html(parser, &mut inner_html, false);
+        inner_html
+    }
 
-            dest.push_str(&k);
+    /// Writes the markup of this tag (if `outer` is true) and of all of its subnodes to `out`
+    ///
+    /// An explicit stack is used instead of recursion so that deeply nested documents cannot overflow the thread stack.
+    fn write_html<'p>(&'p self, parser: &'p Parser<'a>, out: &mut String, outer: bool) {
+        enum Step<'p, 'a> {
+            Open(&'p HTMLTag<'a>),
+            Node(&'p Node<'a>),
+            Close(&'p HTMLTag<'a>),
+        }
 
-            if let Some(value) = v {
-                dest.push_str("=\"");
-                dest.push_str(&value);
-                dest.push('"');
-            }
+        fn push_children<'p, 'a>(
+            tag: &'p HTMLTag<'a>,
+            parser: &'p Parser<'a>,
+            stack: &mut Vec<Step<'p, 'a>>,
+        ) {
+            stack.extend(
+                tag._children
+                    .as_slice()
+                    .iter()
+                    .rev()
+                    .map(|handle| Step::Node(handle.get(parser).unwrap())),
+            );
         }
 
-        let attr = self.attributes();
+        let mut stack = Vec::new();
 
-        for (k, v) in attr.iter() {
-            write_attribute(&mut outer_html, k, v);
+        if outer {
+            stack.push(Step::Open(self));
+        } else {
+            push_children(self, parser, &mut stack);
         }
 
-        outer_html.push('>');
+        while let Some(step) = stack.pop() {
+            let tag = match step {
+                Step::Open(tag) | Step::Node(Node::Tag(tag)) => tag,
+                Step::Node(Node::Raw(text) | Node::Comment(text)) => {
+                    out.push_str(&text.as_utf8_str());
+                    continue;
+                }
+                Step::Close(tag) => {
+                    out.push_str("</");
+                    out.push_str(&tag._name.as_utf8_str());
+                    out.push('>');
+                    continue;
+                }
+            };
 
-        // void elements have neither content nor a closing tag.
-        if is_void_element {
-            return outer_html;
-        }
+            let tag_name = tag._name.as_utf8_str();
+            out.push('<');
+            out.push_str(&tag_name);
+
+            for (k, v) in tag.attributes().iter() {
+                out.push(' ');
+                out.push_str(&k);
 
-        // TODO(y21): More of an idea than a TODO, but a potential perf improvement
-        // could be having some kind of internal inner_html function that takes a &mut String
-        // and simply writes to it instead of returning a newly allocated string for every element
-        // and appending it
-        outer_html.push_str(&self.inner_html(parser));
+                if let Some(value) = v {
+                    out.push_str("=\"");
+                    out.push_str(&value);
+                    out.push('"');
+                }
+            }
 
-        outer_html.push_str("</");
-        outer_html.push_str(&self._name.as_utf8_str());
-        outer_html.push('>');
+            out.push('>');
 
-        outer_html
-    }
+            // void elements have neither content nor a closing tag.
+            if HTML_VOID_ELEMENTS.contains(&tag_name.as_ref()) {
+                continue;
+            }
 
-    /// Returns the contained markup
-    ///
-    /// ## Limitations
-    /// - The order of tag attributes is not guaranteed
-    /// - Spaces within the tag are not preserved (i.e. `<img      src="">` may become `<img src="">`)
-    ///
-    /// Equivalent to [Element#innerHTML](https://developer.mozilla.org/en-US/docs/Web/API/Element/innerHTML) in browsers.
-    pub fn inner_html<'p>(&'p self, parser: &'p Parser<'a>) -> String {
-        self.children()
-            .top()
-            .iter()
-            .map(|handle| handle.get(parser).unwrap())
-            .map(|node| node.outer_html(parser))
-            .collect::<String>()
+            stack.push(Step::Close(tag));
+            push_children(tag, parser, &mut stack);
+        }
     }
 
     /// Returns the raw HTML of this tag.
@@ -373,10 +572,12 @@ impl<'a> HTMLTag<'a> {
     /// ```
     pub fn boundaries(&self, parser: &Parser<'a>) -> (usize, usize) {
         let raw = self._raw.as_bytes();
-        let input = parser.stream.data().as_ptr();
+        let input = parser.tokenizer.input().as_ptr();
         let start = raw.as_ptr();
-        let offset = start as usize - input as usize;
-        let end = offset + raw.len() - 1;
+        // nodes that were not parsed from the input (i.e. fragments) have no meaningful boundaries,
+        // but computing them must not panic
+        let offset = (start as usize).wrapping_sub(input as usize);
+        let end = offset.wrapping_add(raw.len()).wrapping_sub(1);
         (offset, end)
     }
 
@@ -385,32 +586,29 @@ impl<'a> HTMLTag<'a> {
     /// This function may not allocate memory for a new string as it can just return the part of the tag that doesn't have markup.
     /// For tags that *do* have more than one subnode, this will allocate memory
     pub fn inner_text<'p>(&self, parser: &'p Parser<'a>) -> Cow<'p, str> {
-        let len = self._children.len();
-
-        if len == 0 {
-            // If there are no subnodes, we can just return a static, empty, string slice
-            return Cow::Borrowed("");
-        }
-
-        let first = self._children[0].get(parser).unwrap();
-
-        if len == 1 {
-            match &first {
-                Node::Tag(t) => return t.inner_text(parser),
-                Node::Raw(e) => return e.as_utf8_str(),
-                Node::Comment(_) => return Cow::Borrowed(""),
+        let mut children = self._children.as_slice();
+
+        // As long as there is at most one subnode, we can return the part of the tag that doesn't have markup without allocating
+        loop {
+            match children {
+                [] => return Cow::Borrowed(""),
+                [only] => match only.get(parser).unwrap() {
+                    Node::Tag(t) => children = t._children.as_slice(),
+                    Node::Raw(e) => return e.as_utf8_str(),
+                    Node::Comment(_) => return Cow::Borrowed(""),
+                },
+                _ => break,
             }
         }
 
-        // If there are >1 nodes, we need to allocate a new string and push each inner_text in it
-        // TODO: check if String::with_capacity() is worth it
-        let mut s = String::from(first.inner_text(parser));
-
-        for &id in self._children.iter().skip(1) {
-            let node = id.get(parser).unwrap();
+        // If there are >1 nodes, we need to allocate a new string and push each inner_text in it.
+        // An explicit stack is used instead of recursion so that deeply nested documents cannot overflow the thread stack.
+        let mut s = String::new();
+        let mut stack: Vec<NodeHandle> = children.iter().rev().copied().collect();
 
-            match &node {
-                Node::Tag(t) => s.push_str(&t.inner_text(parser)),
+        while let Some(id) = stack.pop() {
+            match id.get(parser).unwrap() {
+                Node::Tag(t) => stack.extend(t._children.as_slice().iter().rev()),
                 Node::Raw(e) => s.push_str(&e.as_utf8_str()),
                 Node::Comment(_) => { /* no op */ }
             }
@@ -518,7 +716,7 @@ impl<'a, 'b> Children<'a, 'b> {
     /// assert_eq!(a.children().top().len(), 3);
     /// ```
     #[inline]
-    pub fn top(&self) -> &RawChildren {
+    pub fn top(&self) -> &'b RawChildren {
         &self.0._children
     }
 
@@ -590,23 +788,25 @@ impl<'a, 'b> ChildrenMut<'a, 'b> {
 
 /// Attempts to find the very last node handle that is contained in the given tag
 fn find_last_node_handle<'a>(tag: &HTMLTag<'a>, parser: &Parser<'a>) -> Option<NodeHandle> {
-    let last_handle = tag._children.as_slice().last().copied()?;
+    let mut last_handle = tag._children.as_slice().last().copied()?;
 
-    let child = last_handle
+    // Descend into the last child until we get to the innermost node
+    while let Some(child) = last_handle
         .get(parser)
         .expect("Failed to get child node, please open a bug report") // this shouldn't happen
-        .as_tag();
-
-    if let Some(child) = child {
-        // Recursively call this function to get to the innermost node
-        find_last_node_handle(child, parser).or(Some(last_handle))
-    } else {
-        Some(last_handle)
+        .as_tag()
+        .and_then(|child| child._children.as_slice().last().copied())
+    {
+        last_handle = child;
     }
+
+    Some(last_handle)
 }
 
 /// An HTML Node
 #[derive(Debug, Clone)]
+// the wider spans of the `span64` feature push `HTMLTag` over the size threshold of this lint
+#[cfg_attr(feature = "span64", allow(clippy::large_enum_variant))]
 pub enum Node<'a> {
     /// A regular HTML element/tag
     Tag(HTMLTag<'a>),
@@ -617,6 +817,23 @@ pub enum Node<'a> {
 }
 
 impl<'a> Node<'a> {
+    /// Converts this node into a node that owns all of its data
+    pub(crate) fn into_owned(self) -> Node<'static> {
+        match self {
+            Node::Tag(tag) => Node::Tag(tag.into_owned()),
+            Node::Raw(raw) => Node::Raw(raw.into_owned()),
+            Node::Comment(comment) => Node::Comment(comment.into_owned()),
+        }
+    }
+
+    /// Points this node into `to` if it borrows from `from`, see [`Bytes::rebase()`]
+    pub(crate) unsafe fn rebase(&mut self, from: &[u8], to: *const u8) {
+        match self {
+            Node::Tag(tag) => tag.rebase(from, to),
+            Node::Raw(bytes) | Node::Comment(bytes) => bytes.rebase(from, to),
+        }
+    }
+
     /// Returns the inner text of this node
     pub fn inner_text<'s, 'p: 's>(&'s self, parser: &'p Parser<'a>) -> Cow<'s, str> {
         match self {
@@ -660,20 +877,24 @@ impl<'a> Node<'a> {
     where
         F: FnMut(&Node<'a>) -> bool,
     {
-        if let Some(children) = self.children() {
-            for &id in children.top().iter() {
-                let node = id.get(parser).unwrap();
+        let children = self.children()?;
 
-                if f(node) {
-                    return Some(id);
-                }
+        // Nodes are visited in document order, using an explicit stack instead of recursion
+        // so that deeply nested documents cannot overflow the thread stack
+        let mut stack: Vec<NodeHandle> = children.top().as_slice().iter().rev().copied().collect();
 
-                let subnode = node.find_node(parser, f);
-                if subnode.is_some() {
-                    return subnode;
-                }
+        while let Some(id) = stack.pop() {
+            let node = id.get(parser).unwrap();
+
+            if f(node) {
+                return Some(id);
+            }
+
+            if let Some(children) = node.children() {
+                stack.extend(children.top().as_slice().iter().rev());
             }
         }
+
         None
     }
 
@@ -0,0 +1,519 @@
+use super::{constants, tag::Attributes};
+use crate::{bytes::Bytes, simd, stream::Stream, LimitPolicy, ParseError, ParserOptions};
+use std::ops::Range;
+
+/// A single unit of markup read by a [`Tokenizer`]
+///
+/// Each token carries its span, which is the range of bytes in the input it was read from.
+#[derive(Debug, Clone)]
+pub enum Token<'a> {
+    /// A start tag, such as `<a href="/">`
+    StartTag {
+        /// The name of the tag
+        name: Bytes<'a>,
+        /// The attributes of the tag
+        attributes: Attributes<'a>,
+        /// Whether the tag ends with `/>`
+        self_closing: bool,
+        /// The range of bytes in the input
+        span: Range<usize>,
+    },
+    /// An end tag, such as `</a>`
+    EndTag {
+        /// The name of the tag
+        name: Bytes<'a>,
+        /// The range of bytes in the input
+        span: Range<usize>,
+    },
+    /// Text between two tags
+    Text {
+        /// The raw text, without decoding character references
+        text: Bytes<'a>,
+        /// The range of bytes in the input
+        span: Range<usize>,
+    },
+    /// A comment, such as `<!-- comment -->`
+    Comment {
+        /// The comment, including the `<!--` and `-->` delimiters
+        comment: Bytes<'a>,
+        /// The range of bytes in the input
+        span: Range<usize>,
+    },
+    /// A `<!DOCTYPE>` declaration
+    Doctype {
+        /// The name of the document type, such as `html`
+        name: Bytes<'a>,
+        /// The range of bytes in the input
+        span: Range<usize>,
+    },
+}
+
+impl<'a> Token<'a> {
+    /// Returns the range of bytes in the input that this token was read from
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Token::StartTag { span, .. }
+            | Token::EndTag { span, .. }
+            | Token::Text { span, .. }
+            | Token::Comment { span, .. }
+            | Token::Doctype { span, .. } => span.clone(),
+        }
+    }
+
+    /// Converts this token into one that owns all of its data
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::StartTag {
+                name,
+                attributes,
+                self_closing,
+                span,
+            } => Token::StartTag {
+                name: name.into_owned(),
+                attributes: attributes.into_owned(),
+                self_closing,
+                span,
+            },
+            Token::EndTag { name, span } => Token::EndTag {
+                name: name.into_owned(),
+                span,
+            },
+            Token::Text { text, span } => Token::Text {
+                text: text.into_owned(),
+                span,
+            },
+            Token::Comment { comment, span } => Token::Comment {
+                comment: comment.into_owned(),
+                span,
+            },
+            Token::Doctype { name, span } => Token::Doctype {
+                name: name.into_owned(),
+                span,
+            },
+        }
+    }
+}
+
+/// A tokenizer that reads HTML into a stream of [`Token`]s without building a tree
+///
+/// This is the tokenizer that [`parse()`](crate::parse) uses internally, so the same markup produces the same tokens.
+/// The contents of raw text elements, such as `<script>` and `<style>`, are read as text up to their end tag.
+/// The attribute limits of the [`ParserOptions`] are applied to start tags. If a limit with [`LimitPolicy::Error`]
+/// is exceeded, the iterator ends and [`Tokenizer::error()`] returns the error.
+///
+/// # Example
+/// ```
+/// use tl::{ParserOptions, Token, Tokenizer};
+///
+/// let input = r#"<a href="/simple/tl/">tl</a>"#;
+/// let mut tokens = Tokenizer::new(input, ParserOptions::default());
+///
+/// match tokens.next() {
+///     Some(Token::StartTag { name, attributes, span, .. }) => {
+///         assert_eq!(name, *"a");
+///         assert_eq!(attributes.get("href"), Some(Some(&"/simple/tl/".into())));
+///         assert_eq!(span, 0..22);
+///     }
+///     _ => unreachable!(),
+/// }
+///
+/// assert!(matches!(tokens.next(), Some(Token::Text { text, .. }) if text == *"tl"));
+/// assert!(matches!(tokens.next(), Some(Token::EndTag { name, .. }) if name == *"a"));
+/// assert!(tokens.next().is_none());
+/// ```
+#[derive(Debug)]
+pub struct Tokenizer<'a> {
+    /// The inner stream that is used to iterate through the HTML source
+    pub(crate) stream: Stream<'a, u8>,
+    options: ParserOptions,
+    /// The error that stopped tokenizing, if a limit was exceeded
+    pub(crate) error: Option<ParseError>,
+    /// The name of the raw text element (such as `<script>`) whose contents are read next
+    pub(crate) raw_text: Option<&'a [u8]>,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a new tokenizer for the given input
+    pub fn new(input: &'a str, options: ParserOptions) -> Self {
+        Self::from_bytes(input.as_bytes(), options)
+    }
+
+    /// Creates a new tokenizer for input that is not necessarily valid UTF-8
+    pub fn from_bytes(input: &'a [u8], options: ParserOptions) -> Self {
+        Self {
+            stream: Stream::new(input),
+            options,
+            error: None,
+            raw_text: None,
+        }
+    }
+
+    /// Returns the input of this tokenizer
+    #[inline]
+    pub fn input(&self) -> &'a [u8] {
+        self.stream.slice(0, self.stream.len())
+    }
+
+    /// Returns the position of the next token in the input
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.stream.idx
+    }
+
+    /// Returns the error that ended the token stream early, if any
+    #[inline]
+    pub fn error(&self) -> Option<&ParseError> {
+        self.error.as_ref()
+    }
+
+    /// Stops tokenizing by skipping the rest of the input, optionally recording an error
+    #[cold]
+    pub(crate) fn stop(&mut self, error: Option<ParseError>) {
+        self.error = error;
+        self.stream.idx = self.stream.len();
+    }
+
+    #[inline(always)]
+    fn skip_whitespaces(&mut self) {
+        self.read_while2(b' ', b'\n');
+    }
+
+    fn read_to(&mut self, needle: u8) -> &'a [u8] {
+        let start = self.stream.idx;
+        let bytes = &self.stream.data()[start..];
+
+        let end = simd::find(bytes, needle).unwrap_or_else(|| self.stream.len() - start);
+
+        self.stream.idx += end;
+        self.stream.slice(start, start + end)
+    }
+
+    fn read_to3(&mut self, needle: [u8; 3]) -> &'a [u8] {
+        let start = self.stream.idx;
+        let bytes = &self.stream.data()[start..];
+
+        let end = simd::find3(bytes, needle).unwrap_or_else(|| self.stream.len() - start);
+
+        self.stream.idx += end;
+        self.stream.slice(start, start + end)
+    }
+
+    fn read_while2(&mut self, needle1: u8, needle2: u8) -> Option<()> {
+        loop {
+            let ch = self.stream.current_cpy()?;
+
+            let eq1 = ch == needle1;
+            let eq2 = ch == needle2;
+
+            if !eq1 & !eq2 {
+                return Some(());
+            }
+
+            self.stream.advance();
+        }
+    }
+
+    fn read_ident(&mut self) -> Option<&'a [u8]> {
+        let start = self.stream.idx;
+        let bytes = &self.stream.data()[start..];
+
+        // If we do not find any characters that are not identifiers
+        // then we are probably at the end of the stream
+        let end = simd::search_non_ident(bytes).unwrap_or_else(|| self.stream.len() - start);
+
+        // If we don't find any identifier characters, return `None`.
+        if end == 0 {
+            return None;
+        }
+
+        self.stream.idx += end;
+        Some(self.stream.slice(start, start + end))
+    }
+
+    fn skip_comment_with_start(&mut self, start: usize) -> &'a [u8] {
+        while !self.stream.is_eof() {
+            let idx = self.stream.idx;
+
+            if self
+                .stream
+                .slice_len(idx, constants::COMMENT.len())
+                .eq(constants::COMMENT)
+            {
+                self.stream.advance_by(constants::COMMENT.len());
+
+                let is_end_of_comment = self.stream.expect_and_skip_cond(b'>');
+
+                if is_end_of_comment {
+                    return self.stream.slice(start, self.stream.idx);
+                }
+            }
+
+            self.stream.advance();
+        }
+
+        &[]
+    }
+
+    fn parse_attribute(&mut self) -> Option<(&'a [u8], Option<&'a [u8]>)> {
+        let name = self.read_ident()?;
+        self.skip_whitespaces();
+
+        let has_value = self.stream.expect_and_skip_cond(b'=');
+        if !has_value {
+            return Some((name, None));
+        }
+
+        self.skip_whitespaces();
+
+        let value = if let Some(quote) = self.stream.expect_oneof_and_skip(b"\"'") {
+            self.read_to(quote)
+        } else {
+            self.read_to3([b' ', b'\n', b'>'])
+        };
+
+        Some((name, Some(value)))
+    }
+
+    fn parse_attributes(&mut self) -> Option<Attributes<'a>> {
+        let mut attributes = Attributes::new();
+
+        loop {
+            self.skip_whitespaces();
+
+            let cur = self.stream.current_cpy()?;
+
+            if simd::is_closing(cur) {
+                break;
+            }
+
+            if let Some((key, value)) = self.parse_attribute() {
+                let has_value = value.is_some();
+
+                let value = match (value, self.options.attribute_length_limit()) {
+                    (Some(value), Some((max, policy))) if value.len() > max => {
+                        if policy == LimitPolicy::Error {
+                            self.stop(Some(ParseError::AttributeLengthExceeded));
+                            return None;
+                        }
+
+                        Some(truncate_utf8(value, max))
+                    }
+                    (value, _) => value,
+                };
+                let value: Option<Bytes<'a>> = value.map(Into::into);
+
+                let exceeds_limit = self
+                    .options
+                    .attribute_limit()
+                    .filter(|&(max, _)| attributes.len() >= max && !attributes.contains(key));
+
+                match (exceeds_limit, key) {
+                    (Some((_, LimitPolicy::Error)), _) => {
+                        self.stop(Some(ParseError::AttributeLimitExceeded));
+                        return None;
+                    }
+                    (Some(_), _) => {}
+                    (None, b"id") => attributes.id = value,
+                    (None, b"class") => attributes.class = value,
+                    // like browsers, the first of several attributes with the same name wins
+                    (None, _) if attributes.contains(key) => {}
+                    (None, _) => attributes.raw.insert(key.into(), value),
+                };
+
+                // Only advance past the delimiter if we read a value.
+                if has_value && !simd::is_closing(self.stream.current_cpy()?) {
+                    self.stream.advance();
+                }
+            } else {
+                // No valid attribute found; skip this character.
+                self.stream.advance();
+            }
+        }
+
+        Some(attributes)
+    }
+
+    fn read_end(&mut self, start: usize) -> Option<Token<'a>> {
+        self.stream.advance();
+
+        let closing_tag_name = self.read_to(b'>');
+
+        self.stream.expect_and_skip_cond(b'>');
+
+        Some(Token::EndTag {
+            name: closing_tag_name.into(),
+            span: start..self.stream.idx,
+        })
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn read_markdown(&mut self, start: usize) -> Option<Token<'a>> {
+        let comment_start = self.stream.idx - 1; // position of the < which is needed when registering the comment
+
+        self.stream.advance(); // skip !
+
+        let is_comment = self
+            .stream
+            .slice_len(self.stream.idx, 2)
+            .eq(constants::COMMENT);
+
+        if is_comment {
+            let comment = self.skip_comment_with_start(comment_start);
+
+            Some(Token::Comment {
+                comment: comment.into(),
+                span: start..self.stream.idx,
+            })
+        } else {
+            let tag = self.read_ident()?;
+
+            self.skip_whitespaces();
+
+            if !simd::matches_case_insensitive(tag, *b"doctype") {
+                return None;
+            }
+
+            let doctype = self.read_ident()?;
+
+            self.skip_whitespaces();
+            self.stream.advance(); // skip >
+
+            Some(Token::Doctype {
+                name: doctype.into(),
+                span: start..self.stream.idx,
+            })
+        }
+    }
+
+    fn read_tag(&mut self) -> Option<Token<'a>> {
+        let start = self.stream.idx;
+
+        self.stream.advance();
+        self.skip_whitespaces();
+        let cur = self.stream.current_cpy()?;
+
+        match cur {
+            b'/' => self.read_end(start),
+            b'!' => self.read_markdown(start),
+            _ => {
+                let name = self.read_ident()?;
+                self.skip_whitespaces();
+
+                let attributes = self.parse_attributes()?;
+
+                let self_closing = self.stream.expect_and_skip_cond(b'/');
+
+                self.stream.expect_and_skip(b'>')?;
+
+                let is_raw_text = constants::RAW_TEXT_TAGS
+                    .iter()
+                    .any(|tag| name.eq_ignore_ascii_case(tag));
+
+                if is_raw_text && !self_closing {
+                    self.raw_text = Some(name);
+                }
+
+                Some(Token::StartTag {
+                    name: name.into(),
+                    attributes,
+                    self_closing,
+                    span: start..self.stream.idx,
+                })
+            }
+        }
+    }
+
+    /// Reads the next token from the input
+    ///
+    /// Returns `None` if the markup at the current position does not produce a token,
+    /// in which case the input has still been advanced.
+    pub(crate) fn read_token(&mut self) -> Option<Token<'a>> {
+        if let Some(name) = self.raw_text.take() {
+            if let Some(text) = self.read_raw_text(name) {
+                return Some(text);
+            }
+        }
+
+        if self.stream.current_cpy()? == b'<' {
+            self.read_tag()
+        } else {
+            let start = self.stream.idx;
+            let text = self.read_to(b'<');
+
+            Some(Token::Text {
+                text: text.into(),
+                span: start..self.stream.idx,
+            })
+        }
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Reads the contents of a raw text element up to its end tag, where markup is not recognized
+    ///
+    /// Returns `None` if the element is empty.
+    fn read_raw_text(&mut self, name: &[u8]) -> Option<Token<'a>> {
+        let start = self.stream.idx;
+        let input = self.input();
+
+        let end = find_raw_text_end(input, start, name).unwrap_or(input.len());
+
+        if end == start {
+            return None;
+        }
+
+        self.stream.idx = end;
+
+        Some(Token::Text {
+            text: self.stream.slice(start, end).into(),
+            span: start..end,
+        })
+    }
+}
+
+/// Returns the position of the end tag of a raw text element named `name` whose contents start at or before `from`
+pub(crate) fn find_raw_text_end(input: &[u8], from: usize, name: &[u8]) -> Option<usize> {
+    // <plaintext> has no end tag
+    if name.eq_ignore_ascii_case(b"plaintext") {
+        return None;
+    }
+
+    memchr::memmem::find_iter(&input[from..], b"</")
+        .map(|offset| from + offset)
+        .find(|&offset| {
+            let rest = &input[offset + 2..];
+
+            rest.len() > name.len()
+                && rest[..name.len()].eq_ignore_ascii_case(name)
+                && matches!(
+                    rest[name.len()],
+                    b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r' | b'\x0C'
+                )
+        })
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.stream.is_eof() {
+            if let Some(token) = self.read_token() {
+                return Some(token);
+            }
+        }
+
+        None
+    }
+}
+
+/// Shortens a byte string to at most `max` bytes without splitting a UTF-8 encoded character
+fn truncate_utf8(value: &[u8], max: usize) -> &[u8] {
+    let mut end = max.min(value.len());
+
+    while end > 0 && value.get(end).is_some_and(|&b| b & 0xC0 == 0x80) {
+        end -= 1;
+    }
+
+    &value[..end]
+}
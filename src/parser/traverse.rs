@@ -0,0 +1,170 @@
+use super::{handle::NodeHandle, tag::Node, Parser};
+
+/// An event produced by [`Traverse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    /// The node is entered, before any of its children
+    Open(NodeHandle),
+    /// The node is left, after all of its children
+    Close(NodeHandle),
+}
+
+impl Edge {
+    /// Returns the handle of the node that this edge belongs to
+    #[inline]
+    pub fn handle(&self) -> NodeHandle {
+        match *self {
+            Edge::Open(handle) | Edge::Close(handle) => handle,
+        }
+    }
+}
+
+/// Returns the children of the given node, or an empty slice if it is not a tag
+fn children_of<'p>(parser: &'p Parser<'_>, handle: NodeHandle) -> &'p [NodeHandle] {
+    handle
+        .get(parser)
+        .and_then(Node::as_tag)
+        .map(|tag| tag.children().top().as_slice())
+        .unwrap_or(&[])
+}
+
+/// An iterator over the [`Edge`]s of a tree and their depth, see [`NodeHandle::traverse()`] and [`VDom::traverse()`](crate::VDom::traverse)
+///
+/// Every node is opened before and closed after all of its descendants.
+/// This does not use recursion, so it is safe to use on deeply nested documents.
+#[derive(Debug, Clone)]
+pub struct Traverse<'p, 'a> {
+    parser: &'p Parser<'a>,
+    /// Pending edges, the next one is at the end
+    stack: Vec<(Edge, usize)>,
+    /// The node that was opened last, whose children have not been added to `stack` yet
+    expand: Option<(NodeHandle, usize)>,
+}
+
+impl<'p, 'a> Traverse<'p, 'a> {
+    pub(crate) fn new(parser: &'p Parser<'a>, roots: &[NodeHandle], depth: usize) -> Self {
+        Self {
+            parser,
+            stack: roots
+                .iter()
+                .rev()
+                .map(|&handle| (Edge::Open(handle), depth))
+                .collect(),
+            expand: None,
+        }
+    }
+
+    /// Skips the descendants of the node that was opened last
+    ///
+    /// The next edge is the [`Edge::Close`] of that node.
+    #[inline]
+    pub fn skip_subtree(&mut self) {
+        self.expand = None;
+    }
+}
+
+impl<'p, 'a> Iterator for Traverse<'p, 'a> {
+    type Item = (Edge, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((handle, depth)) = self.expand.take() {
+            let children = children_of(self.parser, handle);
+            self.stack.extend(
+                children
+                    .iter()
+                    .rev()
+                    .map(|&child| (Edge::Open(child), depth + 1)),
+            );
+        }
+
+        let (edge, depth) = self.stack.pop()?;
+
+        if let Edge::Open(handle) = edge {
+            self.stack.push((Edge::Close(handle), depth));
+            self.expand = Some((handle, depth));
+        }
+
+        Some((edge, depth))
+    }
+}
+
+/// An iterator over nodes and their depth in document order, see [`NodeHandle::descendants()`] and [`VDom::descendants()`](crate::VDom::descendants)
+///
+/// This does not use recursion, so it is safe to use on deeply nested documents.
+#[derive(Debug, Clone)]
+pub struct Descendants<'p, 'a> {
+    parser: &'p Parser<'a>,
+    /// Pending nodes, the next one is at the end
+    stack: Vec<(NodeHandle, usize)>,
+    /// The node that was yielded last, whose children have not been added to `stack` yet
+    expand: Option<(NodeHandle, usize)>,
+}
+
+impl<'p, 'a> Descendants<'p, 'a> {
+    pub(crate) fn new(parser: &'p Parser<'a>, roots: &[NodeHandle], depth: usize) -> Self {
+        Self {
+            parser,
+            stack: roots.iter().rev().map(|&handle| (handle, depth)).collect(),
+            expand: None,
+        }
+    }
+
+    /// Skips the descendants of the node that was yielded last
+    #[inline]
+    pub fn skip_subtree(&mut self) {
+        self.expand = None;
+    }
+}
+
+impl<'p, 'a> Iterator for Descendants<'p, 'a> {
+    type Item = (NodeHandle, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((handle, depth)) = self.expand.take() {
+            let children = children_of(self.parser, handle);
+            self.stack
+                .extend(children.iter().rev().map(|&child| (child, depth + 1)));
+        }
+
+        let (handle, depth) = self.stack.pop()?;
+        self.expand = Some((handle, depth));
+
+        Some((handle, depth))
+    }
+}
+
+/// An iterator over tags and their depth in document order, see [`NodeHandle::elements()`] and [`VDom::elements()`](crate::VDom::elements)
+#[derive(Debug, Clone)]
+pub struct Elements<'p, 'a>(pub(crate) Descendants<'p, 'a>);
+
+impl<'p, 'a> Elements<'p, 'a> {
+    /// Skips the descendants of the tag that was yielded last
+    #[inline]
+    pub fn skip_subtree(&mut self) {
+        self.0.skip_subtree();
+    }
+}
+
+impl<'p, 'a> Iterator for Elements<'p, 'a> {
+    type Item = (NodeHandle, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser = self.0.parser;
+        self.0
+            .find(|(handle, _)| matches!(handle.get(parser), Some(Node::Tag(_))))
+    }
+}
+
+/// An iterator over text nodes and their depth in document order, see [`NodeHandle::text_nodes()`] and [`VDom::text_nodes()`](crate::VDom::text_nodes)
+#[derive(Debug, Clone)]
+pub struct TextNodes<'p, 'a>(pub(crate) Descendants<'p, 'a>);
+
+impl<'p, 'a> Iterator for TextNodes<'p, 'a> {
+    type Item = (NodeHandle, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser = self.0.parser;
+        self.0
+            .find(|(handle, _)| matches!(handle.get(parser), Some(Node::Raw(_))))
+    }
+}
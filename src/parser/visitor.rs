@@ -0,0 +1,121 @@
+use super::{
+    limits::{Admission, Limits},
+    tag::HTMLTag,
+    tokenizer::Token,
+    tokenizer::Tokenizer,
+};
+use crate::{inline::vec::InlineVec, Bytes, ParseError, ParserOptions};
+
+/// A visitor that is called for every node while the input is parsed, see [`visit()`](crate::visit)
+///
+/// All methods do nothing by default. `depth` is the number of elements that enclose the node,
+/// so nodes at the top level have a depth of 0.
+pub trait Visitor<'a> {
+    /// Called when an element is opened
+    ///
+    /// The children of the element have not been read yet, so [`HTMLTag::children()`] is always empty.
+    fn enter_element(&mut self, tag: &HTMLTag<'a>, depth: usize) {
+        let _ = (tag, depth);
+    }
+
+    /// Called when an element is closed
+    ///
+    /// Void and self-closing elements are closed immediately after they are opened.
+    /// Elements that are never closed are closed at the end of the input.
+    /// [`HTMLTag::raw()`] contains the source of the entire element if it was closed by an end tag.
+    fn exit_element(&mut self, tag: &HTMLTag<'a>, depth: usize) {
+        let _ = (tag, depth);
+    }
+
+    /// Called for text between tags
+    fn text(&mut self, text: &Bytes<'a>, depth: usize) {
+        let _ = (text, depth);
+    }
+
+    /// Called for a comment, including the `<!--` and `-->` delimiters
+    fn comment(&mut self, comment: &Bytes<'a>, depth: usize) {
+        let _ = (comment, depth);
+    }
+}
+
+/// Drives a visitor through the input, keeping only the currently open elements in memory
+///
+/// Elements are opened and closed exactly like the parser does when building a tree, including the parser limits.
+pub(crate) fn drive<'a, V: Visitor<'a> + ?Sized>(
+    input: &'a str,
+    options: ParserOptions,
+    visitor: &mut V,
+) -> Result<(), ParseError> {
+    let mut tokenizer = Tokenizer::new(input, options);
+    let mut stack: Vec<HTMLTag<'a>> = Vec::with_capacity(4);
+    let mut limits = Limits::new(options);
+    let mut nodes = 0;
+
+    for token in tokenizer.by_ref() {
+        if let Token::EndTag { name, span } = token {
+            if !limits.close(&name) && stack.last().is_some_and(|tag| tag.name() == &name) {
+                let mut tag = stack.pop().unwrap();
+
+                // extend the raw source of the element to the end tag
+                let offset = tag._raw.as_ptr() as usize - input.as_ptr() as usize;
+                tag._raw = input.as_bytes()[offset..span.end].into();
+
+                visitor.exit_element(&tag, stack.len());
+            }
+
+            continue;
+        }
+
+        if let Token::Doctype { .. } = token {
+            continue;
+        }
+
+        let admission = match limits.admit(&token, stack.len(), nodes) {
+            Admission::Skip => continue,
+            Admission::Stop(Some(error)) => return Err(error),
+            Admission::Stop(None) => break,
+            admission => admission,
+        };
+
+        nodes += 1;
+        let depth = stack.len();
+
+        match token {
+            Token::Text { text, .. } => visitor.text(&text, depth),
+            Token::Comment { comment, .. } => visitor.comment(&comment, depth),
+            Token::StartTag {
+                name,
+                attributes,
+                span,
+                ..
+            } => {
+                let tag = HTMLTag::new(
+                    name,
+                    attributes,
+                    InlineVec::new(),
+                    input.as_bytes()[span].into(),
+                );
+
+                visitor.enter_element(&tag, depth);
+
+                if admission == Admission::Open {
+                    stack.push(tag);
+                } else {
+                    // void, self-closing and flattened elements are closed immediately
+                    visitor.exit_element(&tag, depth);
+                }
+            }
+            Token::EndTag { .. } | Token::Doctype { .. } => {}
+        }
+    }
+
+    if let Some(error) = tokenizer.error() {
+        return Err(error.clone());
+    }
+
+    while let Some(tag) = stack.pop() {
+        visitor.exit_element(&tag, stack.len());
+    }
+
+    Ok(())
+}
@@ -31,7 +31,7 @@ impl<'a> QueryIterable<'a> for VDom<'a> {
         parser
             .tags
             .get(index)
-            .map(|node| (node, NodeHandle::new(index as u32)))
+            .map(|node| (node, NodeHandle::new(index as InnerNodeHandle)))
     }
 
     #[inline]
@@ -56,7 +56,7 @@ impl<'a> QueryIterable<'a> for HTMLTag<'a> {
     ) -> Option<(&'b Node<'a>, NodeHandle)> {
         // Add `index` to the starting ID to get the ID of the node we need
         let index = self.start().map(|h| h as usize + index)?;
-        let handle = NodeHandle::new(index as u32);
+        let handle = NodeHandle::new(index as InnerNodeHandle);
         let node = parser.tags.get(index)?;
         Some((node, handle))
     }
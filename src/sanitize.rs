@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::constants;
+use crate::{entities, HTMLTag, Node, NodeHandle, ParseError, Parser, ParserOptions, VDom};
+
+/// Elements that are always removed together with their contents, regardless of the policy
+const ALWAYS_REMOVED: &[&str] = &["script", "style"];
+
+/// Attributes that contain URLs and are checked against the URL scheme allowlist
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "cite",
+    "action",
+    "formaction",
+    "poster",
+    "longdesc",
+    "background",
+];
+
+/// Elements that are allowed by the default policy
+const DEFAULT_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strike",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "tt",
+    "ul",
+    "var",
+];
+
+/// Specifies what happens to elements that are not allowed by a [`Policy`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Disallowed {
+    /// Remove the element's tags, but keep its (sanitized) contents
+    #[default]
+    Strip,
+    /// Render the element's tags as escaped text, and keep its (sanitized) contents
+    Escape,
+    /// Remove the element together with all of its contents
+    Remove,
+}
+
+/// An allowlist based sanitization policy
+///
+/// Everything that is not explicitly allowed is removed: elements, attributes and URL schemes.
+/// The contents of `<script>` and `<style>` elements are always dropped, and comments are never emitted.
+///
+/// The [`Default`] policy is suitable for rendering user supplied documents such as READMEs:
+/// it allows common formatting elements, links with `http`, `https` and `mailto` URLs and images,
+/// and adds `rel="noopener noreferrer"` to links.
+///
+/// # Example
+/// ```
+/// use tl::sanitize::{Disallowed, Policy};
+///
+/// let policy = Policy::new()
+///     .allow_tags(["p", "a"])
+///     .allow_attributes("a", ["href"])
+///     .allow_url_schemes(["https"])
+///     .link_rel(Some("noopener"))
+///     .disallowed(Disallowed::Escape);
+///
+/// let clean = tl::sanitize::sanitize(
+///     r#"<p onclick="x()">Hi <a href="javascript:alert(1)">there</a><b>!</b></p>"#,
+///     &policy,
+/// ).unwrap();
+///
+/// assert_eq!(clean, r#"<p>Hi <a rel="noopener">there</a>&lt;b&gt;!&lt;/b&gt;</p>"#);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Policy {
+    tags: HashSet<String>,
+    attributes: HashMap<String, HashSet<String>>,
+    url_schemes: HashSet<String>,
+    link_rel: Option<String>,
+    disallowed: Disallowed,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new()
+            .allow_tags(DEFAULT_TAGS)
+            .allow_attributes("*", ["title", "lang", "dir"])
+            .allow_attributes("a", ["href"])
+            .allow_attributes("img", ["src", "alt", "width", "height"])
+            .allow_attributes("td", ["colspan", "rowspan", "align"])
+            .allow_attributes("th", ["colspan", "rowspan", "align"])
+            .allow_attributes("ol", ["start"])
+            .allow_attributes("q", ["cite"])
+            .allow_attributes("blockquote", ["cite"])
+            .allow_url_schemes(["http", "https", "mailto"])
+            .link_rel(Some("noopener noreferrer"))
+    }
+}
+
+impl Policy {
+    /// Creates a new, empty policy that does not allow anything
+    pub fn new() -> Self {
+        Self {
+            tags: HashSet::new(),
+            attributes: HashMap::new(),
+            url_schemes: HashSet::new(),
+            link_rel: None,
+            disallowed: Disallowed::default(),
+        }
+    }
+
+    /// Allows the given elements
+    pub fn allow_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.tags
+            .extend(tags.into_iter().map(|t| t.as_ref().to_ascii_lowercase()));
+        self
+    }
+
+    /// Allows the given attributes on an element.
+    ///
+    /// Attributes allowed on the special tag name `*` are allowed on every element.
+    pub fn allow_attributes<I, S>(mut self, tag: &str, attributes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.attributes
+            .entry(tag.to_ascii_lowercase())
+            .or_default()
+            .extend(
+                attributes
+                    .into_iter()
+                    .map(|a| a.as_ref().to_ascii_lowercase()),
+            );
+        self
+    }
+
+    /// Allows the given URL schemes in `href`, `src` and other URL attributes.
+    ///
+    /// Relative URLs are always allowed.
+    pub fn allow_url_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.url_schemes
+            .extend(schemes.into_iter().map(|s| s.as_ref().to_ascii_lowercase()));
+        self
+    }
+
+    /// Sets the `rel` attribute that is forced on every `<a>` element with an `href`,
+    /// replacing any `rel` attribute of the input.
+    ///
+    /// `None` keeps the `rel` attribute of the input if it is allowed by the policy.
+    pub fn link_rel(mut self, rel: Option<&str>) -> Self {
+        self.link_rel = rel.map(String::from);
+        self
+    }
+
+    /// Sets what happens to elements that are not allowed by this policy
+    pub fn disallowed(mut self, disallowed: Disallowed) -> Self {
+        self.disallowed = disallowed;
+        self
+    }
+
+    /// Checks whether the given (lowercase) element is allowed
+    pub fn is_tag_allowed(&self, tag: &str) -> bool {
+        self.tags.contains(tag) && !ALWAYS_REMOVED.contains(&tag)
+    }
+
+    /// Checks whether the given (lowercase) attribute is allowed on the given (lowercase) element
+    pub fn is_attribute_allowed(&self, tag: &str, attribute: &str) -> bool {
+        [tag, "*"].iter().any(|t| {
+            self.attributes
+                .get(*t)
+                .is_some_and(|attributes| attributes.contains(attribute))
+        })
+    }
+
+    /// Checks whether the given URL has an allowed scheme, or is relative
+    pub fn is_url_allowed(&self, url: &str) -> bool {
+        // Browsers ignore ASCII tabs and newlines anywhere in URLs,
+        // and leading/trailing C0 controls and spaces, so they must not be able to hide a scheme
+        let url = url
+            .chars()
+            .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+            .collect::<String>();
+        let url = url.trim_matches(|c: char| c <= ' ');
+
+        match url.find([':', '/', '?', '#']) {
+            Some(idx) if url.as_bytes()[idx] == b':' => {
+                self.url_schemes.contains(&url[..idx].to_ascii_lowercase())
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Parses and sanitizes the given input string according to a [`Policy`]
+///
+/// # Errors
+/// See [`crate::parse`]
+pub fn sanitize(input: &str, policy: &Policy) -> Result<String, ParseError> {
+    let dom = crate::parse(input, ParserOptions::default())?;
+    Ok(sanitize_dom(&dom, policy))
+}
+
+/// Sanitizes an already parsed [`VDom`] according to a [`Policy`]
+pub fn sanitize_dom(dom: &VDom<'_>, policy: &Policy) -> String {
+    let sanitizer = Sanitizer {
+        parser: dom.parser(),
+        policy,
+    };
+
+    sanitizer.serialize(dom.children())
+}
+
+/// Pending work of the sanitizer
+enum Work {
+    /// A node that has yet to be serialized
+    Node(NodeHandle),
+    /// Markup that is emitted once all children of an element have been serialized
+    Close(String),
+}
+
+struct Sanitizer<'p, 'a, 'o> {
+    parser: &'p Parser<'a>,
+    policy: &'o Policy,
+}
+
+impl<'p, 'a, 'o> Sanitizer<'p, 'a, 'o> {
+    fn serialize(&self, roots: &[NodeHandle]) -> String {
+        let mut out = String::new();
+
+        // An explicit stack is used instead of recursion so that deeply nested input cannot overflow the thread stack
+        let mut stack: Vec<Work> = roots.iter().rev().map(|&h| Work::Node(h)).collect();
+
+        while let Some(work) = stack.pop() {
+            let handle = match work {
+                Work::Node(handle) => handle,
+                Work::Close(markup) => {
+                    out.push_str(&markup);
+                    continue;
+                }
+            };
+
+            let tag = match handle.get(self.parser) {
+                Some(Node::Tag(tag)) => tag,
+                Some(Node::Raw(text)) => {
+                    let text = text.as_utf8_str();
+                    out.push_str(&entities::escape_text(&entities::decode(&text)));
+                    continue;
+                }
+                Some(Node::Comment(_)) | None => continue,
+            };
+
+            let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+            if ALWAYS_REMOVED.contains(&name.as_str()) {
+                continue;
+            }
+
+            let is_void = constants::VOID_TAGS.contains(&name.as_bytes());
+
+            let close = if self.policy.is_tag_allowed(&name) {
+                out.push_str(&self.start_tag(&name, tag));
+                (!is_void).then(|| format!("</{}>", name))
+            } else {
+                match self.policy.disallowed {
+                    Disallowed::Strip => None,
+                    Disallowed::Remove => continue,
+                    Disallowed::Escape => {
+                        let start = self.escaped_start_tag(tag);
+                        out.push_str(&entities::escape_text(&start));
+
+                        let end = format!("</{}>", tag.name().as_utf8_str());
+                        (!is_void).then(|| entities::escape_text(&end).into_owned())
+                    }
+                }
+            };
+
+            if let Some(close) = close {
+                stack.push(Work::Close(close));
+            }
+
+            if !is_void {
+                stack.extend(
+                    tag.children()
+                        .top()
+                        .as_slice()
+                        .iter()
+                        .rev()
+                        .map(|&h| Work::Node(h)),
+                );
+            }
+        }
+
+        out
+    }
+
+    fn start_tag(&self, name: &str, tag: &HTMLTag<'a>) -> String {
+        let mut attributes = Vec::new();
+
+        for (key, value) in tag.attributes().iter() {
+            let key = key.to_ascii_lowercase();
+
+            if !self.policy.is_attribute_allowed(name, &key) {
+                continue;
+            }
+
+            if key == "rel" && name == "a" && self.policy.link_rel.is_some() {
+                continue;
+            }
+
+            let value = value.map(|v| entities::decode(&v).into_owned());
+
+            if URL_ATTRIBUTES.contains(&key.as_str())
+                && !value
+                    .as_deref()
+                    .is_some_and(|url| self.policy.is_url_allowed(url))
+            {
+                continue;
+            }
+
+            attributes.push((key, value));
+        }
+
+        if name == "a" && tag.attributes().contains("href") {
+            if let Some(rel) = &self.policy.link_rel {
+                attributes.push((String::from("rel"), Some(rel.clone())));
+            }
+        }
+
+        // Attributes are sorted so that the output is deterministic
+        attributes.sort();
+
+        let mut out = format!("<{}", name);
+        for (key, value) in attributes {
+            out.push(' ');
+            out.push_str(&key);
+            if let Some(value) = value {
+                out.push_str("=\"");
+                out.push_str(&entities::escape_attribute(&value));
+                out.push('"');
+            }
+        }
+        out.push('>');
+
+        out
+    }
+
+    fn escaped_start_tag(&self, tag: &HTMLTag<'a>) -> String {
+        let mut out = format!("<{}", tag.name().as_utf8_str());
+
+        let mut attributes = tag.attributes().iter().collect::<Vec<_>>();
+        attributes.sort();
+
+        for (key, value) in attributes {
+            out.push(' ');
+            out.push_str(&key);
+            if let Some(value) = value {
+                out.push_str("=\"");
+                out.push_str(&value);
+                out.push('"');
+            }
+        }
+        out.push('>');
+
+        out
+    }
+}
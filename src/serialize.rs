@@ -0,0 +1,470 @@
+use std::fmt;
+
+use serde::de::{self, value::MapAccessDeserializer, value::SeqAccessDeserializer};
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::inline::vec::InlineVec;
+use crate::{
+    Attributes, Bytes, HTMLTag, InnerNodeHandle, Node, NodeHandle, OwnedVDom, Parser,
+    ParserOptions, VDom,
+};
+
+/// Serializes a node and its descendants in the nested form
+///
+/// Elements are serialized as `{"type": "element", "name": ..., "attributes": {...}, "children": [...]}`,
+/// text as `{"type": "text", "text": ...}` and comments as `{"type": "comment", "comment": ...}`,
+/// where the comment includes its `<!--` and `-->` delimiters like [`Node::Comment`].
+/// Attributes without a value are serialized as `null`.
+/// A [`VDom`] is serialized as a sequence of its topmost nodes in the same form.
+///
+/// The nested form is serialized recursively, so documents of untrusted depth should use the [`Arena`] form instead.
+///
+/// # Example
+/// ```
+/// let dom = tl::parse(r#"<p class="intro">Hello <b>world</b></p>"#, Default::default()).unwrap();
+/// let b = dom.query_selector("b").unwrap().next().unwrap();
+///
+/// let json = serde_json::to_string(&tl::serialize::Subtree::new(&dom, b)).unwrap();
+/// assert_eq!(json, r#"{"type":"element","name":"b","attributes":{},"children":[{"type":"text","text":"world"}]}"#);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Subtree<'p, 'a> {
+    parser: &'p Parser<'a>,
+    handle: NodeHandle,
+}
+
+impl<'p, 'a> Subtree<'p, 'a> {
+    /// Creates a serializable view of the subtree rooted at `handle`
+    pub fn new(dom: &'p VDom<'a>, handle: NodeHandle) -> Self {
+        Self {
+            parser: dom.parser(),
+            handle,
+        }
+    }
+}
+
+impl Serialize for Subtree<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self
+            .handle
+            .get(self.parser)
+            .ok_or_else(|| serde::ser::Error::custom("dangling node handle"))?;
+
+        let children = NestedChildren {
+            parser: self.parser,
+            handles: node.as_tag().map_or(&[], |tag| tag._children.as_slice()),
+        };
+
+        serialize_node(node, children, serializer)
+    }
+}
+
+/// The children of an element in the nested form
+struct NestedChildren<'p, 'a> {
+    parser: &'p Parser<'a>,
+    handles: &'p [NodeHandle],
+}
+
+impl Serialize for NestedChildren<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.handles.len()))?;
+        for &handle in self.handles {
+            seq.serialize_element(&Subtree {
+                parser: self.parser,
+                handle,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for VDom<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NestedChildren {
+            parser: self.parser(),
+            handles: self.children(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for OwnedVDom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get_ref().serialize(serializer)
+    }
+}
+
+/// Deserializes a document in the nested form that was produced by [`VDom`] or a single [`Subtree`]
+///
+/// The nodes own their data, so the [`OwnedVDom::source()`] of the document is empty,
+/// and [`HTMLTag::raw()`] is empty for every element.
+/// Ids and classes are added to the tracking tables if enabled in `options`.
+///
+/// # Example
+/// ```
+/// let dom = tl::parse(r#"<ul id="list"><li>a</li><li>b</li></ul>"#, Default::default()).unwrap();
+/// let json = serde_json::to_string(&dom).unwrap();
+///
+/// let options = tl::ParserOptions::default().track_ids();
+/// let owned = tl::serialize::from_nested(&mut serde_json::Deserializer::from_str(&json), options).unwrap();
+/// let owned = owned.get_ref();
+///
+/// assert_eq!(owned.outer_html(), dom.outer_html());
+/// assert!(owned.get_element_by_id("list").is_some());
+/// ```
+///
+/// # Errors
+/// Returns an error if the input is not in the nested form.
+pub fn from_nested<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    options: ParserOptions,
+) -> Result<OwnedVDom, D::Error> {
+    let Roots(roots) = Roots::deserialize(deserializer)?;
+    let mut parser = Parser::new("", options);
+
+    let mut stack: Vec<(Nested, Option<NodeHandle>)> =
+        roots.into_iter().rev().map(|node| (node, None)).collect();
+
+    // nodes are registered in document order, like the parser does
+    while let Some((Nested(data), parent)) = stack.pop() {
+        let (node, children) = data.into_node()?;
+        let handle = parser.register_tag(node);
+
+        match parent.and_then(|parent| parent.get_mut(&mut parser)) {
+            Some(Node::Tag(tag)) => tag._children.push(handle),
+            _ => parser.ast.push(handle),
+        }
+
+        if let Some(children) = children {
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, Some(handle))),
+            );
+        }
+    }
+
+    Ok(finish(parser))
+}
+
+impl<'de> Deserialize<'de> for OwnedVDom {
+    /// Deserializes a document in the nested form with the default [`ParserOptions`], see [`from_nested()`]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        from_nested(deserializer, ParserOptions::default())
+    }
+}
+
+/// Serializes a document in the arena form, which keeps the handle indices of all nodes
+///
+/// The document is serialized as `{"nodes": [...], "children": [...]}`,
+/// where `nodes` contains every node of [`VDom::nodes()`] in the same form as [`Subtree`],
+/// except that the children of elements are node handles, and `children` contains the handles of the topmost nodes.
+/// A [`NodeHandle`] of the serialized document refers to the same node after deserializing it with [`from_arena()`].
+///
+/// Nodes are not nested, so this form can be used for documents of any depth.
+///
+/// # Example
+/// ```
+/// let dom = tl::parse("<p>Hello</p>", Default::default()).unwrap();
+///
+/// let json = serde_json::to_string(&tl::serialize::Arena::new(&dom)).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"nodes":[{"type":"element","name":"p","attributes":{},"children":[1]},{"type":"text","text":"Hello"}],"children":[0]}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Arena<'p, 'a>(&'p VDom<'a>);
+
+impl<'p, 'a> Arena<'p, 'a> {
+    /// Creates a serializable view of the given document in the arena form
+    pub fn new(dom: &'p VDom<'a>) -> Self {
+        Self(dom)
+    }
+}
+
+impl Serialize for Arena<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Arena", 2)?;
+        state.serialize_field("nodes", &ArenaNodes(self.0.nodes()))?;
+        state.serialize_field("children", &Handles(self.0.children()))?;
+        state.end()
+    }
+}
+
+/// All nodes of a document in the arena form
+struct ArenaNodes<'p, 'a>(&'p [Node<'a>]);
+
+impl Serialize for ArenaNodes<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for node in self.0 {
+            seq.serialize_element(&ArenaNode(node))?;
+        }
+        seq.end()
+    }
+}
+
+struct ArenaNode<'p, 'a>(&'p Node<'a>);
+
+impl Serialize for ArenaNode<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let children = Handles(self.0.as_tag().map_or(&[], |tag| tag._children.as_slice()));
+        serialize_node(self.0, children, serializer)
+    }
+}
+
+/// A list of node handles, serialized as their indices
+struct Handles<'p>(&'p [NodeHandle]);
+
+impl Serialize for Handles<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(NodeHandle::get_inner))
+    }
+}
+
+/// Deserializes a document in the arena form that was produced by [`Arena`]
+///
+/// Every node keeps its handle, so handles that were obtained from the serialized document
+/// can be used with the deserialized document.
+/// As with [`from_nested()`], the nodes own their data and ids and classes are tracked if enabled in `options`.
+///
+/// # Example
+/// ```
+/// let dom = tl::parse(r#"<div><p id="a">Hello</p></div>"#, Default::default()).unwrap();
+/// let p = dom.get_element_by_id("a").unwrap();
+/// let json = serde_json::to_string(&tl::serialize::Arena::new(&dom)).unwrap();
+///
+/// let owned = tl::serialize::from_arena(&mut serde_json::Deserializer::from_str(&json), Default::default()).unwrap();
+/// let owned = owned.get_ref();
+///
+/// assert_eq!(p.get(owned.parser()).unwrap().inner_text(owned.parser()), "Hello");
+/// ```
+///
+/// # Errors
+/// Returns an error if the input is not in the arena form, if a handle is out of bounds,
+/// or if the handles do not form a tree (a node has more than one parent, or nodes contain themselves).
+pub fn from_arena<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    options: ParserOptions,
+) -> Result<OwnedVDom, D::Error> {
+    let document = ArenaDocument::deserialize(deserializer)?;
+    let len = document.nodes.len();
+
+    let mut nodes = Vec::with_capacity(len);
+    for data in document.nodes {
+        nodes.push(data.into_node()?);
+    }
+
+    let children = |idx: usize| nodes[idx].1.as_deref().unwrap_or(&[]);
+
+    // every node is referenced at most once, either as a topmost node or as a child of exactly one element
+    let mut referenced = vec![false; len];
+    let references = document.children.iter().chain((0..len).flat_map(&children));
+
+    for &handle in references {
+        match referenced.get_mut(handle as usize) {
+            Some(true) => return Err(de::Error::custom("node has more than one parent")),
+            Some(referenced) => *referenced = true,
+            None => return Err(de::Error::custom("node handle out of bounds")),
+        }
+    }
+
+    // nodes that are unreachable from the topmost nodes and detached nodes are part of a cycle
+    let mut stack: Vec<usize> = document
+        .children
+        .iter()
+        .map(|&handle| handle as usize)
+        .chain((0..len).filter(|&idx| !referenced[idx]))
+        .collect();
+    let mut reachable = 0;
+
+    while let Some(idx) = stack.pop() {
+        reachable += 1;
+        stack.extend(children(idx).iter().map(|&handle| handle as usize));
+    }
+
+    if reachable != len {
+        return Err(de::Error::custom("node contains itself"));
+    }
+
+    let mut parser = Parser::new("", options);
+
+    for (mut node, children) in nodes {
+        if let (Node::Tag(tag), Some(children)) = (&mut node, children) {
+            for handle in children {
+                tag._children.push(NodeHandle::new(handle));
+            }
+        }
+
+        parser.register_tag(node);
+    }
+
+    parser.ast = document.children.into_iter().map(NodeHandle::new).collect();
+
+    Ok(finish(parser))
+}
+
+/// Tracks the ids and classes of all nodes and wraps the parser in a document
+fn finish(mut parser: Parser<'static>) -> OwnedVDom {
+    parser.reindex();
+
+    OwnedVDom::from_parser(parser)
+}
+
+/// Serializes a node with the given representation of its children
+fn serialize_node<S: Serializer, C: Serialize>(
+    node: &Node<'_>,
+    children: C,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match node {
+        Node::Tag(tag) => {
+            let mut map = serializer.serialize_map(Some(4))?;
+            map.serialize_entry("type", "element")?;
+            map.serialize_entry("name", &tag.name().as_utf8_str())?;
+            map.serialize_entry("attributes", &AttributesMap(tag.attributes()))?;
+            map.serialize_entry("children", &children)?;
+            map.end()
+        }
+        Node::Raw(text) => {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("type", "text")?;
+            map.serialize_entry("text", &text.as_utf8_str())?;
+            map.end()
+        }
+        Node::Comment(comment) => {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("type", "comment")?;
+            map.serialize_entry("comment", &comment.as_utf8_str())?;
+            map.end()
+        }
+    }
+}
+
+/// The attributes of an element, serialized as a map in the order of [`Attributes::iter()`]
+struct AttributesMap<'p, 'a>(&'p Attributes<'a>);
+
+impl Serialize for AttributesMap<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter())
+    }
+}
+
+/// A node as it is deserialized, where `C` is the representation of the children of elements
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum NodeData<C> {
+    Element {
+        name: String,
+        #[serde(default)]
+        attributes: AttributeList,
+        #[serde(default)]
+        children: C,
+    },
+    Text {
+        text: String,
+    },
+    Comment {
+        comment: String,
+    },
+}
+
+impl<C> NodeData<C> {
+    /// Converts this into a node without children, and the children if this is an element
+    fn into_node<E: de::Error>(self) -> Result<(Node<'static>, Option<C>), E> {
+        Ok(match self {
+            NodeData::Element {
+                name,
+                attributes,
+                children,
+            } => {
+                let mut attrs = Attributes::new();
+                for (key, value) in attributes.0 {
+                    attrs.insert(owned_bytes(key)?, value.map(owned_bytes).transpose()?);
+                }
+
+                let tag = HTMLTag::new(owned_bytes(name)?, attrs, InlineVec::new(), Bytes::new());
+                (Node::Tag(tag), Some(children))
+            }
+            NodeData::Text { text } => (Node::Raw(owned_bytes(text)?), None),
+            NodeData::Comment { comment } => (Node::Comment(owned_bytes(comment)?), None),
+        })
+    }
+}
+
+fn owned_bytes<E: de::Error>(s: String) -> Result<Bytes<'static>, E> {
+    Bytes::try_from(s).map_err(de::Error::custom)
+}
+
+/// A node in the nested form
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct Nested(NodeData<Vec<Nested>>);
+
+/// The topmost nodes of a document in the nested form, or a single node
+struct Roots(Vec<Nested>);
+
+impl<'de> Deserialize<'de> for Roots {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RootsVisitor;
+
+        impl<'de> de::Visitor<'de> for RootsVisitor {
+            type Value = Roots;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a node or a sequence of nodes")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Roots, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(Roots)
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Roots, A::Error> {
+                Nested::deserialize(MapAccessDeserializer::new(map)).map(|node| Roots(vec![node]))
+            }
+        }
+
+        deserializer.deserialize_any(RootsVisitor)
+    }
+}
+
+/// A document in the arena form
+#[derive(Deserialize)]
+struct ArenaDocument {
+    nodes: Vec<NodeData<Vec<InnerNodeHandle>>>,
+    children: Vec<InnerNodeHandle>,
+}
+
+/// Attributes in the order in which they appear in a map, where `null` means that an attribute has no value
+#[derive(Default)]
+struct AttributeList(Vec<(String, Option<String>)>);
+
+impl<'de> Deserialize<'de> for AttributeList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AttributeListVisitor;
+
+        impl<'de> de::Visitor<'de> for AttributeListVisitor {
+            type Value = AttributeList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of attributes")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<AttributeList, A::Error> {
+                let mut attributes = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    attributes.push(entry);
+                }
+                Ok(AttributeList(attributes))
+            }
+        }
+
+        deserializer.deserialize_map(AttributeListVisitor)
+    }
+}
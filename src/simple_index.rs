@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use crate::entities::{self, decode_attribute};
+use crate::{links, Node, VDom};
+
+/// Hash values by the name of their hash algorithm, such as `sha256`
+pub type Hashes = BTreeMap<String, String>;
+
+/// The hash algorithms that may appear in the URL fragment of a file
+const HASH_ALGORITHMS: [&str; 6] = ["md5", "sha1", "sha224", "sha256", "sha384", "sha512"];
+
+/// A file that is listed on a project page of a simple repository
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SimpleFile {
+    /// The name of the file, which is the text of the anchor
+    pub filename: String,
+    /// The URL of the file, without the hash fragment
+    ///
+    /// URLs that have a fragment that is not a hash are kept as is.
+    pub url: String,
+    /// The hashes of the file, taken from the `#<hashname>=<hashvalue>` fragment of the URL
+    pub hashes: Hashes,
+    /// The `data-requires-python` attribute, which is a version specifier such as `>=3.8`
+    pub requires_python: Option<String>,
+    /// The hashes of the core metadata file, if it is available
+    ///
+    /// This is taken from the `data-core-metadata` attribute, or from `data-dist-info-metadata` if it is absent.
+    /// The hashes are empty if the metadata is available, but its hash is not provided.
+    pub core_metadata: Option<Hashes>,
+    /// The reason why the file was yanked, or an empty string if it was yanked without giving a reason
+    pub yanked: Option<String>,
+    /// The `data-gpg-sig` attribute, which tells whether a GPG signature of the file is available
+    pub gpg_sig: Option<bool>,
+}
+
+impl SimpleFile {
+    /// Returns whether this file was yanked
+    #[inline]
+    pub fn is_yanked(&self) -> bool {
+        self.yanked.is_some()
+    }
+}
+
+/// The contents of a project page of a simple repository, see [`parse_project_page()`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProjectPage {
+    /// The version of the repository API, from the `pypi:repository-version` meta tag
+    pub repository_version: Option<String>,
+    /// The status of the project, such as `active` or `archived`, from the `pypi:project-status` meta tag
+    pub project_status: Option<String>,
+    /// The files of the project, in the order in which they appear on the page
+    pub files: Vec<SimpleFile>,
+}
+
+/// Extracts the files and metadata of a project page of a simple repository
+///
+/// This implements the HTML form of the simple repository API ([PEP 503], [PEP 592], [PEP 629], [PEP 658], [PEP 691] and [PEP 714]).
+/// Every anchor with an `href` attribute is a file. Attribute values and anchor texts are entity-decoded.
+/// If `base_url` is given, relative URLs are resolved against it, or against the `<base href>` of the page if it has one.
+///
+/// [PEP 503]: https://peps.python.org/pep-0503/
+/// [PEP 592]: https://peps.python.org/pep-0592/
+/// [PEP 629]: https://peps.python.org/pep-0629/
+/// [PEP 658]: https://peps.python.org/pep-0658/
+/// [PEP 691]: https://peps.python.org/pep-0691/
+/// [PEP 714]: https://peps.python.org/pep-0714/
+///
+/// # Example
+/// ```
+/// let input = r#"<!DOCTYPE html>
+/// <html>
+///   <head><meta name="pypi:repository-version" content="1.1"></head>
+///   <body>
+///     <a href="../../packages/demo-1.0.tar.gz#sha256=abcd" data-requires-python="&gt;=3.8">demo-1.0.tar.gz</a>
+///     <a href="../../packages/demo-0.9.tar.gz" data-yanked="broken">demo-0.9.tar.gz</a>
+///   </body>
+/// </html>"#;
+///
+/// let dom = tl::parse(input, Default::default()).unwrap();
+/// let page = tl::simple_index::parse_project_page(&dom, Some("https://example.com/simple/demo/"));
+///
+/// assert_eq!(page.repository_version.as_deref(), Some("1.1"));
+/// assert_eq!(page.files[0].filename, "demo-1.0.tar.gz");
+/// assert_eq!(page.files[0].url, "https://example.com/packages/demo-1.0.tar.gz");
+/// assert_eq!(page.files[0].hashes["sha256"], "abcd");
+/// assert_eq!(page.files[0].requires_python.as_deref(), Some(">=3.8"));
+/// assert_eq!(page.files[1].yanked.as_deref(), Some("broken"));
+/// ```
+pub fn parse_project_page(dom: &VDom<'_>, base_url: Option<&str>) -> ProjectPage {
+    let parser = dom.parser();
+    let base_url = base_url.map(|url| links::base_url(dom, url));
+    let mut page = ProjectPage::default();
+
+    for (handle, _) in dom.elements() {
+        let Some(Node::Tag(tag)) = handle.get(parser) else {
+            continue;
+        };
+
+        let name = tag.name().as_bytes();
+
+        if name.eq_ignore_ascii_case(b"meta") {
+            let target = match decode_attribute(tag, "name").as_deref() {
+                Some("pypi:repository-version") => &mut page.repository_version,
+                Some("pypi:project-status") => &mut page.project_status,
+                _ => continue,
+            };
+
+            if let Some(content) = decode_attribute(tag, "content") {
+                *target = Some(content);
+            }
+        } else if name.eq_ignore_ascii_case(b"a") {
+            let Some(href) = decode_attribute(tag, "href") else {
+                continue;
+            };
+
+            let mut file = SimpleFile {
+                filename: entities::decode(&tag.inner_text(parser)).trim().to_string(),
+                requires_python: decode_attribute(tag, "data-requires-python"),
+                core_metadata: decode_attribute(tag, "data-core-metadata")
+                    .or_else(|| decode_attribute(tag, "data-dist-info-metadata"))
+                    .and_then(|value| parse_metadata(&value)),
+                yanked: decode_attribute(tag, "data-yanked"),
+                gpg_sig: match decode_attribute(tag, "data-gpg-sig").as_deref() {
+                    Some("true") => Some(true),
+                    Some("false") => Some(false),
+                    _ => None,
+                },
+                ..SimpleFile::default()
+            };
+
+            let url = match &base_url {
+                Some(base) => links::join(base, &href).unwrap_or(href),
+                None => href,
+            };
+
+            match url.split_once('#') {
+                Some((without_fragment, fragment)) => match parse_hash(fragment) {
+                    Some((algorithm, value)) => {
+                        file.hashes.insert(algorithm, value);
+                        file.url = without_fragment.to_string();
+                    }
+                    None => file.url = url,
+                },
+                None => file.url = url,
+            }
+
+            page.files.push(file);
+        }
+    }
+
+    page
+}
+
+/// Parses a `<hashname>=<hashvalue>` pair with a supported hash algorithm
+fn parse_hash(value: &str) -> Option<(String, String)> {
+    let (algorithm, hash) = value.split_once('=')?;
+    let algorithm = algorithm.to_ascii_lowercase();
+
+    (HASH_ALGORITHMS.contains(&algorithm.as_str()) && !hash.is_empty())
+        .then(|| (algorithm, hash.to_string()))
+}
+
+/// Parses the value of a `data-core-metadata` attribute, which is either `true` or a hash
+fn parse_metadata(value: &str) -> Option<Hashes> {
+    if value.eq_ignore_ascii_case("true") {
+        return Some(Hashes::new());
+    }
+
+    parse_hash(value).map(|(algorithm, hash)| Hashes::from([(algorithm, hash)]))
+}
@@ -0,0 +1,281 @@
+/// The descriptor of an image candidate in a `srcset` attribute
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Descriptor {
+    /// A width descriptor such as `640w`, which is the width of the image in pixels
+    Width(u32),
+    /// A pixel density descriptor such as `2x`
+    ///
+    /// Candidates without a descriptor have a density of 1.
+    Density(f64),
+}
+
+impl Default for Descriptor {
+    fn default() -> Self {
+        Descriptor::Density(1.0)
+    }
+}
+
+/// A source size of a `sizes` attribute, see [`parse_sizes()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceSize {
+    /// The media condition, such as `(max-width: 600px)`, or `None` for the default size
+    pub media: Option<String>,
+    /// The size, such as `480px` or `calc(100vw - 2em)`
+    pub size: String,
+}
+
+/// Parses the value of a `srcset` or `imagesrcset` attribute into its image candidates
+///
+/// This follows the [parsing algorithm] of the HTML standard, so URLs may contain commas,
+/// and candidates with invalid descriptors are left out. Height descriptors are validated, but not returned.
+/// Entities must be decoded before and URLs are not resolved.
+///
+/// [parsing algorithm]: https://html.spec.whatwg.org/multipage/images.html#parse-a-srcset-attribute
+///
+/// # Example
+/// ```
+/// use tl::srcset::{parse_srcset, Descriptor};
+///
+/// let candidates = parse_srcset("small.jpg 480w, data:image/png;base64,iVBO 800w, /large.jpg");
+///
+/// assert_eq!(candidates, [
+///     ("small.jpg".to_string(), Descriptor::Width(480)),
+///     ("data:image/png;base64,iVBO".to_string(), Descriptor::Width(800)),
+///     ("/large.jpg".to_string(), Descriptor::Density(1.0)),
+/// ]);
+/// ```
+pub fn parse_srcset(value: &str) -> Vec<(String, Descriptor)> {
+    let is_whitespace = |c: char| c.is_ascii_whitespace();
+    let mut candidates = Vec::new();
+    let mut rest = value;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| is_whitespace(c) || c == ',');
+        if rest.is_empty() {
+            return candidates;
+        }
+
+        let end = rest.find(is_whitespace).unwrap_or(rest.len());
+        let (url, after) = rest.split_at(end);
+        rest = after;
+
+        let mut descriptors = Vec::new();
+
+        let url = match url.strip_suffix(',') {
+            // a URL that ends with a comma has no descriptors
+            Some(_) => url.trim_end_matches(','),
+            None => {
+                rest = tokenize_descriptors(rest, &mut descriptors);
+                url
+            }
+        };
+
+        if let Some(descriptor) = parse_descriptors(&descriptors) {
+            candidates.push((url.to_string(), descriptor));
+        }
+    }
+}
+
+/// Splits the descriptors of a candidate until the comma that ends it, and returns the input after that comma
+///
+/// Commas inside of parentheses do not end the candidate.
+fn tokenize_descriptors<'a>(input: &'a str, descriptors: &mut Vec<&'a str>) -> &'a str {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+    let mut start = None;
+    let mut in_parens = false;
+
+    for (index, c) in input.char_indices() {
+        if in_parens {
+            in_parens = c != ')';
+            continue;
+        }
+
+        match c {
+            c if c.is_ascii_whitespace() => {
+                if let Some(start) = start.take() {
+                    descriptors.push(&input[start..index]);
+                }
+            }
+            ',' => {
+                if let Some(start) = start.take() {
+                    descriptors.push(&input[start..index]);
+                }
+                return &input[index + 1..];
+            }
+            _ => {
+                in_parens = c == '(';
+                start.get_or_insert(index);
+            }
+        }
+    }
+
+    if let Some(start) = start {
+        descriptors.push(&input[start..]);
+    }
+
+    ""
+}
+
+/// Parses the descriptors of a candidate, or returns `None` if they are invalid
+fn parse_descriptors(descriptors: &[&str]) -> Option<Descriptor> {
+    let mut width = None;
+    let mut density = None;
+    let mut height = None;
+
+    for descriptor in descriptors {
+        let kind = descriptor.chars().last()?;
+        let number = &descriptor[..descriptor.len() - kind.len_utf8()];
+
+        match kind {
+            'w' if width.is_none() && density.is_none() => {
+                width = Some(parse_integer(number).filter(|&width| width > 0)?);
+            }
+            'x' if width.is_none() && density.is_none() && height.is_none() => {
+                density = Some(parse_float(number).filter(|&density| density >= 0.0)?);
+            }
+            'h' if height.is_none() && density.is_none() => {
+                height = Some(parse_integer(number).filter(|&height| height > 0)?);
+            }
+            _ => return None,
+        }
+    }
+
+    match (width, density, height) {
+        (Some(width), _, _) => Some(Descriptor::Width(width)),
+        // a height descriptor is only valid along with a width descriptor
+        (None, _, Some(_)) => None,
+        (None, Some(density), None) => Some(Descriptor::Density(density)),
+        (None, None, None) => Some(Descriptor::default()),
+    }
+}
+
+/// Parses a valid non-negative integer, which consists of digits only
+fn parse_integer(value: &str) -> Option<u32> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+/// Parses a valid floating-point number, which is like `-1.5e3` without a leading `+` or `.`
+fn parse_float(value: &str) -> Option<f64> {
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    let rest = value.strip_prefix('-').unwrap_or(value);
+    let integer = digits(rest);
+    if integer == 0 {
+        return None;
+    }
+    let mut rest = &rest[integer..];
+
+    if let Some(after) = rest.strip_prefix('.') {
+        let fraction = digits(after);
+        if fraction == 0 {
+            return None;
+        }
+        rest = &after[fraction..];
+    }
+
+    if let Some(after) = rest.strip_prefix(['e', 'E']) {
+        let after = after.strip_prefix(['-', '+']).unwrap_or(after);
+        let exponent = digits(after);
+        if exponent == 0 {
+            return None;
+        }
+        rest = &after[exponent..];
+    }
+
+    if !rest.is_empty() {
+        return None;
+    }
+
+    value.parse().ok().filter(|value: &f64| value.is_finite())
+}
+
+/// Parses the value of a `sizes` attribute into its source sizes
+///
+/// Sizes are separated by commas outside of parentheses, and the size is the last component of each,
+/// with everything before it being the media condition. Empty entries are left out,
+/// but the media conditions and sizes are not validated.
+///
+/// # Example
+/// ```
+/// use tl::srcset::{parse_sizes, SourceSize};
+///
+/// let sizes = parse_sizes("(max-width: 600px) 480px, calc(100vw - 2em)");
+///
+/// assert_eq!(sizes, [
+///     SourceSize { media: Some("(max-width: 600px)".into()), size: "480px".into() },
+///     SourceSize { media: None, size: "calc(100vw - 2em)".into() },
+/// ]);
+/// ```
+pub fn parse_sizes(value: &str) -> Vec<SourceSize> {
+    let mut sizes = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (index, c) in value
+        .char_indices()
+        .chain(std::iter::once((value.len(), ',')))
+    {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 || index == value.len() => {
+                if let Some(size) = parse_source_size(&value[start..index]) {
+                    sizes.push(size);
+                }
+                start = index + 1;
+                depth = 0;
+            }
+            _ => {}
+        }
+    }
+
+    sizes
+}
+
+/// Splits a source size into its media condition and its size, which is the last component value
+fn parse_source_size(value: &str) -> Option<SourceSize> {
+    let value = value.trim_matches(|c: char| c.is_ascii_whitespace());
+    if value.is_empty() {
+        return None;
+    }
+
+    let start = if value.ends_with(')') {
+        // find the opening parenthesis of a function such as calc(...) and the name before it
+        let mut depth = 0usize;
+        let mut open = 0;
+
+        for (index, c) in value.char_indices().rev() {
+            match c {
+                ')' => depth += 1,
+                '(' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        open = index;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        value[..open]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map_or(0, |index| index + 1)
+    } else {
+        value
+            .rfind(|c: char| c.is_ascii_whitespace())
+            .map_or(0, |index| index + 1)
+    };
+
+    let media = value[..start].trim_end_matches(|c: char| c.is_ascii_whitespace());
+
+    Some(SourceSize {
+        media: (!media.is_empty()).then(|| media.to_string()),
+        size: value[start..].to_string(),
+    })
+}
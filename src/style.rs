@@ -0,0 +1,384 @@
+use std::fmt;
+
+use crate::errors::StyleError;
+
+/// A declaration of an inline style, such as `color: red !important`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration {
+    /// The name of the property, which is lowercase unless it is a custom property like `--main-color`
+    pub property: String,
+    /// The value of the property without `!important` and without comments
+    pub value: String,
+    /// Whether the declaration is marked `!important`
+    pub important: bool,
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.property, self.value)?;
+
+        if self.important {
+            f.write_str(" !important")?;
+        }
+
+        f.write_str(";")
+    }
+}
+
+/// The declarations of an inline `style` attribute, see [`Attributes::style()`](crate::Attributes::style)
+///
+/// Declarations are kept in source order, including repeated properties, so that all of them can be inspected.
+/// [`Style::get()`] returns the declaration that applies, like browsers do.
+///
+/// Formatting a style with `Display` serializes it like the `cssText` of a declaration block in browsers,
+/// for example `color: red; display: none !important;`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Style {
+    declarations: Vec<Declaration>,
+}
+
+impl Style {
+    /// Creates an empty style
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the declarations of a `style` attribute
+    ///
+    /// Malformed declarations are skipped like CSS error recovery does: a declaration that does not start with
+    /// a property name followed by a colon, or that has an empty value, is dropped up to the next `;` that is
+    /// not inside of a string, a comment or brackets, and parsing continues after it.
+    ///
+    /// # Example
+    /// ```
+    /// use tl::style::Style;
+    ///
+    /// let style = Style::parse("color: red; 1bad: x; background: url('a;b.png') !IMPORTANT; display:none");
+    ///
+    /// assert_eq!(style.len(), 3);
+    /// assert_eq!(style.get("background").map(|d| d.value.as_str()), Some("url('a;b.png')"));
+    /// assert!(style.get("background").unwrap().important);
+    /// assert!(style.is_hidden());
+    /// ```
+    pub fn parse(input: &str) -> Self {
+        let declarations = split_declarations(input)
+            .iter()
+            .filter_map(|declaration| parse_declaration(declaration))
+            .collect();
+
+        Self { declarations }
+    }
+
+    /// Returns all declarations in order
+    pub fn declarations(&self) -> &[Declaration] {
+        &self.declarations
+    }
+
+    /// Returns the number of declarations
+    pub fn len(&self) -> usize {
+        self.declarations.len()
+    }
+
+    /// Checks whether this style has no declarations
+    pub fn is_empty(&self) -> bool {
+        self.declarations.is_empty()
+    }
+
+    /// Returns an iterator over all declarations in order
+    pub fn iter(&self) -> impl Iterator<Item = &Declaration> + '_ {
+        self.declarations.iter()
+    }
+
+    /// Returns the declaration of a property that applies
+    ///
+    /// This is the last `!important` declaration of the property, or the last declaration if none of them is important.
+    /// Property names are matched case-insensitively, except for custom properties.
+    pub fn get(&self, property: &str) -> Option<&Declaration> {
+        let property = normalize_property(property);
+        let mut matching = self
+            .declarations
+            .iter()
+            .filter(|declaration| declaration.property == property);
+
+        matching
+            .clone()
+            .rfind(|declaration| declaration.important)
+            .or_else(|| matching.next_back())
+    }
+
+    /// Returns the value of the declaration of a property that applies, see [`Style::get()`]
+    pub fn value(&self, property: &str) -> Option<&str> {
+        self.get(property)
+            .map(|declaration| declaration.value.as_str())
+    }
+
+    /// Sets the value of a property
+    ///
+    /// If the property is already declared, its first declaration is replaced and all other declarations of it are removed.
+    /// Otherwise, the declaration is appended.
+    ///
+    /// # Errors
+    /// Returns an error if `property` is not an identifier, or if `value` could not be parsed back as the value
+    /// of this declaration: it is empty (unless the property is a custom property), contains a `;` or `!`
+    /// outside of strings and brackets, contains a comment, or has unbalanced brackets or quotes.
+    /// Use the `important` flag rather than appending `!important` to the value.
+    ///
+    /// # Example
+    /// ```
+    /// use tl::{errors::StyleError, style::Style};
+    ///
+    /// let mut style = Style::parse("color: red");
+    /// style.set("background", "url('a;b.png')", true).unwrap();
+    ///
+    /// assert_eq!(style.set("color", "blue; display: none", false), Err(StyleError::InvalidValue));
+    /// assert_eq!(style.to_string(), "color: red; background: url('a;b.png') !important;");
+    /// ```
+    pub fn set(&mut self, property: &str, value: &str, important: bool) -> Result<(), StyleError> {
+        let property = property.trim_matches(is_whitespace);
+        if !is_ident(property) {
+            return Err(StyleError::InvalidProperty);
+        }
+
+        let declaration = Declaration {
+            property: normalize_property(property),
+            value: value.trim_matches(is_whitespace).to_string(),
+            important,
+        };
+
+        if !is_value(&declaration.value)
+            || (declaration.value.is_empty() && !declaration.property.starts_with("--"))
+        {
+            return Err(StyleError::InvalidValue);
+        }
+
+        match self
+            .declarations
+            .iter()
+            .position(|d| d.property == declaration.property)
+        {
+            Some(index) => {
+                let property = declaration.property.clone();
+                self.declarations[index] = declaration;
+
+                let mut position = 0;
+                self.declarations.retain(|d| {
+                    position += 1;
+                    position - 1 == index || d.property != property
+                });
+            }
+            None => self.declarations.push(declaration),
+        }
+
+        Ok(())
+    }
+
+    /// Removes all declarations of a property, and returns the one that applied
+    pub fn remove(&mut self, property: &str) -> Option<Declaration> {
+        let removed = self.get(property).cloned()?;
+        self.declarations.retain(|d| d.property != removed.property);
+        Some(removed)
+    }
+
+    /// Checks whether this style hides the element with `display: none`, or `visibility: hidden` or `collapse`
+    pub fn is_hidden(&self) -> bool {
+        let value = |property| self.value(property).map(str::to_ascii_lowercase);
+
+        matches!(value("display").as_deref(), Some("none"))
+            || matches!(value("visibility").as_deref(), Some("hidden" | "collapse"))
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, declaration) in self.declarations.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+
+            write!(f, "{declaration}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'s> IntoIterator for &'s Style {
+    type Item = &'s Declaration;
+    type IntoIter = std::slice::Iter<'s, Declaration>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.declarations.iter()
+    }
+}
+
+/// Lowercases a property name, unless it is a custom property
+fn normalize_property(property: &str) -> String {
+    let property = property.trim();
+
+    if property.starts_with("--") {
+        property.to_string()
+    } else {
+        property.to_ascii_lowercase()
+    }
+}
+
+/// Splits the input on the semicolons that are not inside of strings, comments or brackets, and removes comments
+fn split_declarations(input: &str) -> Vec<String> {
+    let mut declarations = Vec::new();
+    let mut current = String::new();
+    // the closing brackets of the open blocks
+    let mut blocks: Vec<char> = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            '"' | '\'' => {
+                current.push(c);
+
+                while let Some(s) = chars.next() {
+                    current.push(s);
+
+                    match s {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                current.push(escaped);
+                            }
+                        }
+                        // an unterminated string ends at the end of the line
+                        '\n' => break,
+                        s if s == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                let mut previous = ' ';
+                for s in chars.by_ref() {
+                    if previous == '*' && s == '/' {
+                        break;
+                    }
+                    previous = s;
+                }
+            }
+            '(' | '[' | '{' => {
+                blocks.push(match c {
+                    '(' => ')',
+                    '[' => ']',
+                    _ => '}',
+                });
+                current.push(c);
+            }
+            ')' | ']' | '}' => {
+                if blocks.last() == Some(&c) {
+                    blocks.pop();
+                }
+                current.push(c);
+            }
+            ';' if blocks.is_empty() => declarations.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    declarations.push(current);
+    declarations
+}
+
+/// Checks whether a value can be serialized as part of a single declaration and parsed back unchanged
+///
+/// This is the case if all strings and brackets are closed, and it has no comments and no `;` or `!` outside of them.
+fn is_value(value: &str) -> bool {
+    // the closing brackets of the open blocks
+    let mut blocks: Vec<char> = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // an escape at the end would escape whatever follows the value
+            '\\' if chars.next().is_none() => return false,
+            '"' | '\'' => loop {
+                match chars.next() {
+                    Some('\\') if chars.next().is_none() => return false,
+                    Some(s) if s == c => break,
+                    None | Some('\n') => return false,
+                    _ => {}
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => return false,
+            '(' => blocks.push(')'),
+            '[' => blocks.push(']'),
+            '{' => blocks.push('}'),
+            ')' | ']' | '}' if blocks.pop() != Some(c) => return false,
+            ';' | '!' if blocks.is_empty() => return false,
+            _ => {}
+        }
+    }
+
+    blocks.is_empty()
+}
+
+/// Parses a single declaration, or returns `None` if it is malformed
+fn parse_declaration(input: &str) -> Option<Declaration> {
+    let input = input.trim_matches(is_whitespace);
+    let (property, value) = input.split_once(':')?;
+    let property = property.trim_end_matches(is_whitespace);
+
+    if !is_ident(property) {
+        return None;
+    }
+
+    let mut value = value.trim_matches(is_whitespace);
+    let mut important = false;
+
+    if let Some(index) = value.rfind('!') {
+        let flag = value[index + 1..].trim_start_matches(is_whitespace);
+
+        if flag.eq_ignore_ascii_case("important") {
+            important = true;
+            value = value[..index].trim_end_matches(is_whitespace);
+        }
+    }
+
+    let property = normalize_property(property);
+
+    // only custom properties may have an empty value
+    if value.is_empty() && !property.starts_with("--") {
+        return None;
+    }
+
+    Some(Declaration {
+        property,
+        value: value.to_string(),
+        important,
+    })
+}
+
+/// Checks whether a property name is an identifier, such as `color`, `-webkit-box-shadow` or `--main-color`
+fn is_ident(name: &str) -> bool {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii();
+    let rest = name
+        .strip_prefix("--")
+        .or_else(|| name.strip_prefix('-'))
+        .unwrap_or(name);
+
+    if name.starts_with("--") {
+        return name.chars().all(is_name_char);
+    }
+
+    rest.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii())
+        && rest.chars().all(is_name_char)
+}
+
+/// Checks whether a character is CSS whitespace
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
@@ -0,0 +1,394 @@
+use crate::util::collapse_whitespace;
+use crate::{entities, Edge, Node, NodeHandle, Parser};
+
+/// The largest `colspan` that is honored, as in browsers
+const MAX_COLSPAN: usize = 1000;
+/// The largest `rowspan` that is honored, as in browsers
+const MAX_ROWSPAN: usize = 65534;
+
+/// The row group that a row belongs to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Section {
+    /// A row in a `<thead>`
+    Head,
+    /// A row in a `<tbody>`, or directly in the `<table>`
+    #[default]
+    Body,
+    /// A row in a `<tfoot>`
+    Foot,
+}
+
+/// A cell of a [`Table`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// The `<td>` or `<th>` element
+    pub handle: NodeHandle,
+    /// The text of the cell, with character references decoded and whitespace collapsed
+    pub text: String,
+    /// Whether the cell is a `<th>` element
+    pub is_header: bool,
+    /// The row of the top left slot that this cell occupies
+    pub row: usize,
+    /// The column of the top left slot that this cell occupies
+    pub column: usize,
+    /// The number of rows that this cell spans
+    pub rowspan: usize,
+    /// The number of columns that this cell spans
+    pub colspan: usize,
+}
+
+/// A table as a rectangular grid of cells, see [`parse_table()`]
+///
+/// Cells that span multiple rows or columns occupy all of the slots they span,
+/// and slots that are not covered by any cell are empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Table {
+    caption: Option<String>,
+    cells: Vec<Cell>,
+    /// The index of the cell in every slot, by row
+    grid: Vec<Vec<Option<usize>>>,
+    width: usize,
+    sections: Vec<Section>,
+    header_rows: usize,
+}
+
+impl Table {
+    /// Returns the text of the `<caption>`, if the table has one
+    pub fn caption(&self) -> Option<&str> {
+        self.caption.as_deref()
+    }
+
+    /// Returns the number of rows
+    pub fn height(&self) -> usize {
+        self.grid.len()
+    }
+
+    /// Returns the number of columns
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns all cells in the order in which they appear in the grid, row by row
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Returns the cell that occupies a slot, if any
+    pub fn cell(&self, row: usize, column: usize) -> Option<&Cell> {
+        let index = (*self.grid.get(row)?.get(column)?)?;
+        self.cells.get(index)
+    }
+
+    /// Returns the cells of a row, with one entry for every column
+    pub fn row(&self, row: usize) -> Option<Vec<Option<&Cell>>> {
+        let slots = self.grid.get(row)?;
+        Some(
+            slots
+                .iter()
+                .map(|index| index.and_then(|index| self.cells.get(index)))
+                .collect(),
+        )
+    }
+
+    /// Returns the row group of a row
+    pub fn section(&self, row: usize) -> Option<Section> {
+        self.sections.get(row).copied()
+    }
+
+    /// Returns the number of header rows, which are at the top of the grid
+    ///
+    /// These are the rows of the `<thead>`, or if there is none, the leading rows that only contain `<th>` cells.
+    pub fn header_rows(&self) -> usize {
+        self.header_rows
+    }
+
+    /// Returns the text of every slot, with an empty string for slots that are not covered by any cell
+    pub fn text_grid(&self) -> Vec<Vec<String>> {
+        (0..self.height())
+            .map(|row| {
+                (0..self.width)
+                    .map(|column| {
+                        self.cell(row, column)
+                            .map_or_else(String::new, |cell| cell.text.clone())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A cell before it is placed in the grid
+struct PendingCell {
+    handle: NodeHandle,
+    text: String,
+    is_header: bool,
+    rowspan: usize,
+    colspan: usize,
+}
+
+/// A row before it is placed in the grid
+struct PendingRow {
+    section: Section,
+    group: usize,
+    cells: Vec<PendingCell>,
+}
+
+/// Extracts the rows and cells of a `<table>` element into a rectangular grid
+///
+/// Rows and cells are found in document order, so cells with an omitted `</td>` or `</th>`
+/// and rows with an omitted `</tr>`, which are nested in the previous cell, are still separate.
+/// The contents of nested tables are part of the text of the cell that contains them.
+/// Rows of a `<tfoot>` are moved to the end of the table, like browsers do.
+/// `rowspan` and `colspan` are expanded, where a `rowspan` of 0 extends the cell to the end of its row group.
+///
+/// Returns `None` if `handle` is not a `<table>` element.
+///
+/// # Example
+/// ```
+/// let input = r#"<table>
+///   <caption>Support</caption>
+///   <thead><tr><th>Version</th><th>Linux</th><th>macOS</th></tr></thead>
+///   <tr><td>1.0<td colspan="2">yes
+///   <tr><td>2.0<td>yes<td>no
+/// </table>"#;
+///
+/// let dom = tl::parse(input, Default::default()).unwrap();
+/// let handle = dom.query_selector("table").unwrap().next().unwrap();
+/// let table = tl::table::parse_table(dom.parser(), handle).unwrap();
+///
+/// assert_eq!(table.caption(), Some("Support"));
+/// assert_eq!(table.header_rows(), 1);
+/// assert_eq!(table.text_grid(), [
+///     ["Version", "Linux", "macOS"],
+///     ["1.0", "yes", "yes"],
+///     ["2.0", "yes", "no"],
+/// ]);
+/// ```
+pub fn parse_table(parser: &Parser<'_>, handle: NodeHandle) -> Option<Table> {
+    let root = handle.get(parser)?.as_tag()?;
+    if !root.name().as_bytes().eq_ignore_ascii_case(b"table") {
+        return None;
+    }
+
+    let mut caption: Option<String> = None;
+    let mut rows: Vec<PendingRow> = Vec::new();
+    let mut section = Section::Body;
+    // the row group, which changes whenever a `<thead>`, `<tbody>` or `<tfoot>` is opened or closed
+    let mut group = 0;
+
+    // whether text belongs to the caption rather than the current cell
+    let mut in_caption = false;
+    // the number of open tables inside of the table, whose contents are part of the current cell
+    let mut nested: usize = 0;
+
+    let mut edges = handle.traverse(parser);
+    edges.next();
+
+    while let Some((edge, _)) = edges.next() {
+        let node = match edge.handle().get(parser) {
+            Some(node) => node,
+            None => continue,
+        };
+
+        let tag = match (edge, node) {
+            (Edge::Open(_), Node::Raw(text)) => {
+                let target = if in_caption {
+                    caption.as_mut()
+                } else {
+                    rows.last_mut()
+                        .and_then(|row| row.cells.last_mut())
+                        .map(|cell| &mut cell.text)
+                };
+
+                if let Some(target) = target {
+                    target.push_str(&entities::decode(&text.as_utf8_str()));
+                }
+                continue;
+            }
+            (_, Node::Raw(_) | Node::Comment(_)) => continue,
+            // the table itself is closed last
+            (Edge::Close(closed), _) if closed == handle => break,
+            (Edge::Close(_), Node::Tag(tag)) => {
+                let name = tag.name().as_utf8_str();
+
+                match name.to_ascii_lowercase().as_str() {
+                    "table" => {
+                        nested -= 1;
+                        push_space(&mut rows);
+                    }
+                    "caption" if nested == 0 => in_caption = false,
+                    "thead" | "tbody" | "tfoot" if nested == 0 => {
+                        section = Section::Body;
+                        group += 1;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            (Edge::Open(_), Node::Tag(tag)) => tag,
+        };
+
+        let name = tag.name().as_utf8_str().to_ascii_lowercase();
+
+        if nested > 0 {
+            if name == "table" {
+                nested += 1;
+            } else if name == "br" {
+                push_space(&mut rows);
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "table" => {
+                nested += 1;
+                push_space(&mut rows);
+            }
+            "caption" => {
+                in_caption = true;
+                caption.get_or_insert_with(String::new);
+            }
+            "colgroup" | "col" => edges.skip_subtree(),
+            "thead" | "tbody" | "tfoot" => {
+                section = match name.as_str() {
+                    "thead" => Section::Head,
+                    "tfoot" => Section::Foot,
+                    _ => Section::Body,
+                };
+                group += 1;
+            }
+            "tr" => {
+                in_caption = false;
+                rows.push(PendingRow {
+                    section,
+                    group,
+                    cells: Vec::new(),
+                });
+            }
+            "td" | "th" => {
+                in_caption = false;
+
+                if rows.is_empty() {
+                    rows.push(PendingRow {
+                        section,
+                        group,
+                        cells: Vec::new(),
+                    });
+                }
+
+                let span = |name: &str, max: usize, default: usize| {
+                    tag.attributes()
+                        .get(name)
+                        .flatten()
+                        .and_then(|value| value.as_utf8_str().trim().parse::<usize>().ok())
+                        .map_or(default, |value| value.min(max))
+                };
+
+                let cell = PendingCell {
+                    handle: edge.handle(),
+                    text: String::new(),
+                    is_header: name == "th",
+                    rowspan: span("rowspan", MAX_ROWSPAN, 1),
+                    colspan: span("colspan", MAX_COLSPAN, 1).max(1),
+                };
+
+                rows.last_mut().unwrap().cells.push(cell);
+            }
+            "br" => push_space(&mut rows),
+            _ => {}
+        }
+    }
+
+    // footer rows are rendered after all other rows
+    let (footer, mut rows): (Vec<_>, Vec<_>) = rows
+        .into_iter()
+        .partition(|row| row.section == Section::Foot);
+    rows.extend(footer);
+
+    let mut table = build(rows);
+    table.caption = caption.map(|caption| collapse_whitespace(&caption));
+
+    Some(table)
+}
+
+/// Separates the text of the current cell from the text that follows, for elements like `<br>`
+fn push_space(rows: &mut [PendingRow]) {
+    if let Some(cell) = rows.last_mut().and_then(|row| row.cells.last_mut()) {
+        cell.text.push(' ');
+    }
+}
+
+/// Places the cells of the rows in a grid
+fn build(rows: Vec<PendingRow>) -> Table {
+    let mut table = Table {
+        sections: rows.iter().map(|row| row.section).collect(),
+        ..Table::default()
+    };
+
+    let groups: Vec<usize> = rows.iter().map(|row| row.group).collect();
+    table.grid = vec![Vec::new(); rows.len()];
+
+    for (r, row) in rows.into_iter().enumerate() {
+        // the end of the row group of this row, for cells with a rowspan of 0
+        let group_end = groups[r..]
+            .iter()
+            .position(|&group| group != groups[r])
+            .map_or(groups.len(), |len| r + len);
+
+        let mut column = 0;
+
+        for cell in row.cells {
+            while table.grid[r].get(column).is_some_and(Option::is_some) {
+                column += 1;
+            }
+
+            let rowspan = match cell.rowspan {
+                0 => group_end - r,
+                rowspan => rowspan.min(group_end - r),
+            };
+
+            let index = table.cells.len();
+            table.cells.push(Cell {
+                handle: cell.handle,
+                text: collapse_whitespace(&cell.text),
+                is_header: cell.is_header,
+                row: r,
+                column,
+                rowspan,
+                colspan: cell.colspan,
+            });
+
+            for slots in &mut table.grid[r..r + rowspan] {
+                if slots.len() < column + cell.colspan {
+                    slots.resize(column + cell.colspan, None);
+                }
+
+                for slot in &mut slots[column..column + cell.colspan] {
+                    // overlapping cells keep the slot of the cell that was placed first
+                    slot.get_or_insert(index);
+                }
+            }
+
+            column += cell.colspan;
+        }
+    }
+
+    table.width = table.grid.iter().map(Vec::len).max().unwrap_or(0);
+
+    for slots in &mut table.grid {
+        slots.resize(table.width, None);
+    }
+
+    table.header_rows = match table.sections.iter().position(|&s| s != Section::Head) {
+        Some(0) => (0..table.height())
+            .take_while(|&row| {
+                let mut cells = table.row(row).unwrap().into_iter().flatten().peekable();
+                cells.peek().is_some() && cells.all(|cell| cell.is_header)
+            })
+            .count(),
+        Some(rows) => rows,
+        None => table.height(),
+    };
+
+    table
+}
@@ -1,4 +1,4 @@
-use crate::{parse, parse_owned, Bytes};
+use crate::{parse, Bytes};
 use crate::{parser::*, HTMLTag, Node};
 
 fn force_as_tag<'a, 'b>(actual: &'a Node<'b>) -> &'a HTMLTag<'b> {
@@ -126,6 +126,44 @@ fn html5() {
     assert_eq!(dom.children().len(), 1)
 }
 
+#[test]
+fn raw_text_elements() {
+    for name in [
+        "iframe", "noembed", "noframes", "script", "style", "textarea", "title", "xmp",
+    ] {
+        let input = format!("<div><{name} a=b><p>x</{name}s> &amp; </P></{name}><p>y</p></div>");
+        let dom = parse(&input, ParserOptions::default()).unwrap();
+        let parser = dom.parser();
+
+        let div = force_as_tag(dom.children()[0].get(parser).unwrap());
+        assert_eq!(div.children().top().len(), 2, "{name}");
+
+        // the contents are a single text node, and the end tag closes the element
+        let element = force_as_tag(div.children().top()[0].get(parser).unwrap());
+        assert_eq!(element.name(), name, "{name}");
+        assert_eq!(element.children().top().len(), 1, "{name}");
+        assert_eq!(
+            element.inner_html(parser),
+            format!("<p>x</{name}s> &amp; </P>"),
+            "{name}"
+        );
+        assert_eq!(dom.query_selector("p").unwrap().count(), 1, "{name}");
+    }
+
+    // <plaintext> has no end tag, so it contains the rest of the document
+    let dom = parse("<plaintext><p>a</plaintext><p>b", ParserOptions::default()).unwrap();
+    let parser = dom.parser();
+    assert_eq!(dom.nodes().len(), 2);
+    assert_eq!(
+        dom.children()[0].get(parser).unwrap().inner_html(parser),
+        "<p>a</plaintext><p>b"
+    );
+
+    // self-closing raw text elements have no contents
+    let dom = parse("<script/><p>a</p>", ParserOptions::default()).unwrap();
+    assert_eq!(dom.query_selector("p").unwrap().count(), 1);
+}
+
 #[test]
 fn ignore_void_closing_tags() {
     let input = r#"
@@ -183,11 +221,12 @@ fn nested_inner_text() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn owned_dom() {
     let owned_dom = {
         let input = String::from("<p id=\"test\">hello</p>");
 
-        unsafe { parse_owned(input, ParserOptions::default()).unwrap() }
+        unsafe { crate::parse_owned(input, ParserOptions::default()).unwrap() }
     };
 
     let dom = owned_dom.get_ref();
@@ -199,10 +238,11 @@ fn owned_dom() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn move_owned() {
     let input = String::from("<p id=\"test\">hello</p>");
 
-    let guard = unsafe { parse_owned(input, ParserOptions::default()).unwrap() };
+    let guard = unsafe { crate::parse_owned(input, ParserOptions::default()).unwrap() };
 
     fn move_me<T>(p: T) -> T {
         p
@@ -536,6 +576,21 @@ mod bytes {
         let _x = Bytes::new();
     }
 
+    #[test]
+    fn size() {
+        #[cfg(not(feature = "span64"))]
+        assert_eq!(std::mem::size_of::<Bytes>(), 16);
+        #[cfg(feature = "span64")]
+        assert_eq!(std::mem::size_of::<Bytes>(), 24);
+    }
+
+    #[test]
+    fn into_owned() {
+        let x = Bytes::from("hello").into_owned();
+        assert_eq!(x.as_bytes_borrowed(), None);
+        assert_eq!(x.as_bytes(), b"hello");
+    }
+
     #[test]
     fn empty_set() {
         let mut x = Bytes::new();
@@ -1004,3 +1059,3388 @@ fn double_space_before_closing_bracket() {
     assert_eq!(attrs.get("href").unwrap().unwrap().as_utf8_str(), "url");
     assert_eq!(attrs.get("rel").unwrap().unwrap().as_utf8_str(), "internal");
 }
+
+#[cfg(feature = "markdown")]
+mod markdown {
+    use super::*;
+    use crate::markdown::{node_to_markdown, to_markdown, MarkdownOptions, UnknownElements};
+
+    fn convert(input: &str) -> String {
+        let dom = parse(input, ParserOptions::default()).unwrap();
+        to_markdown(&dom, &MarkdownOptions::default())
+    }
+
+    #[test]
+    fn headings_and_paragraphs() {
+        assert_eq!(
+            convert("<h1>Title</h1>\n<p>Some   <em>emphasized</em>\n and <strong>strong</strong> text.</p><h3>Sub</h3>"),
+            "# Title\n\nSome *emphasized* and **strong** text.\n\n### Sub"
+        );
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(convert("<p>2 * 3 = 6_</p>"), "2 \\* 3 = 6\\_");
+        assert_eq!(convert("<p># not a heading</p>"), "\\# not a heading");
+        assert_eq!(convert("<p>1. not a list</p>"), "1\\. not a list");
+    }
+
+    #[test]
+    fn decodes_and_escapes_text() {
+        assert_eq!(convert("<p>a &lt;b&gt; &amp; c</p>"), "a \\<b> & c");
+        assert_eq!(convert("a &amp;amp; &lt;div&gt;"), "a \\&amp; \\<div>");
+        assert_eq!(convert("<p><em>&#42;</em></p>"), "*\\**");
+    }
+
+    #[test]
+    fn valueless_attributes() {
+        assert_eq!(convert("<p><a href>text</a></p>"), "text");
+        assert_eq!(convert("<p><img src alt=\"x\">y</p>"), "y");
+    }
+
+    #[test]
+    fn escapes_block_starts() {
+        // a fence would swallow the rest of the document
+        assert_eq!(convert("<p>~~~</p><p>after</p>"), "\\~~~\n\nafter");
+        assert_eq!(convert("<p><del>~x</del></p>"), "\\~~~x~~");
+        assert_eq!(convert("<p><code>a</code></p>"), "`a`");
+
+        // literal asterisks are escaped, so they cannot form a thematic break
+        assert_eq!(convert("<p>* * *</p>"), "\\* \\* \\*");
+
+        // block syntax can also interrupt a paragraph after a line break
+        assert_eq!(
+            convert("<p>a<br>~~~ b<br># c<br>d</p>"),
+            "a\\\n\\~~~ b\\\n\\# c\\\nd"
+        );
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let count = if cfg!(miri) { 300usize } else { 200_000usize };
+
+        assert_eq!(convert(&("<div>".repeat(count) + "a &amp; b")), "a & b");
+
+        let emphasized = convert(&("<em>".repeat(count) + "text"));
+        assert!(emphasized.contains("text"));
+        assert!(!emphasized.contains("<em>"));
+    }
+
+    #[test]
+    fn links_and_images() {
+        let dom = parse(
+            r#"<p><a href="../a?x=1&amp;y=2" title="A">link</a> <img src="/img.png" alt="pic"></p>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+        let options = MarkdownOptions::new().base_url("https://example.com/docs/page.html");
+
+        assert_eq!(
+            to_markdown(&dom, &options),
+            r#"[link](https://example.com/a?x=1&y=2 "A") ![pic](https://example.com/img.png)"#
+        );
+    }
+
+    #[test]
+    fn nested_lists() {
+        assert_eq!(
+            convert("<ul><li>one</li><li>two<ul><li>nested</li></ul></li></ul><ol start=\"3\"><li>three</li><li>four</li></ol>"),
+            "- one\n- two\n  - nested\n\n3. three\n4. four"
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            convert("<pre><code class=\"language-rust\">fn main() {\n    let x = &amp;1;\n}\n</code></pre>"),
+            "```rust\nfn main() {\n    let x = &1;\n}\n```"
+        );
+        assert_eq!(
+            convert("<p>Use <code>a &lt; b</code> or <code>`x`</code></p>"),
+            "Use `a < b` or `` `x` ``"
+        );
+    }
+
+    #[test]
+    fn blockquote() {
+        assert_eq!(
+            convert("<blockquote><p>first</p><p>second</p></blockquote>"),
+            "> first\n>\n> second"
+        );
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            convert("<table><thead><tr><th>Name</th><th>Version</th></tr></thead><tbody><tr><td>tl</td><td>0|7</td></tr><tr><td>x</td></tr></tbody></table>"),
+            "| Name | Version |\n| --- | --- |\n| tl | 0\\|7 |\n| x |  |"
+        );
+    }
+
+    #[test]
+    fn unknown_elements() {
+        let input = "<p>a <custom-tag>b</custom-tag> c</p><script>ignored()</script>";
+        let dom = parse(input, ParserOptions::default()).unwrap();
+
+        let unwrap = MarkdownOptions::new();
+        let remove = MarkdownOptions::new().unknown_elements(UnknownElements::Remove);
+        let html = MarkdownOptions::new().unknown_elements(UnknownElements::Html);
+
+        assert_eq!(to_markdown(&dom, &unwrap), "a b c");
+        assert_eq!(to_markdown(&dom, &remove), "a c");
+        assert_eq!(to_markdown(&dom, &html), "a <custom-tag>b</custom-tag> c");
+
+        let dom = parse(
+            "<p>a <custom-tag>\n  b\n\n  # c\n</custom-tag> d</p>",
+            ParserOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            to_markdown(&dom, &html),
+            "a <custom-tag> b # c </custom-tag> d"
+        );
+    }
+
+    #[test]
+    fn subtree() {
+        let dom = parse(
+            "<div><p>skip</p><div id=\"x\">Hello<br>World</div></div>",
+            ParserOptions::default(),
+        )
+        .unwrap();
+        let handle = dom.get_element_by_id("x").unwrap();
+
+        assert_eq!(
+            node_to_markdown(dom.parser(), handle, &MarkdownOptions::default()),
+            "Hello\\\nWorld"
+        );
+    }
+}
+
+mod sanitize {
+    use crate::sanitize::{sanitize, Disallowed, Policy};
+
+    /// Known XSS vectors, most of them taken from the OWASP filter evasion cheat sheet
+    const XSS_VECTORS: &[&str] = &[
+        r#"<script>alert(1)</script>"#,
+        r#"<SCRIPT SRC=http://xss.example/xss.js></SCRIPT>"#,
+        r#"<IMG SRC="javascript:alert('XSS');">"#,
+        r#"<IMG SRC=javascript:alert('XSS')>"#,
+        r#"<IMG SRC=JaVaScRiPt:alert('XSS')>"#,
+        r#"<IMG SRC="jav	ascript:alert('XSS');">"#,
+        r#"<IMG SRC="jav&#x09;ascript:alert('XSS');">"#,
+        r#"<IMG SRC="jav&#x0A;ascript:alert('XSS');">"#,
+        r#"<IMG SRC=" &#14;  javascript:alert('XSS');">"#,
+        r#"<IMG SRC=&#106;&#97;&#118;&#97;&#115;&#99;&#114;&#105;&#112;&#116;&#58;&#97;&#108;&#101;&#114;&#116;&#40;&#39;&#88;&#83;&#83;&#39;&#41;>"#,
+        r#"<IMG SRC=&#x6A&#x61&#x76&#x61&#x73&#x63&#x72&#x69&#x70&#x74&#x3A&#x61&#x6C&#x65&#x72&#x74&#x28&#x27&#x58&#x53&#x53&#x27&#x29>"#,
+        r#"<a href="javascript&colon;alert(1)">x</a>"#,
+        r#"<a href="  JAVASCRIPT:alert(1)">x</a>"#,
+        r#"<a href="vbscript:msgbox(1)">x</a>"#,
+        r#"<a href="data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==">x</a>"#,
+        r#"<img src=x onerror=alert(1)>"#,
+        r#"<img src="x" ONERROR="alert(1)">"#,
+        r#"<body onload=alert(1)>"#,
+        r#"<svg/onload=alert(1)>"#,
+        r#"<svg><script>alert(1)</script></svg>"#,
+        r#"<iframe src="javascript:alert(1)"></iframe>"#,
+        r#"<object data="javascript:alert(1)"></object>"#,
+        r#"<embed src="javascript:alert(1)">"#,
+        r#"<math><mtext><table><mglyph><style><img src=x onerror=alert(1)>"#,
+        r#"<noscript><p title="</noscript><img src=x onerror=alert(1)>">"#,
+        r#"<div style="background:url(javascript:alert(1))">x</div>"#,
+        r##"<a href="#" onclick="alert(1)">x</a>"##,
+        r#"<form action="javascript:alert(1)"><button>x</button></form>"#,
+        r#"<meta http-equiv="refresh" content="0;url=javascript:alert(1)">"#,
+        r#"<link rel="stylesheet" href="javascript:alert(1)">"#,
+        r#"<base href="javascript:alert(1)//">"#,
+        r#"<p title="&quot;><script>alert(1)</script>">x</p>"#,
+        r#"<<script>alert(1);//<</script>"#,
+        r#"<scr<script>ipt>alert(1)</script>"#,
+        r#"<!--<img src="--><img src=x onerror=alert(1)//">"#,
+        r#"<style>@import 'javascript:alert(1)';</style>"#,
+        r#"<a href="java&#0000115;cript:alert(1)">x</a>"#,
+        r#"<input onfocus=alert(1) autofocus>"#,
+        r#"<details open ontoggle=alert(1)>"#,
+        r#"<video><source onerror="alert(1)"></video>"#,
+    ];
+
+    /// Re-parses the sanitized output and checks that it only contains allowed markup
+    fn assert_safe(vector: &str, output: &str, policy: &Policy) {
+        assert!(
+            !output.to_ascii_lowercase().contains("<script"),
+            "{} -> {}",
+            vector,
+            output
+        );
+
+        let dom = crate::parse(output, Default::default()).unwrap();
+
+        for tag in dom.nodes().iter().filter_map(|n| n.as_tag()) {
+            let name = tag.name().as_utf8_str();
+            assert!(policy.is_tag_allowed(&name), "{} -> {}", vector, output);
+
+            for (key, value) in tag.attributes().iter() {
+                assert!(
+                    policy.is_attribute_allowed(&name, &key) || key == "rel",
+                    "{} -> {}",
+                    vector,
+                    output
+                );
+
+                if key == "href" || key == "src" {
+                    let value = value.unwrap_or_default();
+                    assert!(!value.contains(':') || value.starts_with("http"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn xss_corpus() {
+        let policies = [
+            Policy::default(),
+            Policy::default().disallowed(Disallowed::Escape),
+            Policy::default().disallowed(Disallowed::Remove),
+        ];
+
+        for policy in &policies {
+            for vector in XSS_VECTORS {
+                let output = sanitize(vector, policy).unwrap();
+                assert_safe(vector, &output, policy);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_allowed_markup() {
+        let output = sanitize(
+            r#"<h1 title="Hi">Title</h1><p>Some <b>bold</b> &amp; <i>italic</i> text</p><img src="/logo.png" alt="logo">"#,
+            &Policy::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            r#"<h1 title="Hi">Title</h1><p>Some <b>bold</b> &amp; <i>italic</i> text</p><img alt="logo" src="/logo.png">"#
+        );
+    }
+
+    #[test]
+    fn forces_link_rel() {
+        let output = sanitize(
+            r#"<a href="https://example.com" rel="opener" target="_blank">x</a>"#,
+            &Policy::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            r#"<a href="https://example.com" rel="noopener noreferrer">x</a>"#
+        );
+    }
+
+    #[test]
+    fn disallowed_modes() {
+        let input = "<p>a <marquee>b</marquee> <script>c</script>d</p>";
+
+        let strip = Policy::new().allow_tags(["p"]);
+        let escape = strip.clone().disallowed(Disallowed::Escape);
+        let remove = strip.clone().disallowed(Disallowed::Remove);
+
+        assert_eq!(sanitize(input, &strip).unwrap(), "<p>a b d</p>");
+        assert_eq!(
+            sanitize(input, &escape).unwrap(),
+            "<p>a &lt;marquee&gt;b&lt;/marquee&gt; d</p>"
+        );
+        assert_eq!(sanitize(input, &remove).unwrap(), "<p>a  d</p>");
+    }
+
+    #[test]
+    fn escapes_attribute_values() {
+        let policy = Policy::new()
+            .allow_tags(["p"])
+            .allow_attributes("p", ["title"]);
+
+        assert_eq!(
+            sanitize(r#"<p title='"><b>'>x</p>"#, &policy).unwrap(),
+            r#"<p title="&quot;&gt;&lt;b&gt;">x</p>"#
+        );
+    }
+
+    #[test]
+    fn url_schemes() {
+        let policy = Policy::default();
+
+        assert!(policy.is_url_allowed("https://example.com"));
+        assert!(policy.is_url_allowed("/relative:path"));
+        assert!(policy.is_url_allowed("?a=b:c"));
+        assert!(policy.is_url_allowed("MAILTO:someone@example.com"));
+        assert!(!policy.is_url_allowed("javascript:alert(1)"));
+        assert!(!policy.is_url_allowed(" java\tscript:alert(1)"));
+        assert!(!policy.is_url_allowed("ftp://example.com"));
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let count = if cfg!(miri) { 100usize } else { 100_000usize };
+        let input = "<b>".repeat(count);
+
+        let output = sanitize(&input, &Policy::default()).unwrap();
+        assert_eq!(output.len(), count * "<b></b>".len());
+    }
+}
+
+mod fragment {
+    use super::*;
+    use crate::errors::ParseError;
+
+    #[test]
+    fn replaces_children() {
+        let mut dom = parse(
+            r#"<div id="a"><p class="old">old</p></div><span>after</span>"#,
+            ParserOptions::default().track_ids().track_classes(),
+        )
+        .unwrap();
+        let a = dom.get_element_by_id("a").unwrap();
+
+        let handles = dom
+            .parse_fragment(a, r#"text<p id="new" class="fresh">new <b>bold</b></p>"#)
+            .unwrap();
+
+        assert_eq!(handles.len(), 2);
+        assert_eq!(
+            dom.outer_html(),
+            r#"<div id="a">text<p id="new" class="fresh">new <b>bold</b></p></div><span>after</span>"#
+        );
+
+        let new = dom.get_element_by_id("new").unwrap();
+        assert_eq!(new, handles[1]);
+        assert_eq!(
+            dom.get_elements_by_class_name("fresh").collect::<Vec<_>>(),
+            vec![new]
+        );
+        assert_eq!(dom.get_elements_by_class_name("old").count(), 0);
+
+        let parser = dom.parser();
+        let a = a.get(parser).unwrap().as_tag().unwrap();
+        assert_eq!(a.inner_text(parser), "textnew bold");
+        assert_eq!(a.query_selector(parser, "b").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn raw_text_context() {
+        let mut dom = parse("<textarea></textarea>", ParserOptions::default()).unwrap();
+        let textarea = dom.children()[0];
+
+        let handles = dom.parse_fragment(textarea, "<b>not bold</b>").unwrap();
+
+        assert_eq!(handles.len(), 1);
+        assert_eq!(
+            dom.parser()
+                .resolve_node_id(handles[0].get_inner())
+                .unwrap()
+                .as_raw()
+                .unwrap()
+                .as_utf8_str(),
+            "<b>not bold</b>"
+        );
+    }
+
+    #[test]
+    fn same_tree_as_parse() {
+        let cases = [
+            ("script", "if (a<b) { x = '<p>'; }"),
+            ("style", "a > b { content: '</p>' }"),
+            ("table", "<tr><td>a<td>b"),
+            ("select", "<option>a<option>b"),
+            ("ul", "<li>a<li>b"),
+        ];
+
+        for (context, html) in cases {
+            let document = format!("<{context}>{html}</{context}>");
+            let expected = parse(&document, ParserOptions::default()).unwrap();
+
+            let original = format!("<{context}>old</{context}>");
+            let mut dom = parse(&original, ParserOptions::default()).unwrap();
+            let element = dom.children()[0];
+            dom.parse_fragment(element, html).unwrap();
+
+            let parser = dom.parser();
+            let expected_parser = expected.parser();
+            assert_eq!(
+                element.get(parser).unwrap().inner_html(parser),
+                expected.children()[0]
+                    .get(expected_parser)
+                    .unwrap()
+                    .inner_html(expected_parser),
+                "{document}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_context() {
+        let mut dom = parse("text", ParserOptions::default()).unwrap();
+        let text = dom.children()[0];
+
+        assert_eq!(
+            dom.parse_fragment(text, "<p></p>"),
+            Err(ParseError::InvalidContext)
+        );
+    }
+}
+
+mod import {
+    use super::*;
+
+    #[test]
+    fn import_subtree() {
+        let mut dom = parse(
+            r#"<div id="target"></div><p id="taken">existing</p>"#,
+            ParserOptions::default().track_ids().track_classes(),
+        )
+        .unwrap();
+
+        let handle = {
+            let input = String::from(
+                r#"<section id="s" class="x y"><p id="taken" class="x">a<!-- c --></p><img id="i"></section>"#,
+            );
+            let other = parse(&input, ParserOptions::default()).unwrap();
+            let section = other.children()[0];
+            dom.import_subtree(&other, section).unwrap()
+            // `input` and `other` are dropped here, the imported nodes must not borrow from them
+        };
+
+        let target = dom.get_element_by_id("target").unwrap();
+        target
+            .get_mut(dom.parser_mut())
+            .unwrap()
+            .as_tag_mut()
+            .unwrap()
+            .children_mut()
+            .top_mut()
+            .push(handle);
+
+        assert_eq!(
+            dom.outer_html(),
+            r#"<div id="target"><section id="s" class="x y"><p id="taken" class="x">a<!-- c --></p><img id="i"></section></div><p id="taken">existing</p>"#
+        );
+
+        assert_eq!(dom.get_element_by_id("s"), Some(handle));
+        assert!(dom.get_element_by_id("i").is_some());
+
+        // existing ids are not overwritten
+        let taken = dom.get_element_by_id("taken").unwrap();
+        assert_eq!(
+            taken.get(dom.parser()).unwrap().inner_text(dom.parser()),
+            "existing"
+        );
+
+        assert_eq!(dom.get_elements_by_class_name("x").count(), 2);
+        assert_eq!(
+            dom.get_elements_by_class_name("y").collect::<Vec<_>>(),
+            vec![handle]
+        );
+
+        // copied nodes are laid out in document order
+        let inner = handle
+            .get(dom.parser())
+            .unwrap()
+            .as_tag()
+            .unwrap()
+            .children()
+            .top()
+            .to_vec();
+        assert_eq!(inner[0].get_inner(), handle.get_inner() + 1);
+        assert_eq!(inner[1].get_inner(), handle.get_inner() + 4);
+    }
+
+    #[test]
+    fn import_invalid_handle() {
+        let mut dom = parse("<p></p>", ParserOptions::default()).unwrap();
+        let other = parse("<p></p>", ParserOptions::default()).unwrap();
+
+        assert_eq!(dom.import_subtree(&other, NodeHandle::new(42)), None);
+    }
+}
+
+mod compact {
+    use super::*;
+    use crate::VDom;
+
+    fn detach_child(dom: &mut VDom<'_>, parent: NodeHandle, index: usize) -> NodeHandle {
+        parent
+            .get_mut(dom.parser_mut())
+            .unwrap()
+            .as_tag_mut()
+            .unwrap()
+            .children_mut()
+            .top_mut()
+            .remove(index)
+    }
+
+    #[test]
+    fn removes_unreachable_nodes() {
+        let mut dom = parse(
+            r#"<div id="root"><p id="gone" class="x">a<b class="x y">b</b></p><p id="kept" class="x">c</p></div>"#,
+            ParserOptions::default().track_ids().track_classes(),
+        )
+        .unwrap();
+        let root = dom.get_element_by_id("root").unwrap();
+        let kept = dom.get_element_by_id("kept").unwrap();
+
+        let gone = detach_child(&mut dom, root, 0);
+        let remap = dom.compact();
+
+        assert_eq!(dom.nodes().len(), 3);
+        assert_eq!(remap.len(), 7);
+        assert_eq!(remap[gone.get_inner() as usize], None);
+        assert_eq!(remap[root.get_inner() as usize], Some(NodeHandle::new(0)));
+
+        let kept = remap[kept.get_inner() as usize].unwrap();
+        assert_eq!(kept, NodeHandle::new(1));
+        assert_eq!(dom.get_element_by_id("kept"), Some(kept));
+        assert_eq!(dom.get_element_by_id("gone"), None);
+        assert_eq!(
+            dom.get_elements_by_class_name("x").collect::<Vec<_>>(),
+            vec![kept]
+        );
+        assert_eq!(dom.get_elements_by_class_name("y").count(), 0);
+        assert_eq!(dom.query_selector("b").unwrap().count(), 0);
+        assert_eq!(
+            dom.outer_html(),
+            r#"<div id="root"><p id="kept" class="x">c</p></div>"#
+        );
+    }
+
+    #[test]
+    fn restores_document_order() {
+        let mut dom = parse(
+            r#"<ul id="list"><li>old</li></ul><span>after</span>"#,
+            ParserOptions::default().track_ids(),
+        )
+        .unwrap();
+        let list = dom.get_element_by_id("list").unwrap();
+        dom.parse_fragment(list, "<li>1</li><li>2</li>").unwrap();
+
+        dom.compact();
+
+        let nodes = dom.nodes();
+        let names = nodes
+            .iter()
+            .map(|n| match n {
+                Node::Tag(t) => t.name().as_utf8_str().into_owned(),
+                _ => n.inner_text(dom.parser()).into_owned(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["ul", "li", "1", "li", "2", "span", "after"]);
+
+        let list = dom.get_element_by_id("list").unwrap();
+        let children = list.get(dom.parser()).unwrap().children().unwrap();
+        assert_eq!(children.boundaries(dom.parser()), Some((1, 4)));
+        assert_eq!(children.all(dom.parser()).len(), 4);
+    }
+}
+
+mod owned {
+    use super::*;
+    use crate::{OwnedVDom, VDom};
+    use std::sync::Arc;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<OwnedVDom>();
+    }
+
+    #[test]
+    fn outlives_input() {
+        let dom = {
+            let input = String::from(r#"<p id="test" class="a">hello</p>"#);
+            OwnedVDom::parse(input, ParserOptions::default().track_ids()).unwrap()
+        };
+
+        let dom = std::thread::spawn(move || dom).join().unwrap();
+
+        let vdom = dom.get_ref();
+        let p = vdom.get_element_by_id("test").unwrap();
+        assert_eq!(
+            p.get(vdom.parser()).unwrap().inner_text(vdom.parser()),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn shared_source() {
+        let source: Arc<str> = Arc::from("<p>shared</p>");
+        let dom = OwnedVDom::parse(Arc::clone(&source), ParserOptions::default()).unwrap();
+
+        assert!(Arc::ptr_eq(dom.source(), &source));
+        drop(source);
+
+        let dom = Arc::new(dom);
+        let handles = (0..2)
+            .map(|_| {
+                let dom = Arc::clone(&dom);
+                std::thread::spawn(move || dom.get_ref().outer_html())
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), "<p>shared</p>");
+        }
+    }
+
+    #[test]
+    fn mutation() {
+        let mut dom = OwnedVDom::parse("<div><p>a</p></div>", ParserOptions::default()).unwrap();
+        let other = parse("<span>b</span>", ParserOptions::default()).unwrap();
+
+        let len = dom.with_mut(|dom: &mut VDom<'_>| {
+            let div = dom.children()[0];
+            let span = dom.import_subtree(&other, other.children()[0]).unwrap();
+
+            let tag = div.get_mut(dom.parser_mut()).unwrap().as_tag_mut().unwrap();
+            tag.children_mut().top_mut().push(span);
+            tag.attributes_mut().insert("class", Some("x"));
+
+            let text = dom.nodes_mut()[2].as_raw_mut().unwrap();
+            text.set("c").unwrap();
+
+            dom.nodes().len()
+        });
+        drop(other);
+
+        assert_eq!(len, 5);
+        assert_eq!(
+            dom.get_ref().outer_html(),
+            r#"<div class="x"><p>c</p><span>b</span></div>"#
+        );
+    }
+}
+
+mod charset {
+    use super::*;
+    use crate::{parse_bytes, parse_bytes_with_hint, OwnedVDom};
+
+    fn text(dom: &OwnedVDom) -> String {
+        let dom = dom.get_ref();
+        dom.query_selector("p")
+            .unwrap()
+            .next()
+            .unwrap()
+            .get(dom.parser())
+            .unwrap()
+            .inner_text(dom.parser())
+            .into_owned()
+    }
+
+    fn detect(input: &[u8]) -> (Option<&'static str>, String) {
+        let dom = parse_bytes(input, ParserOptions::default()).unwrap();
+        (dom.get_ref().encoding(), text(&dom))
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        assert_eq!(
+            detect(b"\xEF\xBB\xBF<meta charset=latin1><p>\xC3\xA9</p>"),
+            (Some("UTF-8"), String::from("é"))
+        );
+
+        let utf16 = "<p>é</p>"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            detect(&[&[0xFF, 0xFE], utf16.as_slice()].concat()),
+            (Some("UTF-16LE"), String::from("é"))
+        );
+    }
+
+    #[test]
+    fn meta_prescan() {
+        assert_eq!(
+            detect(b"<meta charset=\"ISO-8859-1\"><p>\xE9\x80</p>"),
+            (Some("windows-1252"), String::from("é€"))
+        );
+        assert_eq!(
+            detect(b"<META HTTP-EQUIV='Content-Type' CONTENT='text/html; charset=windows-1252'><p>\xE9</p>"),
+            (Some("windows-1252"), String::from("é"))
+        );
+        assert_eq!(
+            detect(b"<meta content=\"text/html; charset = 'latin1'\" http-equiv=content-type><p>\xE9</p>"),
+            (Some("windows-1252"), String::from("é"))
+        );
+        // `content` without `http-equiv` is ignored
+        assert_eq!(
+            detect(b"<meta content=\"text/html; charset=latin1\"><p>\xC3\xA9</p>").0,
+            Some("UTF-8")
+        );
+        // declarations in comments and attribute values are ignored
+        assert_eq!(
+            detect(
+                b"<!-- <meta charset=latin1> --><div title='<meta charset=latin1>'><p>\xC3\xA9</p>"
+            )
+            .0,
+            Some("UTF-8")
+        );
+        // a UTF-16 declaration means UTF-8
+        assert_eq!(
+            detect(b"<meta charset=utf-16><p>\xC3\xA9</p>"),
+            (Some("UTF-8"), String::from("é"))
+        );
+        // only the first 1024 bytes are scanned
+        let late = [&[b' '; 1024][..], b"<meta charset=latin1><p>\xC3\xA9</p>"].concat();
+        assert_eq!(detect(&late).0, Some("UTF-8"));
+    }
+
+    #[test]
+    fn fallback() {
+        assert_eq!(
+            detect(b"<p>\xC3\xA9</p>"),
+            (Some("UTF-8"), String::from("é"))
+        );
+        assert_eq!(
+            detect(b"<p>\xE9</p>"),
+            (Some("windows-1252"), String::from("é"))
+        );
+    }
+
+    #[test]
+    fn transport_hint() {
+        let input = b"<meta charset=utf-8><p>\xE9</p>";
+
+        let dom = parse_bytes_with_hint(input, Some(" Latin1 "), ParserOptions::default()).unwrap();
+        assert_eq!(dom.get_ref().encoding(), Some("windows-1252"));
+        assert_eq!(text(&dom), "é");
+
+        // unknown labels are ignored
+        let dom = parse_bytes_with_hint(input, Some("bogus"), ParserOptions::default()).unwrap();
+        assert_eq!(dom.get_ref().encoding(), Some("UTF-8"));
+        assert_eq!(text(&dom), "\u{FFFD}");
+
+        // the byte order mark takes precedence
+        let input = b"\xEF\xBB\xBF<p>\xC3\xA9</p>";
+        let dom = parse_bytes_with_hint(input, Some("latin1"), ParserOptions::default()).unwrap();
+        assert_eq!(dom.get_ref().encoding(), Some("UTF-8"));
+    }
+
+    #[test]
+    fn parsed_from_str() {
+        let dom = parse("<p></p>", ParserOptions::default()).unwrap();
+        assert_eq!(dom.encoding(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn transcoding() {
+        assert_eq!(
+            detect(b"<meta charset=shift_jis><p>\x93\xfa\x96\x7b</p>"),
+            (Some("Shift_JIS"), String::from("日本"))
+        );
+    }
+}
+
+#[test]
+fn span_width() {
+    #[cfg(not(feature = "span64"))]
+    assert_eq!(crate::InnerNodeHandle::MAX, u32::MAX);
+    #[cfg(feature = "span64")]
+    assert_eq!(crate::InnerNodeHandle::MAX, u64::MAX);
+}
+
+mod limits {
+    use super::*;
+    use crate::errors::ParseError;
+
+    fn nested(depth: usize, inner: &str) -> String {
+        format!(
+            "{}{}{}",
+            "<div>".repeat(depth),
+            inner,
+            "</div>".repeat(depth)
+        )
+    }
+
+    #[test]
+    fn deep_nesting_without_limits() {
+        let depth = if cfg!(miri) { 100usize } else { 100_000usize };
+        let input = nested(depth, "<b>x</b>y");
+        let dom = parse(&input, ParserOptions::default()).unwrap();
+        let parser = dom.parser();
+        let root = dom.children()[0].get(parser).unwrap();
+
+        assert_eq!(dom.outer_html(), input);
+        assert_eq!(root.inner_text(parser), "xy");
+        assert_eq!(root.inner_html(parser).len(), input.len() - 11);
+        assert!(root
+            .find_node(parser, &mut |n| n.as_tag().is_some_and(|t| t.name() == "b"))
+            .is_some());
+        assert_eq!(dom.query_selector("b").unwrap().count(), 1);
+
+        let children = root.children().unwrap();
+        assert_eq!(children.all(parser).len(), dom.nodes().len() - 1);
+    }
+
+    #[test]
+    fn depth_error() {
+        let options = ParserOptions::default().max_depth(3, LimitPolicy::Error);
+
+        assert!(parse(&nested(3, "<br>x"), options).is_ok());
+        assert_eq!(
+            parse(&nested(4, "x"), options).unwrap_err(),
+            ParseError::DepthLimitExceeded
+        );
+    }
+
+    #[test]
+    fn depth_truncate() {
+        let options = ParserOptions::default().max_depth(2, LimitPolicy::Truncate);
+        let dom = parse("<a><b><c><d>x<br></d>y</c>z</b>w</a><e></e>", options).unwrap();
+
+        assert_eq!(dom.outer_html(), "<a><b>z</b>w</a><e></e>");
+        assert_eq!(dom.nodes().len(), 5);
+    }
+
+    #[test]
+    fn depth_truncate_mismatched_end_tags() {
+        let options = ParserOptions::default().max_depth(2, LimitPolicy::Truncate);
+        let dom = parse("<a><b><c></x>hidden</c>y</b>z</a>", options).unwrap();
+
+        assert_eq!(dom.outer_html(), "<a><b>y</b>z</a>");
+
+        let options = ParserOptions::default().max_depth(2, LimitPolicy::Flatten);
+        let dom = parse("<a><b><c></x>x</c>y</b>z</a>", options).unwrap();
+
+        assert_eq!(dom.outer_html(), "<a><b><c></c>xy</b>z</a>");
+    }
+
+    #[test]
+    fn depth_flatten() {
+        let options = ParserOptions::default().max_depth(2, LimitPolicy::Flatten);
+        let dom = parse("<a><b><c><d>x</d>y</c>z</b>w</a><e></e>", options).unwrap();
+
+        assert_eq!(dom.outer_html(), "<a><b><c></c><d></d>xyz</b>w</a><e></e>");
+    }
+
+    #[test]
+    fn node_limit() {
+        let input = "<p>a</p><p>b</p>";
+
+        let options = ParserOptions::default().max_nodes(3, LimitPolicy::Truncate);
+        assert_eq!(
+            parse(input, options).unwrap().outer_html(),
+            "<p>a</p><p></p>"
+        );
+
+        let options = ParserOptions::default().max_nodes(4, LimitPolicy::Error);
+        assert!(parse(input, options).is_ok());
+
+        let options = ParserOptions::default().max_nodes(3, LimitPolicy::Error);
+        assert_eq!(
+            parse(input, options).unwrap_err(),
+            ParseError::NodeLimitExceeded
+        );
+    }
+
+    #[test]
+    fn attribute_limit() {
+        let input = r#"<p id="x" title="t" lang="en">a</p>"#;
+
+        let options = ParserOptions::default().max_attributes(2, LimitPolicy::Truncate);
+        let dom = parse(input, options).unwrap();
+        let attributes = dom.nodes()[0].as_tag().unwrap().attributes();
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains("id"));
+        assert!(attributes.contains("title"));
+        assert!(!attributes.contains("lang"));
+        assert_eq!(dom.nodes()[1].as_raw().unwrap(), "a");
+
+        let options = ParserOptions::default().max_attributes(2, LimitPolicy::Error);
+        assert_eq!(
+            parse(input, options).unwrap_err(),
+            ParseError::AttributeLimitExceeded
+        );
+    }
+
+    #[test]
+    fn attribute_length_limit() {
+        let input = r#"<p title="héllo" lang="en">a</p>"#;
+
+        let options = ParserOptions::default().max_attribute_length(2, LimitPolicy::Truncate);
+        let dom = parse(input, options).unwrap();
+        let attributes = dom.nodes()[0].as_tag().unwrap().attributes();
+        assert_eq!(attributes.get("title"), Some(Some(&"h".into())));
+        assert_eq!(attributes.get("lang"), Some(Some(&"en".into())));
+
+        let options = ParserOptions::default().max_attribute_length(2, LimitPolicy::Error);
+        assert_eq!(
+            parse(input, options).unwrap_err(),
+            ParseError::AttributeLengthExceeded
+        );
+    }
+}
+
+mod chunked {
+    use super::*;
+
+    const INPUT: &str = r#"<!DOCTYPE html><html><head><!-- a -- comment --></head><body>
+<h1 class="title main">Links for tl</h1>
+<a href="/tl-0.1.tar.gz#sha256=abc" data-requires-python="&gt;=3.7">tl-0.1.tar.gz</a><br />
+<a href='/tl-0.2.tar.gz' data-yanked>tl-0.2.tar.gz</a><br >
+<p id=x>unclosed <b>bold</b>
+</body></html>"#;
+
+    /// Returns the name and inner text of every element passed to the callback
+    fn completed(chunks: &[&[u8]]) -> (Vec<(String, String)>, String) {
+        let mut elements = Vec::new();
+
+        let mut parser = ChunkedParser::new(ParserOptions::default())
+            .encoding_hint("utf-8")
+            .on_element(|tag, parser| {
+                elements.push((
+                    tag.name().as_utf8_str().into_owned(),
+                    tag.inner_text(parser).into_owned(),
+                ));
+            });
+
+        for chunk in chunks {
+            parser.feed(chunk);
+        }
+
+        let dom = parser.finish().unwrap();
+        let html = dom.get_ref().outer_html();
+
+        (elements, html)
+    }
+
+    #[test]
+    fn matches_parse() {
+        let (expected, html) = completed(&[INPUT.as_bytes()]);
+        assert_eq!(
+            html,
+            parse(INPUT, ParserOptions::default()).unwrap().outer_html()
+        );
+        assert_eq!(expected.len(), 7);
+        assert_eq!(expected[0].0, "head");
+        assert_eq!(expected[1], ("h1".into(), "Links for tl".into()));
+        assert_eq!(expected[2], ("a".into(), "tl-0.1.tar.gz".into()));
+        assert_eq!(expected[3].0, "br");
+        assert_eq!(expected[6], ("b".into(), "bold".into()));
+
+        let input = INPUT.as_bytes();
+
+        for split in (0..input.len()).step_by(if cfg!(miri) { 37 } else { 1 }) {
+            let (elements, chunked_html) = completed(&[&input[..split], &input[split..]]);
+            assert_eq!(elements, expected, "split at {}", split);
+            assert_eq!(chunked_html, html, "split at {}", split);
+        }
+
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(completed(&bytes), (expected, html));
+    }
+
+    #[test]
+    fn elements_before_finish() {
+        let mut count = 0;
+        let mut parser = ChunkedParser::new(ParserOptions::default())
+            .encoding_hint("utf-8")
+            .on_element(|tag, _| {
+                assert_eq!(tag.attributes().get("href").flatten().unwrap(), "/a");
+                count += 1;
+            });
+
+        parser.feed(b"<a href=\"/a\">a</a> <a hr");
+        parser.feed(b"ef=\"/a\">a</a");
+        parser.feed(b">");
+        drop(parser);
+
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn write() {
+        let mut parser = ChunkedParser::new(ParserOptions::default());
+        std::io::copy(&mut INPUT.as_bytes(), &mut parser).unwrap();
+
+        let dom = parser.finish().unwrap();
+        assert_eq!(dom.source().as_ref(), INPUT);
+        assert_eq!(dom.get_ref().query_selector("a").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn same_document_as_parse_bytes() {
+        // the encoding is determined in the middle of the input, after the first 1024 bytes
+        let mut input = b"<meta charset=\"iso-8859-1\"><!--".to_vec();
+        input.extend_from_slice(&[b'-'; 1000]);
+        input.extend_from_slice(
+            b"--><p id=a>caf\xe9 <b>x</b></p><script>if (a </b> b) {}</script><br><i>unclosed",
+        );
+        let input = input.as_slice();
+        let options = ParserOptions::default().track_ids();
+        let expected = crate::parse_bytes(input, options).unwrap();
+        let expected = expected.get_ref();
+
+        for split in (0..input.len()).step_by(if cfg!(miri) { 37 } else { 1 }) {
+            let mut raws = Vec::new();
+            let mut parser = ChunkedParser::new(options).on_element(|tag, _| {
+                raws.push(tag.raw().as_utf8_str().into_owned());
+            });
+
+            parser.feed(&input[..split]);
+            parser.feed(&input[split..]);
+
+            let dom = parser.finish().unwrap();
+            let dom = dom.get_ref();
+            assert_eq!(dom.encoding(), Some("windows-1252"), "split at {}", split);
+            assert_eq!(
+                dom.outer_html(),
+                expected.outer_html(),
+                "split at {}",
+                split
+            );
+            assert_eq!(
+                dom.nodes().len(),
+                expected.nodes().len(),
+                "split at {}",
+                split
+            );
+            assert_eq!(dom.get_element_by_id("a"), expected.get_element_by_id("a"));
+
+            // the elements passed to the callback are the elements of the document
+            let tags: Vec<_> = dom.nodes().iter().filter_map(Node::as_tag).collect();
+            let expected_tags = expected.nodes().iter().filter_map(Node::as_tag);
+            for (tag, expected_tag) in tags.iter().zip(expected_tags) {
+                assert_eq!(tag.raw(), expected_tag.raw(), "split at {}", split);
+                assert_eq!(
+                    tag.boundaries(dom.parser()),
+                    expected_tag.boundaries(expected.parser())
+                );
+            }
+
+            assert_eq!(raws.len(), 5, "split at {}", split);
+            assert!(raws.contains(&"<p id=a>caf\u{e9} <b>x</b></p>".to_string()));
+        }
+    }
+
+    #[test]
+    fn encoding_from_start() {
+        let mut parser = ChunkedParser::new(ParserOptions::default());
+        parser.feed(b"<p>a</p>");
+        assert_eq!(parser.input(), "");
+        assert_eq!(parser.finish().unwrap().get_ref().encoding(), Some("UTF-8"));
+
+        let mut parser = ChunkedParser::new(ParserOptions::default()).encoding_hint("latin1");
+        parser.feed(b"<p>\xe9");
+        assert_eq!(parser.input(), "<p>\u{e9}");
+
+        let mut parser = ChunkedParser::new(ParserOptions::default());
+        parser.feed(b"\xef\xbb");
+        assert_eq!(parser.input(), "");
+        parser.feed(b"\xbf<p>\xe2\x82");
+        assert_eq!(parser.input(), "<p>");
+        parser.feed(b"\xac</p>");
+        assert_eq!(parser.input(), "<p>\u{20ac}</p>");
+        assert_eq!(parser.finish().unwrap().get_ref().encoding(), Some("UTF-8"));
+
+        // without a declaration, the first 1024 bytes decide between UTF-8 and windows-1252
+        let mut input = "<p>caf\u{e9}</p>".repeat(100).into_bytes();
+        input.extend_from_slice(b"<p>\xe9</p>");
+        let dom = crate::parse_reader(input.as_slice(), ParserOptions::default()).unwrap();
+        let dom = dom.get_ref();
+        assert_eq!(dom.encoding(), Some("UTF-8"));
+        assert!(dom.outer_html().ends_with("<p>\u{fffd}</p>"));
+    }
+
+    #[test]
+    fn parse_reader() {
+        let dom = crate::parse_reader(INPUT.as_bytes(), ParserOptions::default()).unwrap();
+        assert_eq!(dom.source().as_ref(), INPUT);
+        assert_eq!(
+            dom.get_ref().outer_html(),
+            parse(INPUT, ParserOptions::default()).unwrap().outer_html()
+        );
+
+        let options = ParserOptions::default().max_nodes(2, LimitPolicy::Error);
+        let error = crate::parse_reader(INPUT.as_bytes(), options).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.get_ref().unwrap().downcast_ref(),
+            Some(&crate::ParseError::NodeLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn node_limit() {
+        let options = ParserOptions::default().max_nodes(5, LimitPolicy::Truncate);
+        let mut parser = ChunkedParser::new(options);
+        for chunk in INPUT.as_bytes().chunks(7) {
+            parser.feed(chunk);
+        }
+
+        assert_eq!(
+            parser.finish().unwrap().get_ref().outer_html(),
+            parse(INPUT, options).unwrap().outer_html()
+        );
+    }
+
+    #[test]
+    fn long_text_byte_by_byte() {
+        // text that is read again for every byte would take quadratic time
+        let count = if cfg!(miri) { 20 } else { 20_000 };
+        let input = format!(
+            "<p>{}</p><script>{}</scrip</script><textarea>{}</textarea>",
+            "some text ".repeat(count),
+            "if (a < b) {}\n".repeat(count),
+            "x</textare".repeat(count),
+        );
+
+        let mut parser = ChunkedParser::new(ParserOptions::default()).encoding_hint("utf-8");
+        for byte in input.as_bytes() {
+            parser.feed(std::slice::from_ref(byte));
+        }
+
+        let dom = parser.finish().unwrap();
+        let dom = dom.get_ref();
+        assert_eq!(dom.children().len(), 3);
+        assert_eq!(
+            dom.outer_html(),
+            parse(&input, ParserOptions::default())
+                .unwrap()
+                .outer_html()
+        );
+    }
+
+    #[test]
+    fn limit_error() {
+        let options = ParserOptions::default().max_attributes(1, LimitPolicy::Error);
+        let mut parser = ChunkedParser::new(options).on_element(|_, _| {});
+        parser.feed(br#"<p a="1" b="2">text</p>"#);
+        assert_eq!(
+            parser.finish().unwrap_err(),
+            crate::ParseError::AttributeLimitExceeded
+        );
+    }
+}
+
+mod tokenizer {
+    use super::*;
+
+    #[test]
+    fn raw_text_elements() {
+        let input = "<SCRIPT>a</b> </scripts> <p></Script ><style></style><plaintext></plaintext>";
+        let tokens: Vec<Token<'_>> = Tokenizer::new(input, ParserOptions::default()).collect();
+        assert_eq!(tokens.len(), 7);
+
+        assert!(matches!(&tokens[1], Token::Text { text, .. } if text == "a</b> </scripts> <p>"));
+        assert!(matches!(&tokens[2], Token::EndTag { .. }));
+        assert!(matches!(&tokens[3], Token::StartTag { name, .. } if name == "style"));
+        assert!(matches!(&tokens[4], Token::EndTag { name, .. } if name == "style"));
+        assert!(matches!(&tokens[6], Token::Text { text, .. } if text == "</plaintext>"));
+    }
+
+    #[test]
+    fn all_tokens() {
+        let input = r#"<!DOCTYPE html><!-- c --><p class="a b" hidden>x &amp; y</p><br /></div>"#;
+        let tokens: Vec<Token<'_>> = Tokenizer::new(input, ParserOptions::default()).collect();
+        assert_eq!(tokens.len(), 7);
+
+        for token in &tokens {
+            let span = token.span();
+            assert!(span.start < span.end && span.end <= input.len());
+        }
+
+        assert!(
+            matches!(&tokens[0], Token::Doctype { name, span } if name == "html" && span == &(0..15))
+        );
+        assert!(matches!(&tokens[1], Token::Comment { comment, .. } if comment == "<!-- c -->"));
+
+        match &tokens[2] {
+            Token::StartTag {
+                name,
+                attributes,
+                self_closing,
+                span,
+            } => {
+                assert_eq!(name, "p");
+                assert_eq!(attributes.class(), Some(&"a b".into()));
+                assert_eq!(attributes.get("hidden"), Some(None));
+                assert!(!self_closing);
+                assert_eq!(&input[span.clone()], r#"<p class="a b" hidden>"#);
+            }
+            token => panic!("expected start tag, got {:?}", token),
+        }
+
+        assert!(matches!(&tokens[3], Token::Text { text, .. } if text == "x &amp; y"));
+        assert!(matches!(&tokens[4], Token::EndTag { name, .. } if name == "p"));
+        assert!(
+            matches!(&tokens[5], Token::StartTag { name, self_closing: true, .. } if name == "br")
+        );
+        // end tags without a matching start tag are still tokens
+        assert!(
+            matches!(&tokens[6], Token::EndTag { name, span } if name == "div" && span.end == input.len())
+        );
+    }
+
+    #[test]
+    fn matches_parser() {
+        let input = "<div><p>a</p><img src=x><p>b</p><!-- c --></div>";
+        let dom = parse(input, ParserOptions::default()).unwrap();
+
+        let nodes = Tokenizer::new(input, ParserOptions::default())
+            .filter(|token| !matches!(token, Token::EndTag { .. }))
+            .count();
+        assert_eq!(nodes, dom.nodes().len());
+    }
+
+    #[test]
+    fn limit_error() {
+        let input = r#"<a x="1" y="2">a</a><b>b</b>"#;
+        let options = ParserOptions::default().max_attributes(1, LimitPolicy::Error);
+
+        let mut tokenizer = Tokenizer::new(input, options);
+        assert!(tokenizer.next().is_none());
+        assert_eq!(
+            tokenizer.error(),
+            Some(&crate::ParseError::AttributeLimitExceeded)
+        );
+    }
+}
+
+mod visitor {
+    use super::*;
+    use crate::{visit, ParseError};
+
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl<'a> Visitor<'a> for Events {
+        fn enter_element(&mut self, tag: &HTMLTag<'a>, depth: usize) {
+            self.0
+                .push(format!("{}<{}", depth, tag.name().as_utf8_str()));
+        }
+
+        fn exit_element(&mut self, tag: &HTMLTag<'a>, depth: usize) {
+            self.0
+                .push(format!("{}>{}", depth, tag.raw().as_utf8_str()));
+        }
+
+        fn text(&mut self, text: &Bytes<'a>, depth: usize) {
+            self.0.push(format!("{}:{}", depth, text.as_utf8_str()));
+        }
+
+        fn comment(&mut self, comment: &Bytes<'a>, depth: usize) {
+            self.0.push(format!("{}!{}", depth, comment.as_utf8_str()));
+        }
+    }
+
+    fn events(input: &str, options: ParserOptions) -> Result<Vec<String>, ParseError> {
+        let mut events = Events::default();
+        visit(input, options, &mut events)?;
+        Ok(events.0)
+    }
+
+    #[test]
+    fn order_and_depth() {
+        let input = "<!DOCTYPE html><div>a<br><p>b<!-- c --></p></span></div><p>open";
+
+        assert_eq!(
+            events(input, ParserOptions::default()).unwrap(),
+            [
+                "0<div",
+                "1:a",
+                "1<br",
+                "1><br>",
+                "1<p",
+                "2:b",
+                "2!<!-- c -->",
+                "1><p>b<!-- c --></p>",
+                "0><div>a<br><p>b<!-- c --></p></span></div>",
+                "0<p",
+                "1:open",
+                "0><p>",
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_parse() {
+        let input = r#"<ul class="links"><li><a href="/a">a</a></li><li><a href="/b">b<img src=x /></a></li></ul>"#;
+        let dom = parse(input, ParserOptions::default()).unwrap();
+
+        let events = events(input, ParserOptions::default()).unwrap();
+        let entered = events
+            .iter()
+            .filter(|e| e.contains('<') && !e.contains('>'))
+            .count();
+        let texts = events.iter().filter(|e| e.contains(':')).count();
+
+        let tags = dom.nodes().iter().filter(|n| n.as_tag().is_some()).count();
+        let raws = dom.nodes().iter().filter(|n| n.as_raw().is_some()).count();
+        assert_eq!((entered, texts), (tags, raws));
+    }
+
+    #[test]
+    fn limits() {
+        let input = "<div><div><div>deep</div></div>x</div>";
+
+        let options = ParserOptions::default().max_depth(1, LimitPolicy::Truncate);
+        assert_eq!(
+            events(input, options).unwrap(),
+            ["0<div", "1:x", "0><div><div><div>deep</div></div>x</div>"]
+        );
+
+        let options = ParserOptions::default().max_depth(1, LimitPolicy::Flatten);
+        assert_eq!(
+            events(input, options).unwrap(),
+            [
+                "0<div",
+                "1<div",
+                "1><div>",
+                "1<div",
+                "1><div>",
+                "1:deep",
+                "1:x",
+                "0><div><div><div>deep</div></div>x</div>"
+            ]
+        );
+
+        let options = ParserOptions::default().max_depth(1, LimitPolicy::Error);
+        assert_eq!(events(input, options), Err(ParseError::DepthLimitExceeded));
+
+        // an end tag that does not match the innermost element leaves it open, like in the parser
+        let options = ParserOptions::default().max_depth(1, LimitPolicy::Truncate);
+        assert_eq!(
+            events("<div><div></x>hidden</div>x</div>", options).unwrap(),
+            ["0<div", "1:x", "0><div><div></x>hidden</div>x</div>"]
+        );
+
+        let options = ParserOptions::default().max_nodes(2, LimitPolicy::Truncate);
+        assert_eq!(
+            events(input, options).unwrap(),
+            ["0<div", "1<div", "1><div>", "0><div>"]
+        );
+    }
+}
+
+mod traverse {
+    use super::*;
+
+    const INPUT: &str = "<div id=a><p>one<b>two</b></p><!-- c --><ul><li>three</li></ul></div>four";
+
+    #[test]
+    fn descendants_in_document_order() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+
+        let handles: Vec<_> = dom
+            .descendants()
+            .map(|(handle, _)| handle.get_inner() as usize)
+            .collect();
+        assert_eq!(handles, (0..dom.nodes().len()).collect::<Vec<_>>());
+
+        let depths: Vec<_> = dom.descendants().map(|(_, depth)| depth).collect();
+        assert_eq!(depths, [0, 1, 2, 2, 3, 1, 1, 2, 3, 0]);
+
+        let div = dom.children()[0];
+        assert_eq!(div.descendants(dom.parser()).count(), 8);
+        assert_eq!(div.descendants(dom.parser()).next().unwrap().1, 1);
+
+        let text = dom.children()[1];
+        assert_eq!(text.descendants(dom.parser()).count(), 0);
+        assert_eq!(text.traverse(dom.parser()).count(), 2);
+    }
+
+    #[test]
+    fn filters() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let parser = dom.parser();
+
+        let names: Vec<_> = dom
+            .elements()
+            .map(|(handle, depth)| {
+                (
+                    handle
+                        .get(parser)
+                        .unwrap()
+                        .as_tag()
+                        .unwrap()
+                        .name()
+                        .as_utf8_str(),
+                    depth,
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("div".into(), 0),
+                ("p".into(), 1),
+                ("b".into(), 2),
+                ("ul".into(), 1),
+                ("li".into(), 2)
+            ]
+        );
+
+        let text: Vec<_> = dom
+            .text_nodes()
+            .map(|(handle, _)| handle.get(parser).unwrap().inner_text(parser))
+            .collect();
+        assert_eq!(text, ["one", "two", "three", "four"]);
+
+        let div = dom.children()[0];
+        assert_eq!(div.elements(parser).count(), 4);
+        assert_eq!(div.text_nodes(parser).count(), 3);
+    }
+
+    #[test]
+    fn edges_are_balanced() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+
+        let mut open = Vec::new();
+        for (edge, depth) in dom.traverse() {
+            match edge {
+                Edge::Open(handle) => {
+                    assert_eq!(open.len(), depth);
+                    open.push(handle);
+                }
+                Edge::Close(handle) => {
+                    assert_eq!(open.pop(), Some(handle));
+                    assert_eq!(open.len(), depth);
+                }
+            }
+        }
+
+        assert!(open.is_empty());
+        assert_eq!(dom.traverse().count(), 2 * dom.nodes().len());
+    }
+
+    #[test]
+    fn skip_subtree() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let parser = dom.parser();
+
+        let mut edges = dom.traverse();
+        let mut seen = Vec::new();
+
+        while let Some((edge, _)) = edges.next() {
+            let tag = edge.handle().get(parser).unwrap().as_tag();
+
+            if let (Edge::Open(_), Some(tag)) = (edge, tag) {
+                if tag.name() == "p" {
+                    edges.skip_subtree();
+                }
+            }
+
+            seen.push(edge);
+        }
+
+        // the 3 nodes inside of <p> are skipped
+        assert_eq!(seen.len(), 2 * (dom.nodes().len() - 3));
+
+        let mut descendants = dom.descendants();
+        let mut count = 0;
+
+        while let Some((handle, _)) = descendants.next() {
+            count += 1;
+            if handle == dom.children()[0] {
+                descendants.skip_subtree();
+            }
+        }
+
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn deep() {
+        let depth = if cfg!(miri) { 100 } else { 100_000 };
+        let input = format!("{}x{}", "<div>".repeat(depth), "</div>".repeat(depth));
+        let dom = parse(&input, ParserOptions::default()).unwrap();
+
+        assert_eq!(dom.descendants().last().unwrap().1, depth);
+        assert_eq!(dom.traverse().count(), 2 * (depth + 1));
+        assert_eq!(dom.elements().count(), depth);
+        assert_eq!(dom.text_nodes().count(), 1);
+    }
+}
+
+mod xpath {
+    use super::*;
+    use crate::errors::XPathError;
+    use crate::xpath::{Item, Value, XPath};
+
+    const INPUT: &str = r#"<html lang="en-GB"><body>
+<table id="files">
+<tr><th>File</th><th>Kind</th></tr>
+<tr><td><a href="/a-1.0-py3-none-any.whl">a-1.0</a></td><td>whl</td></tr>
+<tr><td><a href="/a-1.0.tar.gz">a-1.0</a></td><td>sdist</td></tr>
+<tr><td><a href="/a-2.0-py3-none-any.whl" data-yanked>a-2.0</a></td><td>whl</td></tr>
+</table>
+<ul><li>1</li><li>2</li><li>3</li><!-- end --></ul>
+<p>Fish &amp; Chips</p>
+</body></html>"#;
+
+    fn eval(expression: &str) -> Value {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        XPath::compile(expression).unwrap().evaluate(&dom).unwrap()
+    }
+
+    fn strings(expression: &str) -> Vec<String> {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        XPath::compile(expression)
+            .unwrap()
+            .evaluate(&dom)
+            .unwrap()
+            .strings(&dom)
+    }
+
+    #[test]
+    fn request_example() {
+        assert_eq!(
+            strings("//table[@id='files']//tr[td[2][contains(.,'whl')]]/td[1]/a/@href"),
+            ["/a-1.0-py3-none-any.whl", "/a-2.0-py3-none-any.whl"]
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let value = XPath::compile("//a[@data-yanked]/@href")
+            .unwrap()
+            .evaluate(&dom)
+            .unwrap();
+
+        match value.as_node_set().unwrap() {
+            [Item::Attribute {
+                element,
+                name,
+                value,
+            }] => {
+                assert_eq!(name, "href");
+                assert_eq!(value, "/a-2.0-py3-none-any.whl");
+                assert_eq!(
+                    element.get(dom.parser()).unwrap().as_tag().unwrap().name(),
+                    "a"
+                );
+            }
+            items => panic!("unexpected items: {items:?}"),
+        }
+
+        assert_eq!(eval("count(//a/@*)"), Value::Number(4.0));
+        assert_eq!(strings("name(//a[2]/@*)"), [""]);
+        assert_eq!(strings("name((//a)[3]/@*[2])"), ["data-yanked"]);
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(strings("//li[2]"), ["2"]);
+        assert_eq!(strings("//li[last()]"), ["3"]);
+        assert_eq!(strings("//li[position() > 1]"), ["2", "3"]);
+        assert_eq!(strings("(//td/a)[2]"), ["a-1.0"]);
+        assert_eq!(strings("//td/a[1]"), ["a-1.0", "a-1.0", "a-2.0"]);
+        // reverse axes count positions from the context node
+        assert_eq!(strings("//li[3]/preceding-sibling::li[1]"), ["2"]);
+        assert_eq!(strings("//li[3]/preceding-sibling::li"), ["1", "2"]);
+    }
+
+    #[test]
+    fn axes() {
+        assert_eq!(strings("//li[2]/parent::*/@*"), Vec::<String>::new());
+        assert_eq!(eval("name(//li[1]/..)"), Value::String("ul".into()));
+        assert_eq!(eval("count((//a)[1]/ancestor::*)"), Value::Number(5.0));
+        assert_eq!(eval("count(//a/ancestor::*)"), Value::Number(9.0));
+        assert_eq!(eval("name(//a/ancestor::*[2])"), Value::String("tr".into()));
+        assert_eq!(strings("//li[1]/following-sibling::li"), ["2", "3"]);
+        assert_eq!(strings("//li[2]/following::li"), ["3"]);
+        assert_eq!(strings("//li[2]/preceding::li"), ["1"]);
+        assert_eq!(
+            eval("count(//ul/descendant-or-self::*)"),
+            Value::Number(4.0)
+        );
+        assert_eq!(eval("count(//ul/descendant::node())"), Value::Number(7.0));
+        assert_eq!(eval("count(//ul/comment())"), Value::Number(1.0));
+        assert_eq!(strings("//ul/comment()"), [" end "]);
+        assert_eq!(eval("count(/html/body/*)"), Value::Number(3.0));
+        assert_eq!(eval("count(//LI)"), Value::Number(3.0));
+        assert_eq!(eval("count(//li/self::li)"), Value::Number(3.0));
+        assert_eq!(eval("count(//li | //ul | //li)"), Value::Number(4.0));
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(strings("string(//p)"), ["Fish & Chips"]);
+        assert_eq!(strings("normalize-space('  a \n b ')"), ["a b"]);
+        assert_eq!(strings("concat('a', 1, true())"), ["a1true"]);
+        assert_eq!(strings("substring('12345', 1.5, 2.6)"), ["234"]);
+        assert_eq!(strings("substring('12345', 0, 3)"), ["12"]);
+        assert_eq!(strings("substring-before('1999/04/01', '/')"), ["1999"]);
+        assert_eq!(strings("substring-after('1999/04/01', '/')"), ["04/01"]);
+        assert_eq!(strings("translate('bar', 'abc', 'ABC')"), ["BAr"]);
+        assert_eq!(strings("translate('--aaa--', 'abc-', 'ABC')"), ["AAA"]);
+        assert_eq!(eval("string-length('héllo')"), Value::Number(5.0));
+        assert_eq!(eval("sum(//li)"), Value::Number(6.0));
+        assert_eq!(eval("starts-with(//a/@href, '/a-1')"), Value::Boolean(true));
+        assert_eq!(
+            eval("round(2.5) + floor(-1.5) + ceiling(1.2)"),
+            Value::Number(3.0)
+        );
+        assert_eq!(
+            eval("boolean(//table) and not(//form)"),
+            Value::Boolean(true)
+        );
+        assert_eq!(eval("count(id('files'))"), Value::Number(1.0));
+        assert_eq!(eval("lang('en')"), Value::Boolean(false));
+        assert_eq!(eval("boolean(//p[lang('en')])"), Value::Boolean(true));
+        assert_eq!(eval("local-name(//li)"), Value::String("li".into()));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(strings("1 div 0"), ["Infinity"]);
+        assert_eq!(strings("-1 div 0"), ["-Infinity"]);
+        assert_eq!(strings("0 div 0"), ["NaN"]);
+        assert_eq!(strings("-0"), ["0"]);
+        assert_eq!(strings("3 * 2"), ["6"]);
+        assert_eq!(strings("7 mod 3 - 0.5"), ["0.5"]);
+        assert_eq!(strings("number(' 12 ') + number('abc')"), ["NaN"]);
+        assert_eq!(eval("number(' 12 ')"), Value::Number(12.0));
+        assert_eq!(eval("-(2 - 5)"), Value::Number(3.0));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(eval("//li = 2"), Value::Boolean(true));
+        assert_eq!(eval("//li != 2"), Value::Boolean(true));
+        assert_eq!(eval("//li > 3"), Value::Boolean(false));
+        assert_eq!(eval("//li = '4'"), Value::Boolean(false));
+        assert_eq!(eval("//li = //td"), Value::Boolean(false));
+        assert_eq!(eval("//th = 'Kind'"), Value::Boolean(true));
+        assert_eq!(eval("//form = false()"), Value::Boolean(true));
+        assert_eq!(eval("'1' = 1.0"), Value::Boolean(true));
+        assert_eq!(eval("1 < 2 = true()"), Value::Boolean(true));
+        assert_eq!(eval("count(//li[. >= 2])"), Value::Number(2.0));
+    }
+
+    #[test]
+    fn context_node() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let row = XPath::compile("//tr[3]")
+            .unwrap()
+            .evaluate(&dom)
+            .unwrap()
+            .nodes()[0];
+
+        let xpath = XPath::compile("td[2]").unwrap();
+        assert_eq!(
+            xpath.evaluate_from(&dom, row).unwrap().strings(&dom),
+            ["sdist"]
+        );
+
+        // absolute paths start at the document
+        let xpath = XPath::compile("count(//tr)").unwrap();
+        assert_eq!(xpath.evaluate_from(&dom, row).unwrap(), Value::Number(4.0));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(XPath::compile("//").unwrap_err(), XPathError::Syntax);
+        assert_eq!(XPath::compile("//a[").unwrap_err(), XPathError::Syntax);
+        assert_eq!(XPath::compile("'abc").unwrap_err(), XPathError::Syntax);
+        assert_eq!(
+            XPath::compile("foo()").unwrap_err(),
+            XPathError::UnknownFunction
+        );
+        assert_eq!(
+            XPath::compile("count()").unwrap_err(),
+            XPathError::InvalidArguments
+        );
+        assert_eq!(
+            XPath::compile("namespace::*").unwrap_err(),
+            XPathError::UnsupportedAxis
+        );
+        assert_eq!(
+            XPath::compile("$x").unwrap_err(),
+            XPathError::UnboundVariable
+        );
+
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let xpath = XPath::compile("count(1)").unwrap();
+        assert_eq!(xpath.evaluate(&dom).unwrap_err(), XPathError::NotANodeSet);
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let dom = parse("<p>1</p>", ParserOptions::default()).unwrap();
+
+        let nested = format!("{}1{}", "(".repeat(128), ")".repeat(128));
+        let xpath = XPath::compile(&nested).unwrap();
+        assert_eq!(xpath.evaluate(&dom).unwrap(), Value::Number(1.0));
+
+        let too_deep = [
+            format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000)),
+            format!("{}1", "-".repeat(100_000)),
+            format!("1{}", "+1".repeat(100_000)),
+            format!("//p{}", "[p".repeat(100_000)),
+            format!("{}1{}", "string(".repeat(100_000), ")".repeat(100_000)),
+        ];
+
+        for expression in &too_deep {
+            assert_eq!(
+                XPath::compile(expression).unwrap_err(),
+                XPathError::NestingTooDeep
+            );
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use super::*;
+    use crate::serialize::{from_arena, from_nested, Arena, Subtree};
+    use crate::OwnedVDom;
+
+    const INPUT: &str = r#"<!-- head --><div id="main" class="a b"><p hidden>Hello <b>world</b></p><img src="x.png"></div>tail"#;
+
+    #[test]
+    fn nested_round_trip() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let json = serde_json::to_string(&dom).unwrap();
+
+        let owned: OwnedVDom = serde_json::from_str(&json).unwrap();
+        let owned = owned.get_ref();
+
+        assert_eq!(owned.outer_html(), dom.outer_html());
+        assert_eq!(owned.children().len(), 3);
+        assert_eq!(serde_json::to_string(owned).unwrap(), json);
+
+        let p = owned.query_selector("p").unwrap().next().unwrap();
+        let tag = p.get(owned.parser()).unwrap().as_tag().unwrap();
+        assert_eq!(tag.attributes().get("hidden"), Some(None));
+        assert_eq!(tag.raw(), "");
+    }
+
+    #[test]
+    fn nested_format() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let value = serde_json::to_value(&dom).unwrap();
+
+        assert_eq!(
+            value[0],
+            serde_json::json!({"type": "comment", "comment": "<!-- head -->"})
+        );
+        assert_eq!(value[1]["name"], "div");
+        assert_eq!(
+            value[1]["attributes"],
+            serde_json::json!({"id": "main", "class": "a b"})
+        );
+        assert_eq!(
+            value[1]["children"][0]["attributes"],
+            serde_json::json!({"hidden": null})
+        );
+        assert_eq!(
+            value[2],
+            serde_json::json!({"type": "text", "text": "tail"})
+        );
+    }
+
+    #[test]
+    fn subtree() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let p = dom.query_selector("p").unwrap().next().unwrap();
+        let json = serde_json::to_string(&Subtree::new(&dom, p)).unwrap();
+
+        let options = ParserOptions::default().track_classes();
+        let owned = from_nested(&mut serde_json::Deserializer::from_str(&json), options).unwrap();
+        let owned = owned.get_ref();
+
+        assert_eq!(owned.children().len(), 1);
+        assert_eq!(owned.outer_html(), "<p hidden>Hello <b>world</b></p>");
+
+        // attributes can be omitted
+        let json = r#"{"type": "element", "name": "br"}"#;
+        let owned: OwnedVDom = serde_json::from_str(json).unwrap();
+        assert_eq!(owned.get_ref().outer_html(), "<br>");
+
+        let dangling = serde_json::to_string(&Subtree::new(&dom, NodeHandle::new(100)));
+        assert!(dangling.is_err());
+    }
+
+    #[test]
+    fn tracking() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let json = serde_json::to_string(&dom).unwrap();
+
+        let options = ParserOptions::default().track_ids().track_classes();
+        let owned = from_nested(&mut serde_json::Deserializer::from_str(&json), options).unwrap();
+        let owned = owned.get_ref();
+
+        assert!(owned.parser().options.is_tracking());
+        assert_eq!(
+            owned.get_element_by_id("main"),
+            owned.query_selector("div").unwrap().next()
+        );
+        assert_eq!(owned.get_elements_by_class_name("b").count(), 1);
+    }
+
+    #[test]
+    fn arena_round_trip() {
+        let mut dom = parse(INPUT, ParserOptions::default()).unwrap();
+
+        // detach the <img>, which stays in the arena
+        let div = dom.get_element_by_id("main").unwrap();
+        let img = div
+            .get_mut(dom.parser_mut())
+            .unwrap()
+            .as_tag_mut()
+            .unwrap()
+            .children_mut()
+            .top_mut()
+            .remove(1);
+
+        let json = serde_json::to_string(&Arena::new(&dom)).unwrap();
+        let owned = from_arena(
+            &mut serde_json::Deserializer::from_str(&json),
+            ParserOptions::default(),
+        )
+        .unwrap();
+        let owned = owned.get_ref();
+
+        assert_eq!(owned.nodes().len(), dom.nodes().len());
+        assert_eq!(owned.children(), dom.children());
+        assert_eq!(owned.outer_html(), dom.outer_html());
+        assert_eq!(
+            img.get(owned.parser()).unwrap().outer_html(owned.parser()),
+            r#"<img src="x.png">"#
+        );
+        assert_eq!(serde_json::to_string(&Arena::new(owned)).unwrap(), json);
+    }
+
+    #[test]
+    fn arena_invalid() {
+        let from = |json: &str| {
+            from_arena(
+                &mut serde_json::Deserializer::from_str(json),
+                ParserOptions::default(),
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+        };
+
+        let element = |children: &str| {
+            format!(r#"{{"type": "element", "name": "div", "children": {children}}}"#)
+        };
+
+        assert!(from(r#"{"nodes": [], "children": []}"#).is_ok());
+        assert!(from(&format!(
+            r#"{{"nodes": [{}], "children": [0]}}"#,
+            element("[]")
+        ))
+        .is_ok());
+
+        let out_of_bounds = from(&format!(
+            r#"{{"nodes": [{}], "children": [1]}}"#,
+            element("[]")
+        ));
+        assert!(out_of_bounds.unwrap_err().contains("out of bounds"));
+
+        let two_parents = from(&format!(
+            r#"{{"nodes": [{}, {}], "children": [0, 1]}}"#,
+            element("[1]"),
+            element("[]")
+        ));
+        assert!(two_parents.unwrap_err().contains("more than one parent"));
+
+        let self_cycle = from(&format!(
+            r#"{{"nodes": [{}], "children": []}}"#,
+            element("[0]")
+        ));
+        assert!(self_cycle.unwrap_err().contains("contains itself"));
+
+        let cycle = from(&format!(
+            r#"{{"nodes": [{}, {}], "children": []}}"#,
+            element("[1]"),
+            element("[0]")
+        ));
+        assert!(cycle.unwrap_err().contains("contains itself"));
+    }
+
+    #[test]
+    fn arena_deep() {
+        let depth = if cfg!(miri) { 100 } else { 100_000 };
+        let input = format!("{}x{}", "<div>".repeat(depth), "</div>".repeat(depth));
+        let dom = parse(&input, ParserOptions::default()).unwrap();
+
+        let json = serde_json::to_string(&Arena::new(&dom)).unwrap();
+        let owned = from_arena(
+            &mut serde_json::Deserializer::from_str(&json),
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(owned.get_ref().descendants().count(), depth + 1);
+    }
+}
+
+mod simple_index {
+    use super::*;
+    use crate::simple_index::{parse_project_page, Hashes, SimpleFile};
+
+    const INPUT: &str = r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta name="pypi:repository-version" content="1.4">
+<meta name="pypi:project-status" content="archived">    <title>Links for iniconfig</title>
+  </head>
+  <body>
+    <h1>Links for iniconfig</h1>
+<a href="https://files.pythonhosted.org/packages/47/a6/iniconfig-0.1.tar.gz#sha256=f0a16b26" >iniconfig-0.1.tar.gz</a><br />
+<a href="https://files.pythonhosted.org/packages/20/46/iniconfig-1.0.1-py3-none-any.whl#sha256=80cf40c5" data-dist-info-metadata="sha256=27e5d42c" data-core-metadata="sha256=27e5d42c">iniconfig-1.0.1-py3-none-any.whl</a><br />
+<a href="/packages/iniconfig-2.0.0.tar.gz#SHA512=abc" data-requires-python="&gt;=3.7,&lt;4" data-dist-info-metadata="true" data-gpg-sig="false">
+  iniconfig-2.0.0.tar.gz
+</a><br />
+<a href="iniconfig-2.1.0.tar.gz?a=1&amp;b=2#egg=iniconfig" data-yanked data-gpg-sig="true">iniconfig-2.1.0.tar.gz</a>
+<a name="no-href">not a file</a>
+<a href="iniconfig-3.0.0.tar.gz" data-yanked="Broken &quot;wheel&quot;" data-core-metadata="sha256=def" data-dist-info-metadata="true">iniconfig-3.0.0.tar.gz</a>
+  </body>
+</html>"#;
+
+    fn hashes(algorithm: &str, value: &str) -> Hashes {
+        Hashes::from([(algorithm.to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn project_page() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let page = parse_project_page(&dom, Some("https://pypi.org/simple/iniconfig/"));
+
+        assert_eq!(page.repository_version.as_deref(), Some("1.4"));
+        assert_eq!(page.project_status.as_deref(), Some("archived"));
+        assert_eq!(page.files.len(), 5);
+
+        assert_eq!(
+            page.files[0],
+            SimpleFile {
+                filename: "iniconfig-0.1.tar.gz".into(),
+                url: "https://files.pythonhosted.org/packages/47/a6/iniconfig-0.1.tar.gz".into(),
+                hashes: hashes("sha256", "f0a16b26"),
+                ..SimpleFile::default()
+            }
+        );
+
+        assert_eq!(
+            page.files[1].core_metadata,
+            Some(hashes("sha256", "27e5d42c"))
+        );
+
+        let file = &page.files[2];
+        assert_eq!(file.filename, "iniconfig-2.0.0.tar.gz");
+        assert_eq!(file.url, "https://pypi.org/packages/iniconfig-2.0.0.tar.gz");
+        assert_eq!(file.hashes, hashes("sha512", "abc"));
+        assert_eq!(file.requires_python.as_deref(), Some(">=3.7,<4"));
+        assert_eq!(file.core_metadata, Some(Hashes::new()));
+        assert_eq!(file.gpg_sig, Some(false));
+        assert!(!file.is_yanked());
+
+        // a fragment that is not a hash is kept
+        let file = &page.files[3];
+        assert_eq!(
+            file.url,
+            "https://pypi.org/simple/iniconfig/iniconfig-2.1.0.tar.gz?a=1&b=2#egg=iniconfig"
+        );
+        assert!(file.hashes.is_empty());
+        assert_eq!(file.yanked.as_deref(), Some(""));
+        assert_eq!(file.gpg_sig, Some(true));
+        assert_eq!(file.core_metadata, None);
+
+        // data-core-metadata takes precedence over data-dist-info-metadata
+        let file = &page.files[4];
+        assert_eq!(file.yanked.as_deref(), Some(r#"Broken "wheel""#));
+        assert_eq!(file.core_metadata, Some(hashes("sha256", "def")));
+    }
+
+    #[test]
+    fn relative_urls_without_base() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let page = parse_project_page(&dom, None);
+
+        assert_eq!(page.files[2].url, "/packages/iniconfig-2.0.0.tar.gz");
+        assert_eq!(page.files[4].url, "iniconfig-3.0.0.tar.gz");
+    }
+
+    #[test]
+    fn empty_page() {
+        let dom = parse("<html><body></body></html>", ParserOptions::default()).unwrap();
+        let page = parse_project_page(&dom, None);
+
+        assert_eq!(page.repository_version, None);
+        assert_eq!(page.project_status, None);
+        assert!(page.files.is_empty());
+    }
+}
+
+mod links {
+    use super::*;
+    use crate::links::join_url;
+    use crate::Link;
+
+    #[test]
+    fn base_href() {
+        let dom = parse(
+            r#"<head><base target="_blank"><base href="/static/v2/"><base href="/ignored/"></head><a href="a.html">a</a>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            dom.resolve_url("a.html", "https://example.com/page")
+                .as_deref(),
+            Some("https://example.com/static/v2/a.html")
+        );
+        assert_eq!(
+            dom.resolve_url("https://other.org/x", "https://example.com/page")
+                .as_deref(),
+            Some("https://other.org/x")
+        );
+
+        let base = dom.base_url("https://example.com/page");
+        assert_eq!(base.as_str(), "https://example.com/static/v2/");
+        assert_eq!(
+            base.resolve("a.html"),
+            dom.resolve_url("a.html", "https://example.com/page")
+        );
+
+        let dom = parse(r#"<a href="a.html">a</a>"#, ParserOptions::default()).unwrap();
+        assert_eq!(
+            dom.base_url("https://example.com/x/y/z").as_str(),
+            "https://example.com/x/y/z"
+        );
+        assert_eq!(
+            dom.resolve_url("../b/./c", "https://example.com/x/y/z")
+                .as_deref(),
+            Some("https://example.com/x/b/c")
+        );
+    }
+
+    #[test]
+    fn all_links() {
+        let input = r#"<base href="https://cdn.example.com/">
+<a href="/a?x=1&amp;y=2">a</a>
+<img src="i.png" srcset="i-1x.png 1x, i-2x.png 2x">
+<form action="submit"><input type="image" src="btn.png"></form>
+<video poster="p.jpg" src="v.mp4"></video>
+<a name="anchor">no href</a>"#;
+        let dom = parse(input, ParserOptions::default()).unwrap();
+        let links = dom.links("https://example.com/page.html");
+
+        let urls: Vec<(&str, &str)> = links
+            .iter()
+            .map(|link| (link.attribute, link.url.as_str()))
+            .collect();
+
+        assert_eq!(
+            urls,
+            [
+                ("href", "https://cdn.example.com/a?x=1&y=2"),
+                ("src", "https://cdn.example.com/i.png"),
+                ("srcset", "https://cdn.example.com/i-1x.png"),
+                ("srcset", "https://cdn.example.com/i-2x.png"),
+                ("action", "https://cdn.example.com/submit"),
+                ("src", "https://cdn.example.com/btn.png"),
+                ("src", "https://cdn.example.com/v.mp4"),
+                ("poster", "https://cdn.example.com/p.jpg"),
+            ]
+        );
+
+        let img = dom.query_selector("img").unwrap().next().unwrap();
+        assert_eq!(
+            links[1],
+            Link {
+                handle: img,
+                attribute: "src",
+                url: "https://cdn.example.com/i.png".into()
+            }
+        );
+    }
+
+    #[test]
+    fn relative_base() {
+        let dom = parse(
+            r#"<base href="sub/"><a href="x">x</a>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            dom.links("https://example.com/dir/index.html")[0].url,
+            "https://example.com/dir/sub/x"
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn whatwg() {
+        let dom = parse(r#"<a href="x">x</a>"#, ParserOptions::default()).unwrap();
+
+        assert_eq!(
+            dom.resolve_url("/A B", "HTTP://Example.COM/").as_deref(),
+            Some("http://example.com/A%20B")
+        );
+        assert_eq!(dom.resolve_url("http://[::1", "https://example.com/"), None);
+    }
+
+    #[test]
+    fn join_urls() {
+        let base = "https://example.com/a/b/c.html?q=1#frag";
+
+        assert_eq!(join_url(base, "d.html"), "https://example.com/a/b/d.html");
+        assert_eq!(join_url(base, "../d"), "https://example.com/a/d");
+        assert_eq!(join_url(base, "./"), "https://example.com/a/b/");
+        assert_eq!(join_url(base, "/x/./y/../z"), "https://example.com/x/z");
+        assert_eq!(
+            join_url(base, "//cdn.example.com/x"),
+            "https://cdn.example.com/x"
+        );
+        assert_eq!(join_url(base, "?p=2"), "https://example.com/a/b/c.html?p=2");
+        assert_eq!(
+            join_url(base, "#top"),
+            "https://example.com/a/b/c.html?q=1#top"
+        );
+        assert_eq!(join_url(base, ""), "https://example.com/a/b/c.html?q=1");
+        assert_eq!(join_url(base, "mailto:a@b.c"), "mailto:a@b.c");
+        assert_eq!(
+            join_url("https://example.com", "x"),
+            "https://example.com/x"
+        );
+    }
+}
+
+mod table {
+    use super::*;
+    use crate::table::{parse_table, Section, Table};
+
+    fn extract(input: &str) -> Table {
+        let dom = parse(input, ParserOptions::default()).unwrap();
+        let handle = dom.query_selector("table").unwrap().next().unwrap();
+        parse_table(dom.parser(), handle).unwrap()
+    }
+
+    #[test]
+    fn omitted_end_tags() {
+        let table = extract("<table><tr><td>a<td>b<tr><td>c<td>d</table>");
+
+        assert_eq!(table.text_grid(), [["a", "b"], ["c", "d"]]);
+        assert_eq!(table.header_rows(), 0);
+    }
+
+    #[test]
+    fn spans() {
+        let table = extract(
+            r#"<table>
+                <tr><td rowspan="2">a</td><td colspan="2">b</td></tr>
+                <tr><td>c</td><td>d</td><td>e</td></tr>
+                <tr><td colspan="0">f</td><td rowspan="x">g</td></tr>
+            </table>"#,
+        );
+
+        assert_eq!(
+            table.text_grid(),
+            [
+                ["a", "b", "b", ""],
+                ["a", "c", "d", "e"],
+                ["f", "g", "", ""]
+            ]
+        );
+        assert_eq!(table.width(), 4);
+        assert_eq!(table.cell(1, 0).unwrap().rowspan, 2);
+        assert_eq!(table.cell(0, 2).unwrap().column, 1);
+        assert_eq!(table.cell(2, 3), None);
+    }
+
+    #[test]
+    fn rowspan_zero() {
+        let table = extract(
+            "<table><tbody><tr><td rowspan=0>a<td>b<tr><td>c</tbody>\
+             <tbody><tr><td>d<td>e</tbody></table>",
+        );
+
+        assert_eq!(table.text_grid(), [["a", "b"], ["a", "c"], ["d", "e"]]);
+    }
+
+    #[test]
+    fn sections() {
+        let table = extract(
+            "<table><caption> Sales &amp; <b>costs</b> </caption>\
+             <colgroup><col span=2></colgroup>\
+             <tfoot><tr><td>total<td>3</tfoot>\
+             <thead><tr><th>item<th>count</thead>\
+             <tbody><tr><td>x<td>1<tr><td>y<td>2</tbody></table>",
+        );
+
+        assert_eq!(table.caption(), Some("Sales & costs"));
+        assert_eq!(
+            table.text_grid(),
+            [["item", "count"], ["x", "1"], ["y", "2"], ["total", "3"]]
+        );
+        assert_eq!(table.header_rows(), 1);
+        assert_eq!(table.section(0), Some(Section::Head));
+        assert_eq!(table.section(3), Some(Section::Foot));
+        assert!(table.cell(0, 1).unwrap().is_header);
+    }
+
+    #[test]
+    fn header_detection() {
+        let table = extract("<table><tr><th>a<th>b<tr><th>c<td>d</table>");
+        assert_eq!(table.header_rows(), 1);
+
+        let table = extract("<table><tr><td>a<td>b</table>");
+        assert_eq!(table.header_rows(), 0);
+    }
+
+    #[test]
+    fn nested_table() {
+        let table = extract(
+            "<table><tr><td>a<table><tr><td>inner</td></tr></table>b</td><td>c</td></tr></table>",
+        );
+
+        assert_eq!(table.text_grid(), [["a inner b", "c"]]);
+
+        // the inner table on its own, whose close edge ends the traversal
+        let dom = parse(
+            "<table><tr><td><table><tr><td>x<table><tr><td>y</td></tr></table></td><td>z</td></tr></table></td></tr></table>",
+            ParserOptions::default(),
+        )
+        .unwrap();
+        let inner = dom.query_selector("table").unwrap().nth(1).unwrap();
+        let table = parse_table(dom.parser(), inner).unwrap();
+
+        assert_eq!(table.text_grid(), [["x y", "z"]]);
+    }
+
+    #[test]
+    fn handles() {
+        let dom = parse(
+            "<table><tr><td>a</td><td id=b>b</td></tr></table>",
+            ParserOptions::default().track_ids(),
+        )
+        .unwrap();
+        let handle = dom.query_selector("table").unwrap().next().unwrap();
+        let table = parse_table(dom.parser(), handle).unwrap();
+
+        assert_eq!(
+            table.cell(0, 1).unwrap().handle,
+            dom.get_element_by_id("b").unwrap()
+        );
+        assert_eq!(table.row(0).unwrap().len(), 2);
+
+        let td = dom.query_selector("td").unwrap().next().unwrap();
+        assert!(parse_table(dom.parser(), td).is_none());
+    }
+}
+
+mod forms {
+    use super::*;
+    use crate::forms::{parse_form, Enctype, Form, Method};
+    use crate::VDom;
+
+    fn form(dom: &VDom<'_>, selector: &str) -> Form {
+        let handle = dom.query_selector(selector).unwrap().next().unwrap();
+        parse_form(dom, handle).unwrap()
+    }
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn successful_controls() {
+        let dom = parse(
+            r#"<form>
+                <input name="q" value="a&amp;b">
+                <input name="unnamed-is-skipped" disabled>
+                <input value="no name">
+                <input type="checkbox" name="c1" value="x" checked>
+                <input type="checkbox" name="c2">
+                <input type="radio" name="r" value="1" checked>
+                <input type="radio" name="r" value="2" checked>
+                <input type="hidden" name="_charset_">
+                <input type="file" name="upload">
+                <input type="reset" name="reset">
+                <textarea name="t">
+line</textarea>
+                <select name="s1"><option>first</option><option disabled selected>no</option></select>
+                <select name="s2"><option value="a">A</option><option>  b   c </option></select>
+                <select name="m" multiple><option selected>x<option>y<option selected>z</select>
+                <datalist><input name="in-datalist"></datalist>
+            </form>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let form = form(&dom, "form");
+
+        assert_eq!(
+            form.entries(None),
+            entries(&[
+                ("q", "a&b"),
+                ("c1", "x"),
+                ("r", "2"),
+                ("_charset_", "UTF-8"),
+                ("upload", ""),
+                ("t", "line"),
+                ("s2", "a"),
+                ("m", "x"),
+                ("m", "z"),
+            ])
+        );
+        assert_eq!(form.method, Method::Get);
+        assert_eq!(form.enctype, Enctype::UrlEncoded);
+    }
+
+    #[test]
+    fn select_default() {
+        let dom = parse(
+            "<form><select name=s><option disabled>a</option><option>b</option><option>c</option></select></form>",
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(form(&dom, "form").entries(None), entries(&[("s", "b")]));
+    }
+
+    #[test]
+    fn disabled_fieldset() {
+        let dom = parse(
+            r#"<form>
+                <fieldset disabled>
+                    <legend><input name="in-legend" value="1"></legend>
+                    <input name="in-fieldset" value="2">
+                    <legend><input name="second-legend" value="3"></legend>
+                </fieldset>
+                <input name="outside" value="4">
+            </form>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let form = form(&dom, "form");
+
+        assert_eq!(
+            form.entries(None),
+            entries(&[("in-legend", "1"), ("outside", "4")])
+        );
+        assert!(form.controls[1].disabled);
+    }
+
+    #[test]
+    fn form_attribute() {
+        let dom = parse(
+            r#"<input name="before" value="1" form="f">
+            <form id="f"><input name="inside" value="2"><input name="other" form="g"></form>
+            <form id="g"></form>
+            <input name="after" value="3" form="f">
+            <input name="unowned" value="4">"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let form = form(&dom, "#f");
+        assert_eq!(
+            form.entries(None),
+            entries(&[("before", "1"), ("inside", "2"), ("after", "3")])
+        );
+
+        let form = parse_form(&dom, dom.get_element_by_id("g").unwrap()).unwrap();
+        assert_eq!(form.controls.len(), 1);
+        assert_eq!(form.controls[0].name, "other");
+    }
+
+    #[test]
+    fn submitter() {
+        let dom = parse(
+            r#"<form action="/search" method="POST" enctype="multipart/form-data">
+                <input name="q" value="a b">
+                <button name="go" value="1">Go</button>
+                <input type="submit" name="alt" value="Alt" formaction="/alt" formmethod="get">
+                <input type="image" name="map">
+                <button type="button" name="b">Nothing</button>
+            </form>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let form = form(&dom, "form");
+        let button = |selector: &str| dom.query_selector(selector).unwrap().next();
+
+        let submission = form.submission(None);
+        assert_eq!(submission.action, "/search");
+        assert_eq!(submission.method, Method::Post);
+        assert_eq!(submission.enctype, Enctype::Multipart);
+        assert_eq!(submission.entries, entries(&[("q", "a b")]));
+
+        let submission = form.submission(button("button"));
+        assert_eq!(submission.to_urlencoded(), "q=a+b&go=1");
+
+        let submission = form.submission(button("input[type=submit]"));
+        assert_eq!(submission.action, "/alt");
+        assert_eq!(submission.method, Method::Get);
+        assert_eq!(submission.enctype, Enctype::Multipart);
+        assert_eq!(submission.to_urlencoded(), "q=a+b&alt=Alt");
+
+        let submission = form.submission(button("input[type=image]"));
+        assert_eq!(submission.to_urlencoded(), "q=a+b&map.x=0&map.y=0");
+
+        // a button that does not submit is not a submitter
+        let submission = form.submission(button("button[type=button]"));
+        assert_eq!(submission.entries, entries(&[("q", "a b")]));
+    }
+
+    #[test]
+    fn not_a_form() {
+        let dom = parse("<div></div>", ParserOptions::default()).unwrap();
+        let div = dom.children()[0];
+
+        assert!(parse_form(&dom, div).is_none());
+    }
+}
+
+mod metadata {
+    use super::*;
+    use crate::metadata::parse_metadata;
+
+    const INPUT: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <meta http-equiv="Content-Security-Policy" content="default-src 'self'">
+  <title>
+    Release   notes
+  </title>
+  <meta name="Keywords" content="a">
+  <meta name="keywords" content="b">
+  <meta property="og:title" content="Notes &amp; more">
+  <meta property="og:image" content="/1.png">
+  <meta property="og:image:width" content="100">
+  <meta property="og:image" content="/2.png">
+  <meta property="article:author" content="someone">
+  <meta property="twitter:site" content="@site">
+  <meta name="twitter:card" content="summary_large_image">
+  <link rel="canonical" href="https://example.com/notes">
+  <link rel="shortcut icon" href="/favicon.ico" sizes="16x16">
+  <link rel="alternate" type="application/rss+xml" href="/feed.xml" title="Feed">
+  <link rel="alternate" hreflang="de" href="/de/notes">
+  <link rel="manifest" href="/site.webmanifest">
+  <link rel="stylesheet">
+  <script type="application/ld+json">
+    {"@context": "https://schema.org", "@type": "Article"}
+  </script>
+  <script type=" Application/LD+JSON; charset=utf-8">[]</script>
+  <script>var x = "<title>not it</title>";</script>
+</head>
+<body><svg><title>icon</title></svg></body>
+</html>"#;
+
+    #[test]
+    fn meta() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let metadata = parse_metadata(&dom);
+
+        assert_eq!(metadata.title.as_deref(), Some("Release notes"));
+        assert_eq!(metadata.charset.as_deref(), Some("utf-8"));
+        assert_eq!(metadata.names["keywords"], ["a", "b"]);
+        assert_eq!(
+            metadata.http_equiv["content-security-policy"],
+            ["default-src 'self'"]
+        );
+        assert_eq!(metadata.properties["og:title"], ["Notes & more"]);
+    }
+
+    #[test]
+    fn open_graph_and_twitter() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let metadata = parse_metadata(&dom);
+
+        assert_eq!(metadata.open_graph["title"], ["Notes & more"]);
+        assert_eq!(metadata.open_graph["image"], ["/1.png", "/2.png"]);
+        assert_eq!(metadata.open_graph["image:width"], ["100"]);
+        assert_eq!(metadata.open_graph["article:author"], ["someone"]);
+        assert_eq!(metadata.open_graph.len(), 4);
+
+        assert_eq!(metadata.twitter["site"], ["@site"]);
+        assert_eq!(metadata.twitter["card"], ["summary_large_image"]);
+    }
+
+    #[test]
+    fn links_and_json_ld() {
+        let dom = parse(INPUT, ParserOptions::default()).unwrap();
+        let metadata = parse_metadata(&dom);
+
+        assert_eq!(metadata.canonical(), Some("https://example.com/notes"));
+        assert_eq!(metadata.manifest(), Some("/site.webmanifest"));
+
+        let icons: Vec<_> = metadata.icons().collect();
+        assert_eq!(icons.len(), 1);
+        assert_eq!(icons[0].rel, ["shortcut", "icon"]);
+        assert_eq!(icons[0].sizes.as_deref(), Some("16x16"));
+
+        let alternates: Vec<_> = metadata.alternates().map(|link| &link.href).collect();
+        assert_eq!(alternates, ["/feed.xml", "/de/notes"]);
+
+        // links without an href are skipped
+        assert_eq!(metadata.links.len(), 5);
+
+        assert_eq!(
+            metadata.json_ld,
+            [
+                r#"{"@context": "https://schema.org", "@type": "Article"}"#,
+                "[]"
+            ]
+        );
+    }
+}
+
+mod microdata {
+    use super::*;
+    use crate::microdata::{parse_microdata, parse_rdfa_lite, Value};
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    #[test]
+    fn property_values() {
+        let dom = parse(
+            r#"<div itemscope itemtype="https://schema.org/Product  https://schema.org/Product" itemid="urn:isbn:1">
+                <span itemprop="name description">Widget &amp; co</span>
+                <meta itemprop="sku" content="W-1">
+                <a itemprop="url" href="widget.html">link</a>
+                <img itemprop="image" src="/w.png">
+                <time itemprop="releaseDate" datetime="2024-01-01">January</time>
+                <time itemprop="updated">yesterday</time>
+                <data itemprop="weight" value="12">twelve</data>
+                <span itemprop="color">red</span>
+                <span itemprop="color">blue</span>
+            </div>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let items = parse_microdata(&dom, Some("https://example.com/shop/"));
+        assert_eq!(items.len(), 1);
+
+        let item = &items[0];
+        assert_eq!(item.types, ["https://schema.org/Product"]);
+        assert_eq!(item.id.as_deref(), Some("urn:isbn:1"));
+        assert_eq!(item.get("name"), Some(&text("Widget & co")));
+        assert_eq!(item.get("description"), Some(&text("Widget & co")));
+        assert_eq!(item.get("sku"), Some(&text("W-1")));
+        assert_eq!(
+            item.get("url"),
+            Some(&Value::Url("https://example.com/shop/widget.html".into()))
+        );
+        assert_eq!(
+            item.get("image"),
+            Some(&Value::Url("https://example.com/w.png".into()))
+        );
+        assert_eq!(item.get("releaseDate"), Some(&text("2024-01-01")));
+        assert_eq!(item.get("updated"), Some(&text("yesterday")));
+        assert_eq!(item.get("weight"), Some(&text("12")));
+        assert_eq!(item.get_all("color"), [text("red"), text("blue")]);
+        assert!(item.get_all("missing").is_empty());
+    }
+
+    #[test]
+    fn nested_items() {
+        let dom = parse(
+            r#"<div itemscope>
+                <p itemprop="author" itemscope itemtype="https://schema.org/Person">
+                    <span itemprop="name">Jane</span>
+                </p>
+                <span itemprop="title">Post</span>
+            </div>
+            <div itemscope><span itemprop="name">second</span></div>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let items = parse_microdata(&dom, None);
+        assert_eq!(items.len(), 2);
+
+        // properties of the nested item are not properties of the outer item
+        assert_eq!(items[0].properties.len(), 2);
+        assert_eq!(items[0].get("title"), Some(&text("Post")));
+        assert!(items[0].types.is_empty());
+
+        let author = items[0].get("author").and_then(Value::as_item).unwrap();
+        assert!(author.has_type("https://schema.org/Person"));
+        assert_eq!(author.get("name"), Some(&text("Jane")));
+
+        assert_eq!(items[1].get("name"), Some(&text("second")));
+    }
+
+    #[test]
+    fn itemref() {
+        let dom = parse(
+            r#"<div itemscope itemref="a b missing"><span itemprop="first">1</span></div>
+            <p id="b" itemprop="third">3</p>
+            <div id="a"><span itemprop="second">2</span></div>
+            <div itemscope itemref="c"></div>
+            <div id="cw"><div id="c" itemprop="child" itemscope itemref="dw"></div></div>
+            <div id="dw"><div itemprop="grandchild" itemscope itemref="cw"></div></div>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let items = parse_microdata(&dom, None);
+        assert_eq!(items.len(), 2);
+
+        // properties are in document order, regardless of the order in itemref
+        let names: Vec<_> = items[0]
+            .properties
+            .iter()
+            .map(|(name, values)| (name.as_str(), values[0].as_str().unwrap()))
+            .collect();
+        assert_eq!(names, [("first", "1"), ("second", "2"), ("third", "3")]);
+
+        // the innermost item refers back to the item that contains it, which is left out instead of recursing
+        let child = items[1].get("child").and_then(Value::as_item).unwrap();
+        let grandchild = child.get("grandchild").and_then(Value::as_item).unwrap();
+        assert!(grandchild.properties.is_empty());
+    }
+
+    #[test]
+    fn rdfa_lite() {
+        let dom = parse(
+            r##"<div vocab="https://schema.org/" typeof="Product" resource="#widget" prefix="ex: https://example.com/ns#">
+                <span property="name">Widget</span>
+                <meta property="sku" content="W-1">
+                <a property="url" href="/widget">link</a>
+                <div property="offers" typeof="Offer ex:Special">
+                    <span property="price" content="9.99">$9.99</span>
+                </div>
+            </div>
+            <p typeof="Thing"><span property="name">unscoped</span></p>"##,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let items = parse_rdfa_lite(&dom, Some("https://example.com/"));
+        assert_eq!(items.len(), 2);
+
+        let product = &items[0];
+        assert_eq!(product.types, ["https://schema.org/Product"]);
+        assert_eq!(product.id.as_deref(), Some("https://example.com/#widget"));
+        assert_eq!(product.get("name"), Some(&text("Widget")));
+        assert_eq!(product.get("sku"), Some(&text("W-1")));
+        assert_eq!(
+            product.get("url"),
+            Some(&Value::Url("https://example.com/widget".into()))
+        );
+
+        let offer = product.get("offers").and_then(Value::as_item).unwrap();
+        assert_eq!(
+            offer.types,
+            ["https://schema.org/Offer", "https://example.com/ns#Special"]
+        );
+        assert_eq!(offer.get("price"), Some(&text("9.99")));
+        assert!(product.get("price").is_none());
+
+        assert_eq!(items[1].types, ["Thing"]);
+        assert_eq!(items[1].get("name"), Some(&text("unscoped")));
+    }
+}
+
+mod srcset {
+    use super::*;
+    use crate::srcset::{parse_sizes, parse_srcset, Descriptor, SourceSize};
+
+    fn urls(value: &str) -> Vec<String> {
+        parse_srcset(value)
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect()
+    }
+
+    #[test]
+    fn descriptors() {
+        assert_eq!(
+            parse_srcset(" a.png 1.5x,b.png 300w 200h , c.png,"),
+            [
+                ("a.png".to_string(), Descriptor::Density(1.5)),
+                ("b.png".to_string(), Descriptor::Width(300)),
+                ("c.png".to_string(), Descriptor::Density(1.0)),
+            ]
+        );
+
+        // a URL that ends with commas has no descriptors
+        assert_eq!(
+            parse_srcset("a.png,, b.png 2x"),
+            [
+                ("a.png".to_string(), Descriptor::Density(1.0)),
+                ("b.png".to_string(), Descriptor::Density(2.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_descriptors() {
+        assert!(urls("a.png 0w").is_empty());
+        assert!(urls("a.png 1w 2x").is_empty());
+        assert!(urls("a.png 2x 2x").is_empty());
+        assert!(urls("a.png 100h").is_empty());
+        assert!(urls("a.png .5x").is_empty());
+        assert!(urls("a.png +1x").is_empty());
+        assert!(urls("a.png 1.x").is_empty());
+        assert!(urls("a.png -1x").is_empty());
+        assert!(urls("a.png 10q").is_empty());
+        assert!(urls("a.png 1é").is_empty());
+        assert!(urls("").is_empty());
+        assert!(urls(" , ,").is_empty());
+
+        assert_eq!(urls("bad.png 1q, good.png 1e1x"), ["good.png"]);
+        assert_eq!(
+            parse_srcset("a.png 1e1x"),
+            [("a.png".to_string(), Descriptor::Density(10.0))]
+        );
+    }
+
+    #[test]
+    fn commas_in_urls_and_parentheses() {
+        assert_eq!(
+            urls("https://example.com/a,b.png 1x, data:image/svg+xml,<svg/> 2x"),
+            ["https://example.com/a,b.png", "data:image/svg+xml,<svg/>"]
+        );
+
+        // a comma inside of parentheses does not end the candidate, which is dropped for its unknown descriptor
+        assert_eq!(urls("a.png (1x, 2x), b.png"), ["b.png"]);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(
+            parse_sizes(
+                "(min-width: 36em) and (orientation: landscape) 33.3vw, , (max-width: 10em) min(50vw, 100px), 100vw"
+            ),
+            [
+                SourceSize {
+                    media: Some("(min-width: 36em) and (orientation: landscape)".into()),
+                    size: "33.3vw".into()
+                },
+                SourceSize {
+                    media: Some("(max-width: 10em)".into()),
+                    size: "min(50vw, 100px)".into()
+                },
+                SourceSize {
+                    media: None,
+                    size: "100vw".into()
+                },
+            ]
+        );
+        assert!(parse_sizes("").is_empty());
+    }
+
+    #[test]
+    fn links() {
+        let dom = parse(
+            r#"<picture>
+                <source srcset="/a,1.webp 1x, /a-2.webp 2x" type="image/webp">
+                <img src="/a.jpg" srcset="/a-480.jpg 480w, /a-bad.jpg 0w">
+            </picture>
+            <link rel="preload" as="image" imagesrcset="/p.jpg 1x, /p-2.jpg 2x">"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let links: Vec<_> = dom
+            .links("https://example.com/")
+            .into_iter()
+            .map(|link| (link.attribute, link.url))
+            .collect();
+
+        assert_eq!(
+            links,
+            [
+                ("srcset", "https://example.com/a,1.webp".to_string()),
+                ("srcset", "https://example.com/a-2.webp".to_string()),
+                ("src", "https://example.com/a.jpg".to_string()),
+                ("srcset", "https://example.com/a-480.jpg".to_string()),
+                ("imagesrcset", "https://example.com/p.jpg".to_string()),
+                ("imagesrcset", "https://example.com/p-2.jpg".to_string()),
+            ]
+        );
+    }
+}
+
+mod style {
+    use super::*;
+    use crate::{
+        errors::StyleError,
+        style::{Declaration, Style},
+    };
+
+    fn declaration(property: &str, value: &str, important: bool) -> Declaration {
+        Declaration {
+            property: property.into(),
+            value: value.into(),
+            important,
+        }
+    }
+
+    #[test]
+    fn parse_declarations() {
+        let style = Style::parse(
+            " COLOR : Red ; ;margin:0 auto!important;--Accent: #F00 ; --empty:; font-family: \"A;B\", serif /* ; */",
+        );
+
+        assert_eq!(
+            style.declarations(),
+            [
+                declaration("color", "Red", false),
+                declaration("margin", "0 auto", true),
+                declaration("--Accent", "#F00", false),
+                declaration("--empty", "", false),
+                declaration("font-family", "\"A;B\", serif", false),
+            ]
+        );
+        assert_eq!(style.value("Color"), Some("Red"));
+        assert_eq!(style.value("--accent"), None);
+    }
+
+    #[test]
+    fn error_recovery() {
+        let style = Style::parse(
+            "color; 1px: 2; width: ; x y: 1; background: url(a;b) ; height: calc(1px + (2px; 3px)); @media x; top: 1px",
+        );
+
+        let properties: Vec<_> = style.iter().map(|d| d.property.as_str()).collect();
+        assert_eq!(properties, ["background", "height", "top"]);
+        assert_eq!(style.value("background"), Some("url(a;b)"));
+
+        // an unterminated block swallows the rest of the attribute
+        assert_eq!(Style::parse("a: {b; c: d").len(), 1);
+        assert!(Style::parse("").is_empty());
+        assert!(Style::parse("/* only a comment */").is_empty());
+    }
+
+    #[test]
+    fn precedence() {
+        let style =
+            Style::parse("color: red !important; color: blue; display: block; display: none");
+
+        assert_eq!(style.value("color"), Some("red"));
+        assert_eq!(style.value("display"), Some("none"));
+        assert!(style.is_hidden());
+        assert!(!Style::parse("visibility: visible").is_hidden());
+        assert!(Style::parse("visibility: Collapse").is_hidden());
+    }
+
+    #[test]
+    fn edit() {
+        let mut style = Style::parse("color: red; margin: 0; color: blue");
+
+        style.set("COLOR", " green ", false).unwrap();
+        style.set("padding", "1px", true).unwrap();
+        assert_eq!(
+            style.to_string(),
+            "color: green; margin: 0; padding: 1px !important;"
+        );
+
+        assert_eq!(
+            style.remove("margin"),
+            Some(declaration("margin", "0", false))
+        );
+        assert_eq!(style.remove("margin"), None);
+        assert_eq!(style.to_string(), "color: green; padding: 1px !important;");
+        assert_eq!(Style::new().to_string(), "");
+    }
+
+    #[test]
+    fn set_rejects_invalid_values() {
+        let mut style = Style::parse("color: red");
+
+        for value in [
+            "blue; display: none",
+            "blue !important",
+            "blue!IMPORTANT",
+            "url(a",
+            "calc(1px + 2px))",
+            "calc(1px]",
+            "\"Fira Sans",
+            "'a\nb'",
+            "blue /* ; */",
+            "blue\\",
+            "",
+            " \t",
+        ] {
+            assert_eq!(
+                style.set("color", value, false),
+                Err(StyleError::InvalidValue),
+                "{value:?}"
+            );
+        }
+
+        for property in ["", "1px", "color: blue; x", "a b"] {
+            assert_eq!(
+                style.set(property, "blue", false),
+                Err(StyleError::InvalidProperty),
+                "{property:?}"
+            );
+        }
+
+        assert_eq!(style.to_string(), "color: red;");
+
+        for value in [
+            "url(a;b)",
+            "\"a;b!\"",
+            "'it\\'s'",
+            "a\\;b",
+            "calc((1px + 2px) * 3)",
+        ] {
+            style.set("color", value, true).unwrap();
+            assert_eq!(Style::parse(&style.to_string()), style, "{value:?}");
+        }
+
+        style.set("--empty", "", false).unwrap();
+        assert_eq!(Style::parse(&style.to_string()), style);
+
+        let mut dom = parse("<div></div>", ParserOptions::default()).unwrap();
+        let attributes = dom.nodes_mut()[0].as_tag_mut().unwrap().attributes_mut();
+        assert_eq!(
+            attributes.set_style_property("color", "red; display: none", false),
+            Err(StyleError::InvalidValue)
+        );
+        assert!(attributes.style().is_none());
+    }
+
+    #[test]
+    fn attributes() {
+        let mut dom = parse(
+            r#"<div style="font-family: &quot;Fira Sans&quot;; color: red"></div><p></p>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+
+        let div = dom.nodes_mut()[0].as_tag_mut().unwrap();
+        let style = div.attributes().style().unwrap();
+        assert_eq!(style.value("font-family"), Some("\"Fira Sans\""));
+
+        let attributes = div.attributes_mut();
+        attributes
+            .set_style_property("display", "none", false)
+            .unwrap();
+        assert_eq!(
+            attributes.remove_style_property("color").unwrap(),
+            Some(declaration("color", "red", false))
+        );
+        assert_eq!(attributes.remove_style_property("color").unwrap(), None);
+
+        // the attribute is replaced rather than duplicated
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(
+            dom.nodes()[0].outer_html(dom.parser()),
+            r#"<div style="font-family: &quot;Fira Sans&quot;; display: none;"></div>"#
+        );
+
+        let p = dom.nodes_mut()[1].as_tag_mut().unwrap();
+        assert!(p.attributes().style().is_none());
+        p.attributes_mut()
+            .set_style_property("color", "blue", true)
+            .unwrap();
+        assert_eq!(
+            dom.nodes()[1].outer_html(dom.parser()),
+            r#"<p style="color: blue !important;"></p>"#
+        );
+    }
+
+    #[test]
+    fn insert_replaces_attribute() {
+        let mut attributes = Attributes::new();
+        attributes.insert("style", Some("a: b"));
+        attributes.insert("style", Some("c: d"));
+
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes.style().unwrap().value("c"), Some("d"));
+    }
+
+    #[test]
+    fn parse_keeps_first_duplicate_attribute() {
+        let dom = parse(
+            r#"<a href="1" style="a: b" href="2" style="c: d"></a>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+        let attributes = dom.nodes()[0].as_tag().unwrap().attributes();
+
+        assert_eq!(attributes.get("href"), Some(Some(&"1".into())));
+        assert_eq!(attributes.style().unwrap().value("a"), Some("b"));
+    }
+}
+
+mod class_list {
+    use super::*;
+    use crate::errors::ClassListError;
+    use crate::VDom;
+
+    fn classes(dom: &VDom<'_>, class: &str) -> Vec<NodeHandle> {
+        let mut handles: Vec<_> = dom.get_elements_by_class_name(class).collect();
+        handles.sort();
+        handles
+    }
+
+    #[test]
+    fn edit() {
+        let mut dom = parse(
+            r#"<div class=" a  b a "></div><p></p>"#,
+            ParserOptions::default(),
+        )
+        .unwrap();
+        let div = dom.nodes_mut()[0].as_tag_mut().unwrap();
+        let mut list = div.attributes_mut().class_list_mut();
+
+        assert_eq!(list.tokens(), ["a", "b"]);
+        assert_eq!(list.len(), 2);
+        assert!(list.contains("b"));
+        assert!(!list.contains("c"));
+
+        // even a change that does nothing rewrites the attribute
+        assert_eq!(list.add("a"), Ok(false));
+        assert_eq!(list.add("c"), Ok(true));
+        assert_eq!(list.remove("b"), Ok(true));
+        assert_eq!(list.remove("b"), Ok(false));
+        assert_eq!(list.toggle("d"), Ok(true));
+        assert_eq!(list.toggle("a"), Ok(false));
+        assert_eq!(list.replace("x", "y"), Ok(false));
+        assert_eq!(list.replace("c", "e"), Ok(true));
+        assert_eq!(list.tokens(), ["e", "d"]);
+
+        assert_eq!(
+            dom.nodes()[0].outer_html(dom.parser()),
+            r#"<div class="e d"></div>"#
+        );
+
+        // removing the last class keeps an empty attribute, and an element without one does not get one
+        let p = dom.nodes_mut()[1].as_tag_mut().unwrap();
+        let mut list = p.attributes_mut().class_list_mut();
+        assert_eq!(list.remove("a"), Ok(false));
+        assert_eq!(list.toggle("a"), Ok(true));
+        assert_eq!(list.toggle("a"), Ok(false));
+        assert!(list.is_empty());
+        assert_eq!(
+            dom.nodes()[1].outer_html(dom.parser()),
+            r#"<p class=""></p>"#
+        );
+    }
+
+    #[test]
+    fn replace_order() {
+        let mut dom = parse(r#"<div class="a b c"></div>"#, ParserOptions::default()).unwrap();
+        let div = dom.nodes_mut()[0].as_tag_mut().unwrap();
+        let mut list = div.attributes_mut().class_list_mut();
+
+        assert_eq!(list.replace("c", "a"), Ok(true));
+        assert_eq!(list.tokens(), ["a", "b"]);
+        assert_eq!(list.replace("a", "b"), Ok(true));
+        assert_eq!(list.tokens(), ["b"]);
+        assert_eq!(list.replace("b", "b"), Ok(true));
+        assert_eq!(list.tokens(), ["b"]);
+    }
+
+    #[test]
+    fn invalid_tokens() {
+        let mut dom = parse(r#"<div class="a"></div>"#, ParserOptions::default()).unwrap();
+        let div = dom.nodes_mut()[0].as_tag_mut().unwrap();
+        let mut list = div.attributes_mut().class_list_mut();
+
+        assert_eq!(list.add(""), Err(ClassListError::EmptyToken));
+        assert_eq!(list.add("b c"), Err(ClassListError::InvalidCharacter));
+        assert_eq!(list.toggle("\"x"), Err(ClassListError::InvalidCharacter));
+        assert_eq!(
+            list.replace("a", "\t"),
+            Err(ClassListError::InvalidCharacter)
+        );
+        assert_eq!(list.tokens(), ["a"]);
+    }
+
+    #[test]
+    fn tracked_classes_stay_in_sync() {
+        let options = ParserOptions::default().track_classes();
+        let mut dom = parse(
+            r#"<div class="a a b"><p class="b">x</p><span>y</span></div>"#,
+            options,
+        )
+        .unwrap();
+
+        let div = dom.children()[0];
+        let span = dom.query_selector("span").unwrap().next().unwrap();
+        let p = dom.query_selector("p").unwrap().next().unwrap();
+
+        let mut list = dom.class_list_mut(div).unwrap();
+        list.remove("a").unwrap();
+        list.add("c").unwrap();
+        assert_eq!(classes(&dom, "a"), []);
+        assert_eq!(classes(&dom, "b"), [div, p]);
+        assert_eq!(classes(&dom, "c"), [div]);
+
+        dom.class_list_mut(span).unwrap().add("a").unwrap();
+        dom.class_list_mut(p).unwrap().replace("b", "c").unwrap();
+        dom.class_list_mut(div).unwrap().toggle("b").unwrap();
+        assert_eq!(classes(&dom, "a"), [span]);
+        assert_eq!(classes(&dom, "b"), []);
+        assert_eq!(classes(&dom, "c"), [div, p]);
+
+        // the tracked classes agree with a scan of the document
+        let untracked = parse(&dom.outer_html(), ParserOptions::default())
+            .unwrap()
+            .get_elements_by_class_name("c")
+            .count();
+        assert_eq!(untracked, 2);
+
+        // text nodes have no class list
+        let text = NodeHandle::new(2);
+        assert!(dom.class_list_mut(text).is_none());
+    }
+}
+
+mod tracking {
+    use super::*;
+    use crate::VDom;
+
+    const INPUT: &str = r#"<div id="a" class="x y x"><p id="b" class="y">1<img id="a" class="z"><span class="x">2</span></p><br class="y"><section id="c"><p class="z">3</p></section></div><ul id="b"><li class="x">4"#;
+    const IDS: [&str; 4] = ["a", "b", "c", "d"];
+    const CLASSES: [&str; 4] = ["x", "y", "z", "w"];
+
+    fn tracked(input: &str) -> VDom<'_> {
+        parse(input, ParserOptions::default().track_ids().track_classes()).unwrap()
+    }
+
+    /// Checks that the tracked lookups of `dom` agree with the lookups of an untracked parse of its HTML
+    fn assert_consistent(dom: &VDom<'_>) {
+        let html = dom.outer_html();
+        let untracked = parse(&html, ParserOptions::default()).unwrap();
+        assert_eq!(untracked.nodes().len(), dom.nodes().len(), "{html}");
+
+        for id in IDS {
+            assert_eq!(
+                dom.get_element_by_id(id),
+                untracked.get_element_by_id(id),
+                "id {id} in {html}"
+            );
+        }
+
+        for class in CLASSES {
+            assert_eq!(
+                dom.get_elements_by_class_name(class).collect::<Vec<_>>(),
+                untracked
+                    .get_elements_by_class_name(class)
+                    .collect::<Vec<_>>(),
+                "class {class} in {html}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_tracks_all_elements() {
+        let dom = tracked(INPUT);
+        assert_consistent(&dom);
+
+        // the void <img> and the unclosed <li> are tracked, and the first "a" in document order wins
+        assert_eq!(dom.get_element_by_id("a"), Some(NodeHandle::new(0)));
+        assert_eq!(dom.get_elements_by_class_name("z").count(), 2);
+        assert_eq!(dom.get_elements_by_class_name("x").count(), 3);
+
+        let dom = tracked(r#"<p id=a class=b></p><p id=a><b class=b></b><i class=b></i></p>"#);
+        assert_eq!(dom.get_element_by_id("a"), Some(NodeHandle::new(0)));
+        assert_eq!(
+            dom.get_elements_by_class_name("b").collect::<Vec<_>>(),
+            [0, 2, 3].map(NodeHandle::new)
+        );
+
+        for input in ["<img id=x>", "<div id=x>"] {
+            assert_eq!(
+                tracked(input).get_element_by_id("x"),
+                Some(NodeHandle::new(0))
+            );
+        }
+    }
+
+    #[test]
+    fn chunked_tracks_all_elements() {
+        let options = ParserOptions::default().track_ids().track_classes();
+        let mut parser = ChunkedParser::new(options).encoding_hint("utf-8");
+        for chunk in INPUT.as_bytes().chunks(7) {
+            parser.feed(chunk);
+        }
+
+        let dom = parser.finish().unwrap();
+        assert_consistent(dom.get_ref());
+    }
+
+    #[test]
+    fn update_attributes() {
+        let mut dom = tracked(INPUT);
+        let div = dom.get_element_by_id("a").unwrap();
+        let ul = dom.children()[1];
+
+        // the next element with the id takes over
+        assert!(dom.set_attribute(div, "id", Some("d")));
+        assert_eq!(dom.get_element_by_id("d"), Some(div));
+        assert_eq!(
+            dom.query_selector("img").unwrap().next(),
+            dom.get_element_by_id("a")
+        );
+
+        // and the first element in document order refers to it again
+        assert!(dom.set_attribute(div, "id", Some("a")));
+        assert_eq!(dom.get_element_by_id("a"), Some(div));
+        assert!(dom.set_attribute(div, "id", Some("d")));
+
+        assert!(dom.set_attribute(ul, "class", Some("w")));
+        assert_eq!(
+            dom.remove_attribute(div, "class"),
+            Some(Some("x y x".into()))
+        );
+        assert_eq!(
+            dom.get_elements_by_class_name("w").collect::<Vec<_>>(),
+            [ul]
+        );
+        assert_eq!(dom.get_elements_by_class_name("x").count(), 2);
+        assert_consistent(&dom);
+
+        // text nodes have no attributes
+        let text = dom.query_selector("span").unwrap().next().unwrap();
+        let text = text.get(dom.parser()).unwrap().children().unwrap().top()[0];
+        assert!(!dom.set_attribute(text, "id", Some("e")));
+        assert_eq!(dom.remove_attribute(text, "id"), None);
+    }
+
+    #[test]
+    fn reindex() {
+        let mut dom = tracked(INPUT);
+
+        for node in dom.nodes_mut() {
+            if let Some(tag) = node.as_tag_mut() {
+                tag.attributes_mut().insert("class", Some("w"));
+            }
+        }
+        assert_eq!(dom.get_elements_by_class_name("w").count(), 0);
+
+        dom.reindex();
+        assert_eq!(dom.get_elements_by_class_name("w").count(), 9);
+        assert_consistent(&dom);
+
+        // elements that are detached directly are tracked until they are removed
+        let ul = dom.children()[1];
+        dom.parser_mut().ast.pop();
+        dom.reindex();
+        assert_eq!(dom.get_elements_by_class_name("w").count(), 9);
+        assert!(dom.get_elements_by_class_name("w").any(|h| h == ul));
+
+        dom.compact();
+        assert_eq!(dom.get_elements_by_class_name("w").count(), 7);
+        assert_eq!(
+            dom.get_element_by_id("b"),
+            dom.query_selector("p").unwrap().next()
+        );
+        assert_consistent(&dom);
+    }
+
+    #[test]
+    fn fragment_untracks_replaced_elements() {
+        let input = r#"<div id="x"><p id="a" class="y"><b id="c" class="z"></b></p></div><p id="a"></p><p id="b" class="y"></p>"#;
+
+        for options in [
+            ParserOptions::default(),
+            ParserOptions::default().track_ids().track_classes(),
+        ] {
+            let mut dom = parse(input, options).unwrap();
+            let div = dom.get_element_by_id("x").unwrap();
+            let [old_p, old_b] = [1, 2].map(NodeHandle::new);
+            dom.parse_fragment(div, r#"<p id="b" class="z"></p>"#)
+                .unwrap();
+
+            // the second <p id="a"> takes over, and the earlier <p id="b"> keeps its id
+            assert_eq!(dom.get_element_by_id("a"), Some(dom.children()[1]));
+            assert_eq!(dom.get_element_by_id("b"), Some(dom.children()[2]));
+            assert_eq!(dom.get_element_by_id("c"), None);
+            assert_eq!(
+                dom.get_elements_by_class_name("y").collect::<Vec<_>>(),
+                [dom.children()[2]]
+            );
+            assert_eq!(
+                dom.get_elements_by_class_name("z").collect::<Vec<_>>(),
+                [NodeHandle::new(5)]
+            );
+
+            // replaced elements are not tracked again when they are changed
+            assert!(dom.set_attribute(old_b, "id", Some("d")));
+            dom.class_list_mut(old_p).unwrap().add("w").unwrap();
+            dom.reindex();
+            assert_eq!(dom.get_element_by_id("d"), None);
+            assert_eq!(dom.get_elements_by_class_name("w").count(), 0);
+
+            dom.compact();
+            assert_consistent(&dom);
+        }
+    }
+
+    #[test]
+    fn random_mutations() {
+        // xorshift, so that failures are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        let iterations = if cfg!(miri) { 20 } else { 500 };
+        let mut dom = tracked(INPUT);
+        let elements: Vec<NodeHandle> = dom.elements().map(|(handle, _)| handle).collect();
+
+        for _ in 0..iterations {
+            let element = elements[next(elements.len())];
+            let id = IDS[next(IDS.len())];
+            let class = CLASSES[next(CLASSES.len())];
+            let other = CLASSES[next(CLASSES.len())];
+
+            match next(8) {
+                0 => {
+                    dom.set_attribute(element, "id", Some(id));
+                }
+                1 => {
+                    dom.remove_attribute(element, "id");
+                }
+                2 => {
+                    let value = format!(" {class}  {other} {class}");
+                    dom.set_attribute(element, "class", Some(Bytes::try_from(value).unwrap()));
+                }
+                3 => {
+                    dom.remove_attribute(element, "class");
+                }
+                4 => {
+                    dom.class_list_mut(element).unwrap().add(class).unwrap();
+                }
+                5 => {
+                    dom.class_list_mut(element).unwrap().remove(class).unwrap();
+                }
+                6 => {
+                    dom.class_list_mut(element).unwrap().toggle(class).unwrap();
+                }
+                _ => {
+                    dom.class_list_mut(element)
+                        .unwrap()
+                        .replace(class, other)
+                        .unwrap();
+                }
+            }
+
+            assert_consistent(&dom);
+        }
+
+        dom.reindex();
+        assert_consistent(&dom);
+    }
+}
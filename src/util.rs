@@ -29,3 +29,8 @@ pub fn to_lower(byte: u8) -> u8 {
     let lower = is_upper * 0x20;
     byte + lower
 }
+
+/// Trims the text and replaces every sequence of ASCII whitespace with a single space
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
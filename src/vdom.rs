@@ -1,6 +1,11 @@
 use crate::errors::ParseError;
+use crate::links::{self, BaseUrl, Link};
+use crate::parser::constants;
 use crate::parser::HTMLVersion;
 use crate::parser::NodeHandle;
+use crate::parser::RawChildren;
+use crate::parser::{Attributes, ClassList};
+use crate::parser::{Descendants, Elements, TextNodes, Traverse};
 use crate::queryselector;
 use crate::queryselector::QuerySelectorIterator;
 use crate::Bytes;
@@ -8,6 +13,7 @@ use crate::InnerNodeHandle;
 use crate::ParserOptions;
 use crate::{Node, Parser};
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 /// VDom represents a [Document Object Model](https://developer.mozilla.org/en/docs/Web/API/Document_Object_Model)
 ///
@@ -40,6 +46,12 @@ impl<'a> VDom<'a> {
     }
 
     /// Finds an element by its `id` attribute.
+    ///
+    /// If several elements have the id, the first one in [`VDom::nodes()`] is returned, whether ids are tracked or not.
+    /// Elements that were removed by [`VDom::parse_fragment()`] are not found.
+    ///
+    /// When ids are tracked, changes to `id` attributes must be made through [`VDom::update_attributes()`]
+    /// or be followed by a call to [`VDom::reindex()`].
     pub fn get_element_by_id<'b, S>(&'b self, id: S) -> Option<NodeHandle>
     where
         S: Into<Bytes<'a>>,
@@ -48,21 +60,29 @@ impl<'a> VDom<'a> {
         let parser = self.parser();
 
         if parser.options.is_tracking_ids() {
-            parser.ids.get(&bytes).copied()
+            parser
+                .ids
+                .get(&bytes)
+                .and_then(|handles| handles.as_slice().first())
+                .copied()
         } else {
             self.nodes()
                 .iter()
                 .enumerate()
-                .find(|(_, node)| {
+                .map(|(id, node)| (NodeHandle::new(id as InnerNodeHandle), node))
+                .find(|(handle, node)| {
                     node.as_tag().is_some_and(|tag| {
                         tag._attributes.id.as_ref().is_some_and(|x| x.eq(&bytes))
-                    })
+                    }) && !parser.replaced.contains(handle)
                 })
-                .map(|(id, _)| NodeHandle::new(id as InnerNodeHandle))
+                .map(|(handle, _)| handle)
         }
     }
 
     /// Returns a list of elements that match a given class name.
+    ///
+    /// The elements are returned in the order of [`VDom::nodes()`], whether classes are tracked or not.
+    /// Elements that were removed by [`VDom::parse_fragment()`] are skipped.
     pub fn get_elements_by_class_name<'b>(
         &'b self,
         id: &'b str,
@@ -83,10 +103,12 @@ impl<'a> VDom<'a> {
                 .iter()
                 .enumerate()
                 .filter_map(move |(id, node)| {
+                    let handle = NodeHandle::new(id as InnerNodeHandle);
+
                     node.as_tag().and_then(|tag| {
-                        tag._attributes
-                            .is_class_member(member)
-                            .then(|| NodeHandle::new(id as InnerNodeHandle))
+                        (tag._attributes.is_class_member(member)
+                            && !parser.replaced.contains(&handle))
+                        .then_some(handle)
                     })
                 });
 
@@ -118,6 +140,71 @@ impl<'a> VDom<'a> {
         &self.parser.ast
     }
 
+    /// Returns an iterator over the [`Edge`](crate::Edge)s of all nodes in this DOM
+    ///
+    /// Topmost nodes have a depth of 0. See [`NodeHandle::traverse()`].
+    ///
+    /// # Example
+    /// ```
+    /// use tl::Edge;
+    ///
+    /// let dom = tl::parse("<div><p>deep</p></div><p>shallow</p>", Default::default()).unwrap();
+    /// let mut text = Vec::new();
+    /// let mut edges = dom.traverse();
+    ///
+    /// while let Some((edge, _)) = edges.next() {
+    ///     if let Edge::Open(handle) = edge {
+    ///         match handle.get(dom.parser()).unwrap() {
+    ///             tl::Node::Tag(tag) if tag.name() == "div" => edges.skip_subtree(),
+    ///             tl::Node::Raw(raw) => text.push(raw.as_utf8_str()),
+    ///             _ => {}
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(text, ["shallow"]);
+    /// ```
+    pub fn traverse(&self) -> Traverse<'_, 'a> {
+        Traverse::new(&self.parser, &self.parser.ast, 0)
+    }
+
+    /// Returns an iterator over all nodes in this DOM in document order
+    ///
+    /// Unlike [`VDom::nodes()`], this follows the tree structure, so it only yields nodes that are reachable from the topmost nodes.
+    /// Topmost nodes have a depth of 0.
+    pub fn descendants(&self) -> Descendants<'_, 'a> {
+        Descendants::new(&self.parser, &self.parser.ast, 0)
+    }
+
+    /// Returns an iterator over all tags in this DOM in document order, see [`VDom::descendants()`]
+    ///
+    /// # Example
+    /// ```
+    /// let dom = tl::parse("<ul><li><a>a</a></li><li><a>b</a></li></ul>", Default::default()).unwrap();
+    ///
+    /// let mut elements = dom.elements();
+    /// let mut names = Vec::new();
+    ///
+    /// while let Some((handle, depth)) = elements.next() {
+    ///     let tag = handle.get(dom.parser()).unwrap().as_tag().unwrap();
+    ///     names.push((tag.name().as_utf8_str(), depth));
+    ///
+    ///     if tag.name() == "li" {
+    ///         elements.skip_subtree();
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(names, [("ul".into(), 0), ("li".into(), 1), ("li".into(), 1)]);
+    /// ```
+    pub fn elements(&self) -> Elements<'_, 'a> {
+        Elements(self.descendants())
+    }
+
+    /// Returns an iterator over all text nodes in this DOM in document order, see [`VDom::descendants()`]
+    pub fn text_nodes(&self) -> TextNodes<'_, 'a> {
+        TextNodes(self.descendants())
+    }
+
     /// Returns a mutable reference to the topmost subnodes ("children") of this DOM
     pub fn children_mut(&mut self) -> &mut [NodeHandle] {
         &mut self.parser.ast
@@ -129,6 +216,83 @@ impl<'a> VDom<'a> {
         self.parser.version
     }
 
+    /// Returns the name of the character encoding that the document was decoded from, such as `UTF-8` or `windows-1252`.
+    ///
+    /// This is only set for documents that were parsed from bytes using [`crate::parse_bytes()`].
+    pub fn encoding(&self) -> Option<&'static str> {
+        self.parser.encoding
+    }
+
+    /// Returns the base URL of this document, which the URLs in it are resolved against
+    ///
+    /// The base URL is the `href` of the first `<base>` element that has one, resolved against `document_url`,
+    /// or `document_url` if there is no such element.
+    ///
+    /// # Example
+    /// ```
+    /// let dom = tl::parse(r#"<base href="/docs/"><a href="a.html">A</a><a href="b.html">B</a>"#, Default::default()).unwrap();
+    /// let base = dom.base_url("https://example.com/index.html");
+    ///
+    /// assert_eq!(base.as_str(), "https://example.com/docs/");
+    /// assert_eq!(base.resolve("b.html").as_deref(), Some("https://example.com/docs/b.html"));
+    /// ```
+    pub fn base_url(&self, document_url: &str) -> BaseUrl {
+        BaseUrl(links::base_url(self, document_url))
+    }
+
+    /// Resolves a URL from an attribute value against the base URL of this document
+    ///
+    /// The base URL is looked up on every call, see [`VDom::base_url()`].
+    /// To resolve many URLs, look it up once and use [`BaseUrl::resolve()`] instead.
+    /// `value` is used as is, so character references have to be decoded beforehand.
+    ///
+    /// With the `url` feature, URLs are joined as specified by the WHATWG URL Standard,
+    /// and `None` is returned if `value` is not a valid URL.
+    /// Otherwise, the reference resolution algorithm of RFC 3986 is used and this always returns `Some`.
+    ///
+    /// # Example
+    /// ```
+    /// let dom = tl::parse(r#"<base href="/docs/"><a href="intro.html">Intro</a>"#, Default::default()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     dom.resolve_url("intro.html", "https://example.com/index.html").as_deref(),
+    ///     Some("https://example.com/docs/intro.html")
+    /// );
+    /// ```
+    pub fn resolve_url(&self, value: &str, document_url: &str) -> Option<String> {
+        self.base_url(document_url).resolve(value)
+    }
+
+    /// Returns the URLs in the `href`, `src`, `srcset`, `imagesrcset`, `action` and `poster` attributes of all elements in document order,
+    /// resolved against the base URL of this document
+    ///
+    /// Every image candidate of a `srcset` is a separate [`Link`], see [`parse_srcset()`](crate::srcset::parse_srcset).
+    /// The `href` of `<base>` elements is not included.
+    /// Character references in the attribute values are decoded before resolving them, see [`VDom::resolve_url()`].
+    ///
+    /// # Example
+    /// ```
+    /// let dom = tl::parse(
+    ///     r#"<a href="/about">About</a><img src="a.png" srcset="a-2x.png 2x, a-3x.png 3x">"#,
+    ///     Default::default()
+    /// ).unwrap();
+    ///
+    /// let urls: Vec<_> = dom.links("https://example.com/blog/")
+    ///     .into_iter()
+    ///     .map(|link| (link.attribute, link.url))
+    ///     .collect();
+    ///
+    /// assert_eq!(urls, [
+    ///     ("href", "https://example.com/about".to_string()),
+    ///     ("src", "https://example.com/blog/a.png".to_string()),
+    ///     ("srcset", "https://example.com/blog/a-2x.png".to_string()),
+    ///     ("srcset", "https://example.com/blog/a-3x.png".to_string()),
+    /// ]);
+    /// ```
+    pub fn links(&self, document_url: &str) -> Vec<Link> {
+        links::links(self, document_url)
+    }
+
     /// Returns the contained markup of all of the elements in this DOM.
     ///
     /// Equivalent to [Element#outerHTML](https://developer.mozilla.org/en-US/docs/Web/API/Element/outerHTML) in browsers)
@@ -150,7 +314,7 @@ impl<'a> VDom<'a> {
     /// assert_eq!(dom.outer_html(), r#"<div><p href="/" id="find-me">Hello world</p></div>"#);
     /// ```
     pub fn outer_html(&self) -> String {
-        let mut inner_html = String::with_capacity(self.parser.stream.len());
+        let mut inner_html = String::with_capacity(self.parser.tokenizer.input().len());
 
         for node in self.children() {
             let node = node.get(&self.parser).unwrap();
@@ -160,6 +324,261 @@ impl<'a> VDom<'a> {
         inner_html
     }
 
+    /// Parses `html` in the context of the given element and replaces the children of that element with the parsed nodes.
+    ///
+    /// The new nodes are appended to this DOM, and the handles of the topmost new nodes are returned.
+    /// The fragment is parsed with the same rules as [`crate::parse()`], so the new children are the same
+    /// as the children of the element in a document where `html` is its content:
+    ///
+    /// - If the context element is a raw text element (such as `<script>`, `<style>` or `<textarea>`),
+    ///   the markup is not parsed and becomes a single text node instead.
+    /// - Like everywhere else in this crate, end tags are never implied and no elements are inserted,
+    ///   so a context such as `<table>`, `<select>` or `<ul>` does not change how the fragment is parsed.
+    ///   For example, `<tr>` is not wrapped in a `<tbody>` and `<li>a<li>b` is parsed as nested items.
+    ///
+    /// Similar to setting [Element#innerHTML](https://developer.mozilla.org/en-US/docs/Web/API/Element/innerHTML) in browsers.
+    ///
+    /// **Note:** The previous children of the element are detached but stay in the node table,
+    /// which means that they are still yielded by [`VDom::nodes()`] and matched by queries on this DOM.
+    /// They are no longer found by [`VDom::get_element_by_id()`] and [`VDom::get_elements_by_class_name()`].
+    /// Call [`VDom::compact()`] to remove them, for example after replacing the contents of many elements.
+    /// Nodes of the fragment do not have meaningful [`HTMLTag::boundaries()`] since they are not part of the original input.
+    ///
+    /// # Errors
+    /// Returns [`ParseError::InvalidContext`] if the context node is not an HTML element,
+    /// and [`ParseError::InvalidLength`] under the same conditions as [`crate::parse()`].
+    ///
+    /// # Example
+    /// ```
+    /// let mut dom = tl::parse(r#"<ul id="list"><li>old</li></ul>"#, Default::default()).unwrap();
+    /// let list = dom.get_element_by_id("list").unwrap();
+    ///
+    /// let handles = dom.parse_fragment(list, "<li>one</li><li>two</li>").unwrap();
+    ///
+    /// assert_eq!(handles.len(), 2);
+    /// assert_eq!(dom.outer_html(), r#"<ul id="list"><li>one</li><li>two</li></ul>"#);
+    /// ```
+    pub fn parse_fragment(
+        &mut self,
+        context: NodeHandle,
+        html: &'a str,
+    ) -> Result<Vec<NodeHandle>, ParseError> {
+        let context_tag = context
+            .get(&self.parser)
+            .and_then(Node::as_tag)
+            .ok_or(ParseError::InvalidContext)?;
+
+        let is_raw_text = constants::RAW_TEXT_TAGS
+            .iter()
+            .any(|t| context_tag.name().as_bytes().eq_ignore_ascii_case(t));
+
+        let old_children = context_tag._children.to_vec();
+        let mut fragment = Parser::new(html, self.parser.options);
+
+        if is_raw_text {
+            fragment.tags.push(Node::Raw(html.into()));
+            fragment.ast.push(NodeHandle::new(0));
+        } else {
+            fragment.parse()?;
+        }
+
+        for child in old_children {
+            self.parser.untrack_subtree(child);
+        }
+
+        let handles = self.parser.adopt(fragment);
+
+        let mut children = RawChildren::new();
+        for &handle in &handles {
+            children.push(handle);
+        }
+
+        if let Some(tag) = context.get_mut(&mut self.parser).and_then(Node::as_tag_mut) {
+            tag._children = children;
+        }
+
+        Ok(handles)
+    }
+
+    /// Copies the subtree rooted at `handle` from another DOM into this DOM and returns the handle of the copied root.
+    ///
+    /// The copied nodes own their data, so they do not borrow from the source of `other`,
+    /// and their ids and classes are added to the tracking tables of this DOM (if enabled).
+    /// If an id is already tracked in this DOM, it keeps referring to the existing element.
+    ///
+    /// The copy is detached: it is not a child of any node until it is inserted somewhere,
+    /// for example by pushing the handle to [`HTMLTag::children_mut()`].
+    ///
+    /// Returns `None` if `handle` does not belong to `other`.
+    ///
+    /// # Example
+    /// ```
+    /// let mut page = tl::parse(r#"<main id="main"></main>"#, Default::default()).unwrap();
+    /// let partial = tl::parse(r#"<nav><a href="/">Home</a></nav>"#, Default::default()).unwrap();
+    ///
+    /// let nav = page.import_subtree(&partial, partial.children()[0]).unwrap();
+    ///
+    /// let main = page.get_element_by_id("main").unwrap();
+    /// main.get_mut(page.parser_mut())
+    ///     .unwrap()
+    ///     .as_tag_mut()
+    ///     .unwrap()
+    ///     .children_mut()
+    ///     .top_mut()
+    ///     .push(nav);
+    ///
+    /// assert_eq!(page.outer_html(), r#"<main id="main"><nav><a href="/">Home</a></nav></main>"#);
+    /// ```
+    pub fn import_subtree(&mut self, other: &VDom<'_>, handle: NodeHandle) -> Option<NodeHandle> {
+        self.parser.import(&other.parser, handle)
+    }
+
+    /// Calls `f` with the attributes of the element with the given handle and returns its result.
+    ///
+    /// Changes to the `id` and `class` attributes are reflected in the tracking tables (if enabled),
+    /// so [`VDom::get_element_by_id()`] and [`VDom::get_elements_by_class_name()`] stay correct.
+    /// Like the lookups without tracking, this applies to every element in [`VDom::nodes()`], whether it is attached or not,
+    /// except for the elements that were removed by [`VDom::parse_fragment()`].
+    /// Attributes that are changed through [`VDom::nodes_mut()`] or [`VDom::parser_mut()`] are not tracked;
+    /// call [`VDom::reindex()`] after such changes.
+    ///
+    /// Returns `None` if `handle` does not refer to an element.
+    ///
+    /// # Example
+    /// ```
+    /// let options = tl::ParserOptions::default().track_ids();
+    /// let mut dom = tl::parse(r#"<p id="a">1</p><p id="b">2</p>"#, options).unwrap();
+    /// let first = dom.children()[0];
+    ///
+    /// dom.update_attributes(first, |attributes| attributes.insert("id", Some("b")));
+    ///
+    /// assert_eq!(dom.get_element_by_id("a"), None);
+    /// assert_eq!(dom.get_element_by_id("b"), Some(first));
+    /// ```
+    pub fn update_attributes<R, F>(&mut self, handle: NodeHandle, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Attributes<'a>) -> R,
+    {
+        self.parser.update_attributes(handle, f)
+    }
+
+    /// Sets an attribute of the element with the given handle, and keeps the tracking tables in sync.
+    ///
+    /// The value is not escaped. Returns `false` if `handle` does not refer to an element.
+    /// See [`VDom::update_attributes()`].
+    pub fn set_attribute<K, V>(&mut self, handle: NodeHandle, key: K, value: Option<V>) -> bool
+    where
+        K: Into<Bytes<'a>>,
+        V: Into<Bytes<'a>>,
+    {
+        self.update_attributes(handle, |attributes| attributes.insert(key, value))
+            .is_some()
+    }
+
+    /// Removes an attribute of the element with the given handle, and keeps the tracking tables in sync.
+    ///
+    /// Returns the removed value, like [`Attributes::remove()`]. See [`VDom::update_attributes()`].
+    pub fn remove_attribute<B>(&mut self, handle: NodeHandle, key: B) -> Option<Option<Bytes<'a>>>
+    where
+        B: Into<Bytes<'a>>,
+    {
+        self.update_attributes(handle, |attributes| attributes.remove(key))
+            .flatten()
+    }
+
+    /// Rebuilds the id and class tracking tables (if enabled) from all elements in [`VDom::nodes()`].
+    ///
+    /// Like the lookups without tracking, this includes detached elements, except for the ones that were removed
+    /// by [`VDom::parse_fragment()`].
+    /// This is needed after changing `id` or `class` attributes directly, for example through [`VDom::nodes_mut()`].
+    ///
+    /// # Example
+    /// ```
+    /// let options = tl::ParserOptions::default().track_ids();
+    /// let mut dom = tl::parse(r#"<p id="a"></p>"#, options).unwrap();
+    ///
+    /// let p = dom.nodes_mut()[0].as_tag_mut().unwrap();
+    /// p.attributes_mut().insert("id", Some("b"));
+    /// assert!(dom.get_element_by_id("b").is_none());
+    ///
+    /// dom.reindex();
+    /// assert!(dom.get_element_by_id("b").is_some());
+    /// ```
+    pub fn reindex(&mut self) {
+        self.parser.reindex();
+    }
+
+    /// Returns a mutable view of the class names of the element with the given handle, see [`ClassList`].
+    ///
+    /// Unlike [`Attributes::class_list_mut()`](crate::Attributes::class_list_mut), changes made through it are reflected
+    /// in the class tracking table (if enabled), so [`VDom::get_elements_by_class_name()`] keeps finding the right elements.
+    ///
+    /// Returns `None` if `handle` does not refer to an element.
+    ///
+    /// # Example
+    /// ```
+    /// let options = tl::ParserOptions::default().track_classes();
+    /// let mut dom = tl::parse(r#"<p class="a">1</p><p class="b">2</p>"#, options).unwrap();
+    /// let first = dom.children()[0];
+    ///
+    /// let mut classes = dom.class_list_mut(first).unwrap();
+    /// classes.replace("a", "b").unwrap();
+    /// classes.add("c").unwrap();
+    ///
+    /// assert_eq!(dom.get_elements_by_class_name("a").count(), 0);
+    /// assert_eq!(dom.get_elements_by_class_name("b").count(), 2);
+    /// assert_eq!(dom.outer_html(), r#"<p class="b c">1</p><p class="b">2</p>"#);
+    /// ```
+    pub fn class_list_mut(&mut self, handle: NodeHandle) -> Option<ClassList<'_, 'a>> {
+        let parser = &mut self.parser;
+        let tag = parser
+            .tags
+            .get_mut(handle.get_inner() as usize)
+            .and_then(Node::as_tag_mut)?;
+
+        // elements that were removed by `parse_fragment` are not tracked
+        let index = (parser.options.is_tracking_classes() && !parser.replaced.contains(&handle))
+            .then_some((handle, &mut parser.classes));
+
+        Some(ClassList::new(&mut tag._attributes.class, index))
+    }
+
+    /// Removes all nodes that are no longer reachable from [`VDom::children()`] and renumbers the remaining nodes.
+    ///
+    /// Nodes that are removed from the children of an element (or from the topmost nodes) stay in the node table,
+    /// so they are still yielded by [`VDom::nodes()`] and matched by queries on this DOM.
+    /// After compacting, the node table only contains reachable nodes, in document order,
+    /// which also makes [`Children::all()`](crate::Children::all) and [`Children::boundaries()`](crate::Children::boundaries) correct again.
+    ///
+    /// All handles obtained before calling this function are invalidated.
+    /// The returned table maps every old handle (by its inner index) to its new handle, or `None` if the node was removed.
+    ///
+    /// # Example
+    /// ```
+    /// let mut dom = tl::parse("<div><p>a</p><p>b</p></div>", Default::default()).unwrap();
+    /// let div = dom.children()[0];
+    ///
+    /// // detach the first paragraph
+    /// let removed = div.get_mut(dom.parser_mut())
+    ///     .unwrap()
+    ///     .as_tag_mut()
+    ///     .unwrap()
+    ///     .children_mut()
+    ///     .top_mut()
+    ///     .remove(0);
+    ///
+    /// assert_eq!(dom.query_selector("p").unwrap().count(), 2);
+    ///
+    /// let remap = dom.compact();
+    ///
+    /// assert_eq!(remap[removed.get_inner() as usize], None);
+    /// assert_eq!(dom.query_selector("p").unwrap().count(), 1);
+    /// assert_eq!(dom.nodes().len(), 3);
+    /// ```
+    pub fn compact(&mut self) -> Vec<Option<NodeHandle>> {
+        self.parser.compact()
+    }
+
     /// Tries to parse the query selector and returns an iterator over elements that match the given query selector.
     ///
     /// # Example
@@ -179,10 +598,123 @@ impl<'a> VDom<'a> {
     }
 }
 
+/// An owned [`VDom`] that keeps its own input string alive
+///
+/// Unlike [`VDom`], this type does not borrow from anything, so it can be stored in caches,
+/// sent across threads and moved into async tasks.
+/// The input is stored in an [`Arc<str>`], which means that creating an `OwnedVDom` from an existing `Arc<str>` does not copy it.
+///
+/// # Example
+/// ```
+/// use tl::{OwnedVDom, ParserOptions};
+///
+/// let mut dom = OwnedVDom::parse(String::from("<p>Hello</p>"), ParserOptions::default()).unwrap();
+///
+/// dom.with_mut(|dom| {
+///     let p = dom.query_selector("p").unwrap().next().unwrap();
+///     let tag = p.get_mut(dom.parser_mut()).unwrap().as_tag_mut().unwrap();
+///     tag.attributes_mut().insert("id", Some("greeting"));
+/// });
+///
+/// let dom = std::thread::spawn(move || dom).join().unwrap();
+/// assert_eq!(dom.get_ref().outer_html(), r#"<p id="greeting">Hello</p>"#);
+/// ```
+#[derive(Debug)]
+pub struct OwnedVDom {
+    /// The parsed DOM, which borrows from `source`
+    ///
+    /// The `'static` lifetime is not real and must never be exposed:
+    /// the DOM is only handed out with a lifetime that is bound to `self`.
+    /// This field is declared before `source` so that it is dropped first.
+    dom: VDom<'static>,
+    /// The input string
+    source: Arc<str>,
+}
+
+// SAFETY: `VDom` is neither `Send` nor `Sync` only because `Bytes` stores raw pointers.
+// These pointers either point into `source`, which is immutable and owned by this struct,
+// or to an allocation that is exclusively owned by the `Bytes` value.
+// Mutation requires `&mut self`, so the usual borrowing rules apply.
+unsafe impl Send for OwnedVDom {}
+unsafe impl Sync for OwnedVDom {}
+
+impl OwnedVDom {
+    /// Parses the given input string into an owned DOM
+    ///
+    /// # Errors
+    /// See [`crate::parse()`]
+    pub fn parse<S: Into<Arc<str>>>(input: S, options: ParserOptions) -> Result<Self, ParseError> {
+        let source: Arc<str> = input.into();
+
+        // SAFETY: the string is stored on the heap and is never mutated or moved while `source` is alive,
+        // which is at least as long as the DOM, since the DOM is dropped first and never handed out with this lifetime
+        let input: &'static str = unsafe { &*Arc::as_ptr(&source) };
+
+        let mut parser = Parser::new(input, options);
+        parser.parse()?;
+
+        Ok(Self {
+            dom: VDom::from(parser),
+            source,
+        })
+    }
+
+    /// Wraps a parser whose nodes do not borrow from any input, such as one that was built from deserialized nodes
+    ///
+    /// The source of the returned DOM is empty.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_parser(parser: Parser<'static>) -> Self {
+        Self::from_parts(parser, Arc::from(""))
+    }
+
+    /// Wraps a parser whose nodes either own their data or borrow from `source`
+    pub(crate) fn from_parts(parser: Parser<'static>, source: Arc<str>) -> Self {
+        Self {
+            dom: VDom::from(parser),
+            source,
+        }
+    }
+
+    /// Returns the input string that this DOM was parsed from
+    pub fn source(&self) -> &Arc<str> {
+        &self.source
+    }
+
+    /// Returns a reference to the inner DOM
+    ///
+    /// The lifetime of the returned `VDom` is bound to self so that elements cannot outlive this `OwnedVDom`.
+    pub fn get_ref<'s>(&'s self) -> &'s VDom<'s> {
+        &self.dom
+    }
+
+    /// Calls the given closure with a mutable reference to the inner DOM and returns its result
+    ///
+    /// A closure is used instead of returning `&mut VDom` because mutable references are invariant over the lifetime of the DOM.
+    /// The closure must work for any lifetime, which prevents both moving borrowed data out of the DOM
+    /// and storing data in it that does not live as long as the DOM. Owned and `'static` data can be stored as usual.
+    ///
+    /// ```compile_fail
+    /// let mut dom = tl::OwnedVDom::parse("<p>Hello</p>", Default::default()).unwrap();
+    /// let text = String::from("borrowed");
+    ///
+    /// dom.with_mut(|dom| {
+    ///     // `text` does not live as long as the DOM
+    ///     dom.nodes_mut()[1] = tl::Node::Raw(text.as_str().into());
+    /// });
+    /// ```
+    pub fn with_mut<R, F>(&mut self, f: F) -> R
+    where
+        F: for<'s> FnOnce(&mut VDom<'s>) -> R,
+    {
+        f(&mut self.dom)
+    }
+}
+
 /// A RAII guarded version of VDom
 ///
 /// The input string is freed once this struct goes out of scope.
 /// The only way to construct this is by calling `parse_owned()`.
+#[deprecated(note = "use `OwnedVDom` instead")]
 #[derive(Debug)]
 pub struct VDomGuard {
     /// Wrapped VDom instance
@@ -193,9 +725,12 @@ pub struct VDomGuard {
     _phantom: PhantomData<&'static str>,
 }
 
+#[allow(deprecated)]
 unsafe impl Send for VDomGuard {}
+#[allow(deprecated)]
 unsafe impl Sync for VDomGuard {}
 
+#[allow(deprecated)]
 impl VDomGuard {
     /// Parses the input string
     pub(crate) fn parse(input: String, options: ParserOptions) -> Result<VDomGuard, ParseError> {
@@ -221,6 +756,7 @@ impl VDomGuard {
     }
 }
 
+#[allow(deprecated)]
 impl VDomGuard {
     /// Returns a reference to the inner DOM.
     ///
@@ -0,0 +1,799 @@
+use super::parser::{Axis, Expr, Function, NodeTest, Operator, Step};
+use crate::errors::XPathError;
+use crate::{entities, Node, NodeHandle, Parser, VDom};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A node in the XPath data model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum XNode {
+    /// The document itself, whose children are the topmost nodes
+    Root,
+    /// A node in the tree
+    Node(NodeHandle),
+    /// The attribute of an element at the given index of [`Attributes::iter()`](crate::Attributes::iter)
+    Attribute(NodeHandle, usize),
+}
+
+/// The result of evaluating an expression
+#[derive(Debug, Clone)]
+pub(crate) enum Val {
+    Nodes(Vec<XNode>),
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+
+/// The context in which an expression is evaluated
+#[derive(Clone, Copy)]
+struct Context {
+    node: XNode,
+    position: usize,
+    size: usize,
+}
+
+/// Evaluates expressions against a document
+pub(crate) struct Evaluator<'p, 'a> {
+    dom: &'p VDom<'a>,
+    /// The parent of every node that is reachable from the root
+    parents: HashMap<NodeHandle, XNode>,
+    /// The position of every node that is reachable from the root in document order
+    order: HashMap<NodeHandle, usize>,
+}
+
+impl<'p, 'a> Evaluator<'p, 'a> {
+    pub(crate) fn new(dom: &'p VDom<'a>) -> Self {
+        let mut parents = HashMap::new();
+        let mut order = HashMap::new();
+
+        for &handle in dom.children() {
+            parents.insert(handle, XNode::Root);
+        }
+
+        for (index, (handle, _)) in dom.descendants().enumerate() {
+            order.entry(handle).or_insert(index);
+
+            if let Some(tag) = handle.get(dom.parser()).and_then(Node::as_tag) {
+                for &child in tag.children().top().iter() {
+                    parents.entry(child).or_insert(XNode::Node(handle));
+                }
+            }
+        }
+
+        Self {
+            dom,
+            parents,
+            order,
+        }
+    }
+
+    /// Creates an evaluator that can only compute string-values and names, which does not need to walk the document first
+    pub(crate) fn unindexed(dom: &'p VDom<'a>) -> Self {
+        Self {
+            dom,
+            parents: HashMap::new(),
+            order: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    fn parser(&self) -> &'p Parser<'a> {
+        self.dom.parser()
+    }
+
+    pub(crate) fn evaluate(&self, expr: &Expr, node: XNode) -> Result<Val, XPathError> {
+        let context = Context {
+            node,
+            position: 1,
+            size: 1,
+        };
+
+        self.eval(expr, context)
+    }
+
+    /// Returns a key that sorts nodes in document order
+    fn sort_key(&self, node: XNode) -> (usize, usize) {
+        let position = |handle: NodeHandle| {
+            self.order
+                .get(&handle)
+                .map_or(usize::MAX, |&index| index + 1)
+        };
+
+        match node {
+            XNode::Root => (0, 0),
+            XNode::Node(handle) => (position(handle), 0),
+            XNode::Attribute(handle, index) => (position(handle), index + 1),
+        }
+    }
+
+    /// Sorts nodes in document order and removes duplicates
+    fn sort(&self, nodes: &mut Vec<XNode>) {
+        nodes.sort_by_cached_key(|&node| (self.sort_key(node), node_index(node)));
+        nodes.dedup();
+    }
+
+    fn children(&self, node: XNode) -> &'p [NodeHandle] {
+        match node {
+            XNode::Root => self.dom.children(),
+            XNode::Node(handle) => handle
+                .get(self.parser())
+                .and_then(Node::as_tag)
+                .map(|tag| tag.children().top().as_slice())
+                .unwrap_or(&[]),
+            XNode::Attribute(..) => &[],
+        }
+    }
+
+    fn parent(&self, node: XNode) -> Option<XNode> {
+        match node {
+            XNode::Root => None,
+            XNode::Node(handle) => self.parents.get(&handle).copied(),
+            XNode::Attribute(handle, _) => Some(XNode::Node(handle)),
+        }
+    }
+
+    /// Appends all descendants of a node in document order
+    fn descendants(&self, node: XNode, out: &mut Vec<XNode>) {
+        let mut stack: Vec<NodeHandle> = self.children(node).iter().rev().copied().collect();
+
+        while let Some(handle) = stack.pop() {
+            out.push(XNode::Node(handle));
+            stack.extend(self.children(XNode::Node(handle)).iter().rev());
+        }
+    }
+
+    /// Returns the siblings of a node before and after it
+    fn siblings(&self, node: XNode) -> (&'p [NodeHandle], &'p [NodeHandle]) {
+        let (XNode::Node(handle), Some(parent)) = (node, self.parent(node)) else {
+            return (&[], &[]);
+        };
+
+        let siblings = self.children(parent);
+
+        match siblings.iter().position(|&sibling| sibling == handle) {
+            Some(index) => (&siblings[..index], &siblings[index + 1..]),
+            None => (&[], &[]),
+        }
+    }
+
+    /// Returns the nodes on an axis, in reverse document order for reverse axes
+    fn axis(&self, axis: Axis, node: XNode) -> Vec<XNode> {
+        let mut out = Vec::new();
+
+        match axis {
+            Axis::Itself => out.push(node),
+            Axis::Child => out.extend(self.children(node).iter().map(|&h| XNode::Node(h))),
+            Axis::Descendant => self.descendants(node, &mut out),
+            Axis::DescendantOrSelf => {
+                out.push(node);
+                self.descendants(node, &mut out);
+            }
+            Axis::Parent => out.extend(self.parent(node)),
+            Axis::Ancestor | Axis::AncestorOrSelf => {
+                if axis == Axis::AncestorOrSelf {
+                    out.push(node);
+                }
+
+                let mut current = node;
+                while let Some(parent) = self.parent(current) {
+                    out.push(parent);
+                    current = parent;
+                }
+            }
+            Axis::FollowingSibling => {
+                out.extend(self.siblings(node).1.iter().map(|&h| XNode::Node(h)));
+            }
+            Axis::PrecedingSibling => {
+                out.extend(self.siblings(node).0.iter().rev().map(|&h| XNode::Node(h)));
+            }
+            Axis::Following => {
+                let mut current = node;
+
+                if let XNode::Attribute(handle, _) = node {
+                    // the descendants of the element that an attribute belongs to follow the attribute
+                    current = XNode::Node(handle);
+                    self.descendants(current, &mut out);
+                }
+
+                loop {
+                    for &sibling in self.siblings(current).1 {
+                        out.push(XNode::Node(sibling));
+                        self.descendants(XNode::Node(sibling), &mut out);
+                    }
+
+                    match self.parent(current) {
+                        Some(parent) => current = parent,
+                        None => break,
+                    }
+                }
+            }
+            Axis::Preceding => {
+                let mut current = match node {
+                    XNode::Attribute(handle, _) => XNode::Node(handle),
+                    node => node,
+                };
+
+                loop {
+                    for &sibling in self.siblings(current).0.iter().rev() {
+                        let start = out.len();
+                        out.push(XNode::Node(sibling));
+                        self.descendants(XNode::Node(sibling), &mut out);
+                        out[start..].reverse();
+                    }
+
+                    match self.parent(current) {
+                        Some(parent) => current = parent,
+                        None => break,
+                    }
+                }
+            }
+            Axis::Attribute => {
+                if let XNode::Node(handle) = node {
+                    if let Some(tag) = handle.get(self.parser()).and_then(Node::as_tag) {
+                        let count = tag.attributes().iter().count();
+                        out.extend((0..count).map(|index| XNode::Attribute(handle, index)));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Returns the name of a node, or an empty string if it has none
+    pub(crate) fn name(&self, node: XNode) -> String {
+        match node {
+            XNode::Root => String::new(),
+            XNode::Node(handle) => match handle.get(self.parser()) {
+                Some(Node::Tag(tag)) => tag.name().as_utf8_str().into_owned(),
+                _ => String::new(),
+            },
+            XNode::Attribute(handle, index) => self
+                .attribute(handle, index)
+                .map(|(name, _)| name)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn attribute(&self, handle: NodeHandle, index: usize) -> Option<(String, String)> {
+        let tag = handle.get(self.parser())?.as_tag()?;
+        let (name, value) = tag.attributes().iter().nth(index)?;
+        let value = value.map(|value| entities::decode(&value).into_owned());
+
+        Some((name.into_owned(), value.unwrap_or_default()))
+    }
+
+    fn matches(&self, axis: Axis, test: &NodeTest, node: XNode) -> bool {
+        let node_ref = match node {
+            XNode::Node(handle) => handle.get(self.parser()),
+            _ => None,
+        };
+
+        // the principal node type is attribute for the attribute axis and element otherwise
+        let is_principal = match node {
+            XNode::Attribute(..) => axis == Axis::Attribute,
+            _ => axis != Axis::Attribute && matches!(node_ref, Some(Node::Tag(_))),
+        };
+
+        match test {
+            NodeTest::Node => true,
+            NodeTest::Text => matches!(node_ref, Some(Node::Raw(_))),
+            NodeTest::Comment => matches!(node_ref, Some(Node::Comment(_))),
+            NodeTest::ProcessingInstruction => false,
+            NodeTest::Any => is_principal,
+            NodeTest::Prefix(prefix) => {
+                let name = self.name(node);
+                is_principal
+                    && name.len() > prefix.len()
+                    && name.as_bytes()[prefix.len()] == b':'
+                    && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+            }
+            NodeTest::Name(expected) => {
+                is_principal && self.name(node).eq_ignore_ascii_case(expected)
+            }
+        }
+    }
+
+    /// Returns the string-value of a node
+    pub(crate) fn string_value(&self, node: XNode) -> String {
+        let text = |out: &mut String, node: XNode| {
+            let mut nodes = Vec::new();
+            self.descendants(node, &mut nodes);
+
+            for node in nodes {
+                if let XNode::Node(handle) = node {
+                    if let Some(Node::Raw(raw)) = handle.get(self.parser()) {
+                        out.push_str(&entities::decode(&raw.as_utf8_str()));
+                    }
+                }
+            }
+        };
+
+        match node {
+            XNode::Root => {
+                let mut out = String::new();
+                text(&mut out, node);
+                out
+            }
+            XNode::Node(handle) => match handle.get(self.parser()) {
+                Some(Node::Tag(_)) => {
+                    let mut out = String::new();
+                    text(&mut out, node);
+                    out
+                }
+                Some(Node::Raw(raw)) => entities::decode(&raw.as_utf8_str()).into_owned(),
+                Some(Node::Comment(comment)) => {
+                    let comment = comment.as_utf8_str();
+                    let comment = comment.strip_prefix("<!--").unwrap_or(&comment);
+                    let comment = comment.strip_suffix("-->").unwrap_or(comment);
+                    comment.to_string()
+                }
+                None => String::new(),
+            },
+            XNode::Attribute(handle, index) => self
+                .attribute(handle, index)
+                .map(|(_, value)| value)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn to_string(&self, value: &Val) -> String {
+        match value {
+            Val::Nodes(nodes) => nodes
+                .first()
+                .map(|&node| self.string_value(node))
+                .unwrap_or_default(),
+            Val::Boolean(b) => b.to_string(),
+            Val::Number(n) => number_to_string(*n),
+            Val::String(s) => s.clone(),
+        }
+    }
+
+    fn to_number(&self, value: &Val) -> f64 {
+        match value {
+            Val::Boolean(b) => f64::from(u8::from(*b)),
+            Val::Number(n) => *n,
+            Val::String(s) => string_to_number(s),
+            Val::Nodes(_) => string_to_number(&self.to_string(value)),
+        }
+    }
+
+    fn eval(&self, expr: &Expr, context: Context) -> Result<Val, XPathError> {
+        Ok(match expr {
+            Expr::Literal(literal) => Val::String(literal.clone()),
+            Expr::Number(number) => Val::Number(*number),
+            Expr::Negate(expr) => Val::Number(-self.to_number(&self.eval(expr, context)?)),
+            Expr::Binary(Operator::Or, left, right) => Val::Boolean(
+                to_boolean(&self.eval(left, context)?) || to_boolean(&self.eval(right, context)?),
+            ),
+            Expr::Binary(Operator::And, left, right) => Val::Boolean(
+                to_boolean(&self.eval(left, context)?) && to_boolean(&self.eval(right, context)?),
+            ),
+            Expr::Binary(Operator::Union, left, right) => {
+                let (Val::Nodes(mut left), Val::Nodes(right)) =
+                    (self.eval(left, context)?, self.eval(right, context)?)
+                else {
+                    return Err(XPathError::NotANodeSet);
+                };
+
+                left.extend(right);
+                self.sort(&mut left);
+                Val::Nodes(left)
+            }
+            Expr::Binary(operator, left, right) => {
+                let left = self.eval(left, context)?;
+                let right = self.eval(right, context)?;
+
+                match operator {
+                    Operator::Add
+                    | Operator::Sub
+                    | Operator::Mul
+                    | Operator::Div
+                    | Operator::Mod => {
+                        let (l, r) = (self.to_number(&left), self.to_number(&right));
+
+                        Val::Number(match operator {
+                            Operator::Add => l + r,
+                            Operator::Sub => l - r,
+                            Operator::Mul => l * r,
+                            Operator::Div => l / r,
+                            _ => l % r,
+                        })
+                    }
+                    _ => Val::Boolean(self.compare(*operator, &left, &right)),
+                }
+            }
+            Expr::Call(function, args) => self.call(*function, args, context)?,
+            Expr::Path { absolute, steps } => {
+                let start = if *absolute { XNode::Root } else { context.node };
+                Val::Nodes(self.steps(vec![start], steps)?)
+            }
+            Expr::Filter {
+                primary,
+                predicates,
+                steps,
+            } => {
+                let Val::Nodes(mut nodes) = self.eval(primary, context)? else {
+                    return Err(XPathError::NotANodeSet);
+                };
+
+                for predicate in predicates {
+                    nodes = self.filter(nodes, predicate)?;
+                }
+
+                Val::Nodes(self.steps(nodes, steps)?)
+            }
+        })
+    }
+
+    /// Keeps the nodes for which the predicate is true, where the order of `nodes` determines their position
+    fn filter(&self, nodes: Vec<XNode>, predicate: &Expr) -> Result<Vec<XNode>, XPathError> {
+        let size = nodes.len();
+        let mut out = Vec::with_capacity(size);
+
+        for (index, node) in nodes.into_iter().enumerate() {
+            let context = Context {
+                node,
+                position: index + 1,
+                size,
+            };
+
+            let keep = match self.eval(predicate, context)? {
+                Val::Number(n) => n == context.position as f64,
+                value => to_boolean(&value),
+            };
+
+            if keep {
+                out.push(node);
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn steps(&self, mut nodes: Vec<XNode>, steps: &[Step]) -> Result<Vec<XNode>, XPathError> {
+        for step in steps {
+            let mut next = Vec::new();
+
+            for &node in &nodes {
+                let mut selected: Vec<XNode> = self
+                    .axis(step.axis, node)
+                    .into_iter()
+                    .filter(|&n| self.matches(step.axis, &step.test, n))
+                    .collect();
+
+                for predicate in &step.predicates {
+                    selected = self.filter(selected, predicate)?;
+                }
+
+                next.extend(selected);
+            }
+
+            self.sort(&mut next);
+            nodes = next;
+        }
+
+        Ok(nodes)
+    }
+
+    fn compare(&self, operator: Operator, left: &Val, right: &Val) -> bool {
+        match (left, right) {
+            (Val::Nodes(left), Val::Nodes(right)) => {
+                let right: Vec<String> = right.iter().map(|&n| self.string_value(n)).collect();
+                left.iter().any(|&l| {
+                    let l = self.string_value(l);
+                    right.iter().any(|r| {
+                        self.compare_atomic(
+                            operator,
+                            &Val::String(l.clone()),
+                            &Val::String(r.clone()),
+                        )
+                    })
+                })
+            }
+            (Val::Nodes(nodes), other) => self.compare_node_set(operator, nodes, other, false),
+            (other, Val::Nodes(nodes)) => self.compare_node_set(operator, nodes, other, true),
+            (left, right) => self.compare_atomic(operator, left, right),
+        }
+    }
+
+    /// Compares each node in a node-set with a value that is not a node-set
+    fn compare_node_set(
+        &self,
+        operator: Operator,
+        nodes: &[XNode],
+        other: &Val,
+        swapped: bool,
+    ) -> bool {
+        let compare = |value: Val| {
+            if swapped {
+                self.compare_atomic(operator, other, &value)
+            } else {
+                self.compare_atomic(operator, &value, other)
+            }
+        };
+
+        match other {
+            Val::Boolean(_) => compare(Val::Boolean(!nodes.is_empty())),
+            Val::Number(_) => nodes
+                .iter()
+                .any(|&node| compare(Val::Number(string_to_number(&self.string_value(node))))),
+            _ => nodes
+                .iter()
+                .any(|&node| compare(Val::String(self.string_value(node)))),
+        }
+    }
+
+    fn compare_atomic(&self, operator: Operator, left: &Val, right: &Val) -> bool {
+        match operator {
+            Operator::Eq | Operator::Ne => {
+                let equal = match (left, right) {
+                    (Val::Boolean(_), _) | (_, Val::Boolean(_)) => {
+                        to_boolean(left) == to_boolean(right)
+                    }
+                    (Val::Number(_), _) | (_, Val::Number(_)) => {
+                        self.to_number(left) == self.to_number(right)
+                    }
+                    _ => self.to_string(left) == self.to_string(right),
+                };
+
+                equal == (operator == Operator::Eq)
+            }
+            _ => {
+                let ordering = self.to_number(left).partial_cmp(&self.to_number(right));
+
+                match operator {
+                    Operator::Lt => ordering == Some(Ordering::Less),
+                    Operator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Operator::Gt => ordering == Some(Ordering::Greater),
+                    _ => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }
+        }
+    }
+
+    fn call(&self, function: Function, args: &[Expr], context: Context) -> Result<Val, XPathError> {
+        let arg = |index: usize| self.eval(&args[index], context);
+        let string_arg = |index: usize| -> Result<String, XPathError> {
+            match args.get(index) {
+                Some(expr) => Ok(self.to_string(&self.eval(expr, context)?)),
+                None => Ok(self.string_value(context.node)),
+            }
+        };
+        let node_arg = || -> Result<Option<XNode>, XPathError> {
+            match args.first() {
+                Some(expr) => match self.eval(expr, context)? {
+                    Val::Nodes(nodes) => Ok(nodes.first().copied()),
+                    _ => Err(XPathError::NotANodeSet),
+                },
+                None => Ok(Some(context.node)),
+            }
+        };
+
+        Ok(match function {
+            Function::Last => Val::Number(context.size as f64),
+            Function::Position => Val::Number(context.position as f64),
+            Function::Count => match arg(0)? {
+                Val::Nodes(nodes) => Val::Number(nodes.len() as f64),
+                _ => return Err(XPathError::NotANodeSet),
+            },
+            Function::Id => {
+                let ids = match arg(0)? {
+                    Val::Nodes(nodes) => nodes
+                        .iter()
+                        .map(|&node| self.string_value(node))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    value => self.to_string(&value),
+                };
+
+                let mut nodes: Vec<XNode> = self
+                    .dom
+                    .elements()
+                    .filter(|(handle, _)| {
+                        let id = handle
+                            .get(self.parser())
+                            .and_then(Node::as_tag)
+                            .and_then(|tag| tag.attributes().id());
+
+                        id.is_some_and(|id| ids.split_ascii_whitespace().any(|wanted| id == wanted))
+                    })
+                    .map(|(handle, _)| XNode::Node(handle))
+                    .collect();
+
+                self.sort(&mut nodes);
+                Val::Nodes(nodes)
+            }
+            Function::LocalName | Function::Name => {
+                let name = node_arg()?.map(|node| self.name(node)).unwrap_or_default();
+
+                if function == Function::LocalName {
+                    match name.split_once(':') {
+                        Some((_, local)) => Val::String(local.to_string()),
+                        None => Val::String(name),
+                    }
+                } else {
+                    Val::String(name)
+                }
+            }
+            Function::NamespaceUri => {
+                node_arg()?;
+                Val::String(String::new())
+            }
+            Function::String => Val::String(string_arg(0)?),
+            Function::Concat => {
+                let mut out = String::new();
+                for index in 0..args.len() {
+                    out.push_str(&string_arg(index)?);
+                }
+                Val::String(out)
+            }
+            Function::StartsWith => Val::Boolean(string_arg(0)?.starts_with(&string_arg(1)?)),
+            Function::Contains => Val::Boolean(string_arg(0)?.contains(&string_arg(1)?)),
+            Function::SubstringBefore => {
+                let (haystack, needle) = (string_arg(0)?, string_arg(1)?);
+                Val::String(
+                    haystack
+                        .split_once(needle.as_str())
+                        .map(|(before, _)| before.to_string())
+                        .unwrap_or_default(),
+                )
+            }
+            Function::SubstringAfter => {
+                let (haystack, needle) = (string_arg(0)?, string_arg(1)?);
+                Val::String(
+                    haystack
+                        .split_once(needle.as_str())
+                        .map(|(_, after)| after.to_string())
+                        .unwrap_or_default(),
+                )
+            }
+            Function::Substring => {
+                let s = string_arg(0)?;
+                let start = round(self.to_number(&arg(1)?));
+                let end = match args.get(2) {
+                    Some(_) => start + round(self.to_number(&arg(2)?)),
+                    None => f64::INFINITY,
+                };
+
+                // characters are numbered from 1 and included if start <= position < end
+                Val::String(
+                    s.chars()
+                        .enumerate()
+                        .filter(|&(index, _)| {
+                            let position = (index + 1) as f64;
+                            position >= start && position < end
+                        })
+                        .map(|(_, c)| c)
+                        .collect(),
+                )
+            }
+            Function::StringLength => Val::Number(string_arg(0)?.chars().count() as f64),
+            Function::NormalizeSpace => {
+                let s = string_arg(0)?;
+                Val::String(
+                    s.split([' ', '\t', '\r', '\n'])
+                        .filter(|part| !part.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                )
+            }
+            Function::Translate => {
+                let (s, from, to) = (string_arg(0)?, string_arg(1)?, string_arg(2)?);
+                let to: Vec<char> = to.chars().collect();
+
+                Val::String(
+                    s.chars()
+                        .filter_map(|c| match from.chars().position(|f| f == c) {
+                            Some(index) => to.get(index).copied(),
+                            None => Some(c),
+                        })
+                        .collect(),
+                )
+            }
+            Function::Boolean => Val::Boolean(to_boolean(&arg(0)?)),
+            Function::Not => Val::Boolean(!to_boolean(&arg(0)?)),
+            Function::True => Val::Boolean(true),
+            Function::False => Val::Boolean(false),
+            Function::Lang => {
+                let wanted = string_arg(0)?;
+                let lang = self
+                    .axis(Axis::AncestorOrSelf, context.node)
+                    .into_iter()
+                    .find_map(|node| {
+                        let XNode::Node(handle) = node else {
+                            return None;
+                        };
+
+                        let tag = handle.get(self.parser())?.as_tag()?;
+                        tag.attributes()
+                            .iter()
+                            .find(|(name, _)| {
+                                name.eq_ignore_ascii_case("lang")
+                                    || name.eq_ignore_ascii_case("xml:lang")
+                            })
+                            .map(|(_, value)| value.unwrap_or_default().into_owned())
+                    });
+
+                Val::Boolean(lang.is_some_and(|lang| {
+                    lang.eq_ignore_ascii_case(&wanted)
+                        || lang
+                            .split_once('-')
+                            .is_some_and(|(primary, _)| primary.eq_ignore_ascii_case(&wanted))
+                }))
+            }
+            Function::Number => match args.first() {
+                Some(_) => Val::Number(self.to_number(&arg(0)?)),
+                None => Val::Number(string_to_number(&self.string_value(context.node))),
+            },
+            Function::Sum => match arg(0)? {
+                Val::Nodes(nodes) => Val::Number(
+                    nodes
+                        .iter()
+                        .map(|&node| string_to_number(&self.string_value(node)))
+                        .sum(),
+                ),
+                _ => return Err(XPathError::NotANodeSet),
+            },
+            Function::Floor => Val::Number(self.to_number(&arg(0)?).floor()),
+            Function::Ceiling => Val::Number(self.to_number(&arg(0)?).ceil()),
+            Function::Round => Val::Number(round(self.to_number(&arg(0)?))),
+        })
+    }
+}
+
+/// Returns a value that orders nodes with the same sort key deterministically
+fn node_index(node: XNode) -> usize {
+    match node {
+        XNode::Root => 0,
+        XNode::Node(handle) | XNode::Attribute(handle, _) => handle.get_inner() as usize + 1,
+    }
+}
+
+pub(crate) fn to_boolean(value: &Val) -> bool {
+    match value {
+        Val::Nodes(nodes) => !nodes.is_empty(),
+        Val::Boolean(b) => *b,
+        Val::Number(n) => *n != 0.0 && !n.is_nan(),
+        Val::String(s) => !s.is_empty(),
+    }
+}
+
+/// Rounds to the closest integer, and towards positive infinity if there are two such integers
+fn round(n: f64) -> f64 {
+    if n.is_nan() || n.is_infinite() {
+        n
+    } else {
+        (n + 0.5).floor()
+    }
+}
+
+/// Converts a string to a number as defined by the `number()` function
+pub(crate) fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches([' ', '\t', '\r', '\n']);
+    let digits = s.strip_prefix('-').unwrap_or(s);
+
+    let is_valid = !digits.is_empty()
+        && digits != "."
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.chars().filter(|&c| c == '.').count() <= 1;
+
+    if is_valid {
+        s.parse().unwrap_or(f64::NAN)
+    } else {
+        f64::NAN
+    }
+}
+
+/// Converts a number to a string as defined by the `string()` function
+pub(crate) fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else if n == 0.0 {
+        "0".to_string()
+    } else {
+        n.to_string()
+    }
+}
@@ -0,0 +1,210 @@
+mod eval;
+mod parser;
+
+use crate::{errors::XPathError, NodeHandle, VDom};
+use eval::{Evaluator, Val, XNode};
+use parser::Expr;
+
+/// A compiled XPath 1.0 expression
+///
+/// Expressions are compiled once with [`XPath::compile()`] and can then be evaluated against any number of documents.
+/// All axes except the namespace axis are supported, as well as predicates and the complete core function library.
+///
+/// The document is treated like an HTML document in a browser: element and attribute names are matched
+/// case-insensitively and are never in a namespace. Entities in text and attribute values are decoded.
+/// Variables cannot be bound, so expressions that reference them fail to compile.
+///
+/// # Example
+/// ```
+/// # use tl::*;
+/// use tl::xpath::XPath;
+/// let input = r#"
+///     <table id="files">
+///         <tr><td><a href="/a.whl">a</a></td><td>whl</td></tr>
+///         <tr><td><a href="/b.tar.gz">b</a></td><td>sdist</td></tr>
+///     </table>"#;
+/// let dom = parse(input, ParserOptions::default()).unwrap();
+///
+/// let xpath = XPath::compile("//table[@id='files']//tr[td[2][contains(., 'whl')]]/td[1]/a/@href").unwrap();
+/// let hrefs: Vec<String> = xpath.evaluate(&dom).unwrap().strings(&dom);
+/// assert_eq!(hrefs, ["/a.whl"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct XPath {
+    expr: Expr,
+}
+
+impl XPath {
+    /// Compiles an XPath expression
+    ///
+    /// # Errors
+    /// Returns an error if the expression is not valid, calls an unknown function or uses an unsupported feature.
+    pub fn compile(expression: &str) -> Result<Self, XPathError> {
+        parser::parse(expression).map(|expr| Self { expr })
+    }
+
+    /// Evaluates this expression with the document as the context node
+    ///
+    /// # Errors
+    /// Returns an error if an operation requires a node-set, but the operand is a different type.
+    pub fn evaluate(&self, dom: &VDom<'_>) -> Result<Value, XPathError> {
+        self.evaluate_node(dom, XNode::Root)
+    }
+
+    /// Evaluates this expression with the given node as the context node
+    ///
+    /// Absolute location paths still start at the document.
+    ///
+    /// # Errors
+    /// See [`XPath::evaluate()`]
+    pub fn evaluate_from(&self, dom: &VDom<'_>, context: NodeHandle) -> Result<Value, XPathError> {
+        self.evaluate_node(dom, XNode::Node(context))
+    }
+
+    fn evaluate_node(&self, dom: &VDom<'_>, node: XNode) -> Result<Value, XPathError> {
+        let evaluator = Evaluator::new(dom);
+
+        Ok(match evaluator.evaluate(&self.expr, node)? {
+            Val::Nodes(nodes) => Value::NodeSet(
+                nodes
+                    .into_iter()
+                    .map(|node| match node {
+                        XNode::Root => Item::Root,
+                        XNode::Node(handle) => Item::Node(handle),
+                        XNode::Attribute(handle, _) => {
+                            let name = evaluator.name(node);
+                            let value = evaluator.string_value(node);
+
+                            Item::Attribute {
+                                element: handle,
+                                name,
+                                value,
+                            }
+                        }
+                    })
+                    .collect(),
+            ),
+            Val::Boolean(b) => Value::Boolean(b),
+            Val::Number(n) => Value::Number(n),
+            Val::String(s) => Value::String(s),
+        })
+    }
+}
+
+/// A node in a node-set
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// The document itself
+    Root,
+    /// A node of the document
+    Node(NodeHandle),
+    /// An attribute of an element
+    Attribute {
+        /// The element that the attribute belongs to
+        element: NodeHandle,
+        /// The name of the attribute
+        name: String,
+        /// The value of the attribute, with entities decoded
+        value: String,
+    },
+}
+
+impl Item {
+    /// Returns the handle of this node, if it is a node of the document
+    #[inline]
+    pub fn as_node(&self) -> Option<NodeHandle> {
+        match self {
+            Item::Node(handle) => Some(*handle),
+            _ => None,
+        }
+    }
+
+    /// Returns the string-value of this node
+    ///
+    /// This is the decoded text of all descendant text nodes for elements and the document,
+    /// and the value for attributes.
+    pub fn string_value(&self, dom: &VDom<'_>) -> String {
+        let evaluator = Evaluator::unindexed(dom);
+
+        match self {
+            Item::Root => evaluator.string_value(XNode::Root),
+            Item::Node(handle) => evaluator.string_value(XNode::Node(*handle)),
+            Item::Attribute { value, .. } => value.clone(),
+        }
+    }
+}
+
+/// The result of evaluating an XPath expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A set of nodes in document order
+    NodeSet(Vec<Item>),
+    /// A boolean
+    Boolean(bool),
+    /// A number
+    Number(f64),
+    /// A string
+    String(String),
+}
+
+impl Value {
+    /// Returns the nodes of this value, if it is a node-set
+    #[inline]
+    pub fn as_node_set(&self) -> Option<&[Item]> {
+        match self {
+            Value::NodeSet(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns the handles of all nodes of the document in this value
+    ///
+    /// Attributes and the document itself are skipped. Returns an empty vector if this value is not a node-set.
+    pub fn nodes(&self) -> Vec<NodeHandle> {
+        self.as_node_set()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Item::as_node)
+            .collect()
+    }
+
+    /// Returns the string-value of every node if this value is a node-set, or this value converted to a string otherwise
+    pub fn strings(&self, dom: &VDom<'_>) -> Vec<String> {
+        match self {
+            Value::NodeSet(items) => items.iter().map(|item| item.string_value(dom)).collect(),
+            value => vec![value.to_string(dom)],
+        }
+    }
+
+    /// Converts this value to a string like the `string()` function does
+    pub fn to_string(&self, dom: &VDom<'_>) -> String {
+        match self {
+            Value::NodeSet(items) => items
+                .first()
+                .map(|item| item.string_value(dom))
+                .unwrap_or_default(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => eval::number_to_string(*n),
+            Value::String(s) => s.clone(),
+        }
+    }
+
+    /// Converts this value to a number like the `number()` function does
+    pub fn to_number(&self, dom: &VDom<'_>) -> f64 {
+        match self {
+            Value::Boolean(b) => f64::from(u8::from(*b)),
+            Value::Number(n) => *n,
+            value => eval::string_to_number(&value.to_string(dom)),
+        }
+    }
+
+    /// Converts this value to a boolean like the `boolean()` function does
+    pub fn to_boolean(&self) -> bool {
+        match self {
+            Value::NodeSet(items) => !items.is_empty(),
+            Value::Boolean(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::String(s) => !s.is_empty(),
+        }
+    }
+}
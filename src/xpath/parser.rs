@@ -0,0 +1,778 @@
+use crate::errors::XPathError;
+
+/// A location step axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    Itself,
+}
+
+impl Axis {
+    fn from_name(name: &str) -> Result<Self, XPathError> {
+        Ok(match name {
+            "ancestor" => Axis::Ancestor,
+            "ancestor-or-self" => Axis::AncestorOrSelf,
+            "attribute" => Axis::Attribute,
+            "child" => Axis::Child,
+            "descendant" => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "following" => Axis::Following,
+            "following-sibling" => Axis::FollowingSibling,
+            "parent" => Axis::Parent,
+            "preceding" => Axis::Preceding,
+            "preceding-sibling" => Axis::PrecedingSibling,
+            "self" => Axis::Itself,
+            "namespace" => return Err(XPathError::UnsupportedAxis),
+            _ => return Err(XPathError::Syntax),
+        })
+    }
+}
+
+/// A test that nodes on an axis need to pass to be selected by a step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NodeTest {
+    /// `*`, any node of the principal node type of the axis
+    Any,
+    /// `prefix:*`
+    Prefix(String),
+    /// A qualified name
+    Name(String),
+    /// `node()`
+    Node,
+    /// `text()`
+    Text,
+    /// `comment()`
+    Comment,
+    /// `processing-instruction()`, which never matches in HTML
+    ProcessingInstruction,
+}
+
+/// A single step in a location path
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    pub(crate) axis: Axis,
+    pub(crate) test: NodeTest,
+    pub(crate) predicates: Vec<Expr>,
+}
+
+impl Step {
+    fn new(axis: Axis, test: NodeTest) -> Self {
+        Self {
+            axis,
+            test,
+            predicates: Vec::new(),
+        }
+    }
+}
+
+/// A built-in function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Function {
+    Last,
+    Position,
+    Count,
+    Id,
+    LocalName,
+    NamespaceUri,
+    Name,
+    String,
+    Concat,
+    StartsWith,
+    Contains,
+    SubstringBefore,
+    SubstringAfter,
+    Substring,
+    StringLength,
+    NormalizeSpace,
+    Translate,
+    Boolean,
+    Not,
+    True,
+    False,
+    Lang,
+    Number,
+    Sum,
+    Floor,
+    Ceiling,
+    Round,
+}
+
+impl Function {
+    /// Looks up a function by name, returning it together with the minimum and maximum number of arguments
+    fn from_name(name: &str) -> Option<(Self, usize, usize)> {
+        Some(match name {
+            "last" => (Function::Last, 0, 0),
+            "position" => (Function::Position, 0, 0),
+            "count" => (Function::Count, 1, 1),
+            "id" => (Function::Id, 1, 1),
+            "local-name" => (Function::LocalName, 0, 1),
+            "namespace-uri" => (Function::NamespaceUri, 0, 1),
+            "name" => (Function::Name, 0, 1),
+            "string" => (Function::String, 0, 1),
+            "concat" => (Function::Concat, 2, usize::MAX),
+            "starts-with" => (Function::StartsWith, 2, 2),
+            "contains" => (Function::Contains, 2, 2),
+            "substring-before" => (Function::SubstringBefore, 2, 2),
+            "substring-after" => (Function::SubstringAfter, 2, 2),
+            "substring" => (Function::Substring, 2, 3),
+            "string-length" => (Function::StringLength, 0, 1),
+            "normalize-space" => (Function::NormalizeSpace, 0, 1),
+            "translate" => (Function::Translate, 3, 3),
+            "boolean" => (Function::Boolean, 1, 1),
+            "not" => (Function::Not, 1, 1),
+            "true" => (Function::True, 0, 0),
+            "false" => (Function::False, 0, 0),
+            "lang" => (Function::Lang, 1, 1),
+            "number" => (Function::Number, 0, 1),
+            "sum" => (Function::Sum, 1, 1),
+            "floor" => (Function::Floor, 1, 1),
+            "ceiling" => (Function::Ceiling, 1, 1),
+            "round" => (Function::Round, 1, 1),
+            _ => return None,
+        })
+    }
+}
+
+/// A binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operator {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Union,
+}
+
+/// A compiled XPath expression
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Call(Function, Vec<Expr>),
+    /// A location path, relative to the root node if `absolute` is set and to the context node otherwise
+    Path {
+        absolute: bool,
+        steps: Vec<Step>,
+    },
+    /// A filter expression, optionally followed by a relative location path
+    Filter {
+        primary: Box<Expr>,
+        predicates: Vec<Expr>,
+        steps: Vec<Step>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    ColonColon,
+    Slash,
+    DoubleSlash,
+    Pipe,
+    Plus,
+    Minus,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `*`, `and`, `or`, `mod` or `div` in operator position
+    Operator(Operator),
+    NameTest(NodeTest),
+    NodeType(String),
+    FunctionName(String),
+    AxisName(String),
+    Literal(String),
+    Number(f64),
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Splits an expression into tokens, following the disambiguation rules of the XPath specification
+fn tokenize(input: &str) -> Result<Vec<Token>, XPathError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        // `*` and names are operators unless they follow a token that starts an operand
+        let operator_position = tokens.last().is_some_and(|token| {
+            !matches!(
+                token,
+                Token::At
+                    | Token::ColonColon
+                    | Token::LParen
+                    | Token::LBracket
+                    | Token::Comma
+                    | Token::Operator(_)
+                    | Token::Slash
+                    | Token::DoubleSlash
+                    | Token::Pipe
+                    | Token::Plus
+                    | Token::Minus
+                    | Token::Eq
+                    | Token::Ne
+                    | Token::Lt
+                    | Token::Le
+                    | Token::Gt
+                    | Token::Ge
+            )
+        });
+
+        let rest = &input[start..];
+        let mut advance = |n: usize| {
+            for _ in 0..n {
+                chars.next();
+            }
+        };
+
+        let token = match c {
+            '(' => {
+                advance(1);
+                Token::LParen
+            }
+            ')' => {
+                advance(1);
+                Token::RParen
+            }
+            '[' => {
+                advance(1);
+                Token::LBracket
+            }
+            ']' => {
+                advance(1);
+                Token::RBracket
+            }
+            '@' => {
+                advance(1);
+                Token::At
+            }
+            ',' => {
+                advance(1);
+                Token::Comma
+            }
+            '|' => {
+                advance(1);
+                Token::Pipe
+            }
+            '+' => {
+                advance(1);
+                Token::Plus
+            }
+            '-' => {
+                advance(1);
+                Token::Minus
+            }
+            '=' => {
+                advance(1);
+                Token::Eq
+            }
+            '$' => return Err(XPathError::UnboundVariable),
+            ':' if rest.starts_with("::") => {
+                advance(2);
+                Token::ColonColon
+            }
+            '/' if rest.starts_with("//") => {
+                advance(2);
+                Token::DoubleSlash
+            }
+            '/' => {
+                advance(1);
+                Token::Slash
+            }
+            '!' if rest.starts_with("!=") => {
+                advance(2);
+                Token::Ne
+            }
+            '<' if rest.starts_with("<=") => {
+                advance(2);
+                Token::Le
+            }
+            '<' => {
+                advance(1);
+                Token::Lt
+            }
+            '>' if rest.starts_with(">=") => {
+                advance(2);
+                Token::Ge
+            }
+            '>' => {
+                advance(1);
+                Token::Gt
+            }
+            '*' if operator_position => {
+                advance(1);
+                Token::Operator(Operator::Mul)
+            }
+            '*' => {
+                advance(1);
+                Token::NameTest(NodeTest::Any)
+            }
+            '"' | '\'' => {
+                let end = rest[1..].find(c).ok_or(XPathError::Syntax)?;
+                advance(rest[..end + 2].chars().count());
+                Token::Literal(rest[1..end + 1].to_string())
+            }
+            '.' if rest.starts_with("..") => {
+                advance(2);
+                Token::DotDot
+            }
+            '0'..='9' | '.' => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit() && c != '.')
+                    .unwrap_or(rest.len());
+
+                if len == 1 && c == '.' {
+                    advance(1);
+                    Token::Dot
+                } else {
+                    let number = rest[..len].parse().map_err(|_| XPathError::Syntax)?;
+                    advance(len);
+                    Token::Number(number)
+                }
+            }
+            c if is_name_start(c) => {
+                let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                let mut name = &rest[..len];
+                advance(name.chars().count());
+
+                if operator_position {
+                    let operator = match name {
+                        "and" => Operator::And,
+                        "or" => Operator::Or,
+                        "mod" => Operator::Mod,
+                        "div" => Operator::Div,
+                        _ => return Err(XPathError::Syntax),
+                    };
+
+                    tokens.push(Token::Operator(operator));
+                    continue;
+                }
+
+                let after = &input[start + len..];
+                let mut prefix_wildcard = false;
+
+                // qualified names and `prefix:*`
+                if after.starts_with(':') && !after.starts_with("::") {
+                    let local = &after[1..];
+
+                    if local.starts_with('*') {
+                        prefix_wildcard = true;
+                        advance(2);
+                    } else if local.starts_with(is_name_start) {
+                        let local_len = local.find(|c| !is_name_char(c)).unwrap_or(local.len());
+                        name = &rest[..len + 1 + local_len];
+                        advance(1 + local[..local_len].chars().count());
+                    }
+                }
+
+                let following = input[start + name.len()..].trim_start();
+
+                if prefix_wildcard {
+                    Token::NameTest(NodeTest::Prefix(name.to_string()))
+                } else if following.starts_with("::") {
+                    Token::AxisName(name.to_string())
+                } else if following.starts_with('(') {
+                    match name {
+                        "node" | "text" | "comment" | "processing-instruction" => {
+                            Token::NodeType(name.to_string())
+                        }
+                        _ => Token::FunctionName(name.to_string()),
+                    }
+                } else {
+                    Token::NameTest(NodeTest::Name(name.to_string()))
+                }
+            }
+            _ => return Err(XPathError::Syntax),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// The maximum nesting depth of an expression
+///
+/// Expressions are parsed and evaluated recursively, so deeper expressions are rejected instead of overflowing the stack.
+/// Every parenthesized expression, predicate, function argument, negation and binary operator adds a level.
+const MAX_DEPTH: usize = 128;
+
+/// A recursive descent parser for XPath expressions
+struct Parser {
+    tokens: Vec<Token>,
+    idx: usize,
+    /// The current nesting depth, see [`MAX_DEPTH`]
+    depth: usize,
+}
+
+impl Parser {
+    /// Adds a level of nesting, failing if the expression is nested too deeply
+    fn enter(&mut self) -> Result<(), XPathError> {
+        if self.depth == MAX_DEPTH {
+            return Err(XPathError::NestingTooDeep);
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Parses a nested expression
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Expr, XPathError>,
+    ) -> Result<Expr, XPathError> {
+        self.enter()?;
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self.peek() == Some(token);
+        if matches {
+            self.idx += 1;
+        }
+        matches
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), XPathError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(XPathError::Syntax)
+        }
+    }
+
+    /// Parses a chain of left-associative binary operators
+    fn binary(
+        &mut self,
+        operators: &[(Token, Operator)],
+        operand: fn(&mut Self) -> Result<Expr, XPathError>,
+    ) -> Result<Expr, XPathError> {
+        let depth = self.depth;
+        let mut left = operand(self)?;
+
+        'outer: loop {
+            for (token, operator) in operators {
+                if self.eat(token) {
+                    // the operators are left-associative, so every operator nests the left operand
+                    self.enter()?;
+                    let right = operand(self)?;
+                    left = Expr::Binary(*operator, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+
+            self.depth = depth;
+            return Ok(left);
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, XPathError> {
+        self.binary(&[(Token::Operator(Operator::Or), Operator::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, XPathError> {
+        self.binary(
+            &[(Token::Operator(Operator::And), Operator::And)],
+            Self::equality,
+        )
+    }
+
+    fn equality(&mut self) -> Result<Expr, XPathError> {
+        self.binary(
+            &[(Token::Eq, Operator::Eq), (Token::Ne, Operator::Ne)],
+            Self::relational,
+        )
+    }
+
+    fn relational(&mut self) -> Result<Expr, XPathError> {
+        self.binary(
+            &[
+                (Token::Lt, Operator::Lt),
+                (Token::Le, Operator::Le),
+                (Token::Gt, Operator::Gt),
+                (Token::Ge, Operator::Ge),
+            ],
+            Self::additive,
+        )
+    }
+
+    fn additive(&mut self) -> Result<Expr, XPathError> {
+        self.binary(
+            &[(Token::Plus, Operator::Add), (Token::Minus, Operator::Sub)],
+            Self::multiplicative,
+        )
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, XPathError> {
+        self.binary(
+            &[
+                (Token::Operator(Operator::Mul), Operator::Mul),
+                (Token::Operator(Operator::Div), Operator::Div),
+                (Token::Operator(Operator::Mod), Operator::Mod),
+            ],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, XPathError> {
+        if self.eat(&Token::Minus) {
+            Ok(Expr::Negate(Box::new(self.nested(Self::unary)?)))
+        } else {
+            self.binary(&[(Token::Pipe, Operator::Union)], Self::path)
+        }
+    }
+
+    fn path(&mut self) -> Result<Expr, XPathError> {
+        let is_filter = matches!(
+            self.peek(),
+            Some(Token::LParen | Token::Literal(_) | Token::Number(_) | Token::FunctionName(_))
+        );
+
+        if !is_filter {
+            return self.location_path();
+        }
+
+        let primary = self.primary()?;
+        let predicates = self.predicates()?;
+
+        let steps = if self.eat(&Token::Slash) {
+            self.relative_path()?
+        } else if self.eat(&Token::DoubleSlash) {
+            let mut steps = vec![Step::new(Axis::DescendantOrSelf, NodeTest::Node)];
+            steps.extend(self.relative_path()?);
+            steps
+        } else {
+            Vec::new()
+        };
+
+        if predicates.is_empty() && steps.is_empty() {
+            return Ok(primary);
+        }
+
+        Ok(Expr::Filter {
+            primary: Box::new(primary),
+            predicates,
+            steps,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, XPathError> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.nested(Self::expr)?;
+                self.expect(&Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Literal(literal)) => Ok(Expr::Literal(literal)),
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::FunctionName(name)) => {
+                let (function, min, max) =
+                    Function::from_name(&name).ok_or(XPathError::UnknownFunction)?;
+
+                self.expect(&Token::LParen)?;
+
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.nested(Self::expr)?);
+
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+
+                        self.expect(&Token::Comma)?;
+                    }
+                }
+
+                if args.len() < min || args.len() > max {
+                    return Err(XPathError::InvalidArguments);
+                }
+
+                Ok(Expr::Call(function, args))
+            }
+            _ => Err(XPathError::Syntax),
+        }
+    }
+
+    fn predicates(&mut self) -> Result<Vec<Expr>, XPathError> {
+        let mut predicates = Vec::new();
+
+        while self.eat(&Token::LBracket) {
+            predicates.push(self.nested(Self::expr)?);
+            self.expect(&Token::RBracket)?;
+        }
+
+        Ok(predicates)
+    }
+
+    fn location_path(&mut self) -> Result<Expr, XPathError> {
+        let (absolute, mut steps) = if self.eat(&Token::Slash) {
+            (true, Vec::new())
+        } else if self.eat(&Token::DoubleSlash) {
+            (
+                true,
+                vec![Step::new(Axis::DescendantOrSelf, NodeTest::Node)],
+            )
+        } else {
+            (false, Vec::new())
+        };
+
+        let has_step = matches!(
+            self.peek(),
+            Some(
+                Token::Dot
+                    | Token::DotDot
+                    | Token::At
+                    | Token::AxisName(_)
+                    | Token::NameTest(_)
+                    | Token::NodeType(_)
+            )
+        );
+
+        if has_step {
+            steps.extend(self.relative_path()?);
+        } else if !absolute || !steps.is_empty() {
+            // only a lone `/` may omit the relative location path
+            return Err(XPathError::Syntax);
+        }
+
+        Ok(Expr::Path { absolute, steps })
+    }
+
+    fn relative_path(&mut self) -> Result<Vec<Step>, XPathError> {
+        let mut steps = vec![self.step()?];
+
+        loop {
+            if self.eat(&Token::Slash) {
+                steps.push(self.step()?);
+            } else if self.eat(&Token::DoubleSlash) {
+                steps.push(Step::new(Axis::DescendantOrSelf, NodeTest::Node));
+                steps.push(self.step()?);
+            } else {
+                return Ok(steps);
+            }
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, XPathError> {
+        if self.eat(&Token::Dot) {
+            return Ok(Step::new(Axis::Itself, NodeTest::Node));
+        }
+
+        if self.eat(&Token::DotDot) {
+            return Ok(Step::new(Axis::Parent, NodeTest::Node));
+        }
+
+        let axis = match self.peek() {
+            Some(Token::At) => {
+                self.idx += 1;
+                Axis::Attribute
+            }
+            Some(Token::AxisName(name)) => {
+                let axis = Axis::from_name(name)?;
+                self.idx += 1;
+                self.expect(&Token::ColonColon)?;
+                axis
+            }
+            _ => Axis::Child,
+        };
+
+        let test = match self.next() {
+            Some(Token::NameTest(test)) => test,
+            Some(Token::NodeType(name)) => {
+                self.expect(&Token::LParen)?;
+
+                if name == "processing-instruction"
+                    && matches!(self.peek(), Some(Token::Literal(_)))
+                {
+                    self.idx += 1;
+                }
+
+                self.expect(&Token::RParen)?;
+
+                match name.as_str() {
+                    "node" => NodeTest::Node,
+                    "text" => NodeTest::Text,
+                    "comment" => NodeTest::Comment,
+                    _ => NodeTest::ProcessingInstruction,
+                }
+            }
+            _ => return Err(XPathError::Syntax),
+        };
+
+        Ok(Step {
+            axis,
+            test,
+            predicates: self.predicates()?,
+        })
+    }
+}
+
+/// Parses an XPath expression
+pub(crate) fn parse(input: &str) -> Result<Expr, XPathError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        idx: 0,
+        depth: 0,
+    };
+
+    let expr = parser.expr()?;
+
+    if parser.idx != parser.tokens.len() {
+        return Err(XPathError::Syntax);
+    }
+
+    Ok(expr)
+}